@@ -0,0 +1,309 @@
+use anyhow::{anyhow, bail, Context, Result};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::H160,
+};
+use hyperliquid_rust_sdk::{ExchangeClient, ExchangeResponseStatus};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::time;
+
+use crate::{
+    notifier::{Notifier, NotifyEvent},
+    state::now_ms,
+};
+
+// 未配置 AGENT_DIR 时 agent 文件的存放目录
+pub const DEFAULT_AGENT_DIR: &str = "agents";
+
+/// 本地保存的 agent 信息，私钥以加密 keystore 的形式单独存放在同目录下
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRecord {
+    pub name: String,
+    pub address: H160,
+    /// 授权该 agent 的主账户
+    pub master: H160,
+    pub created_at: u64,
+    /// 过期时间（毫秒），`None` 表示不过期
+    pub valid_until: Option<u64>,
+}
+
+impl AgentRecord {
+    pub fn is_expired(&self) -> bool {
+        self.valid_until.is_some_and(|until| until <= now_ms())
+    }
+}
+
+/// agent 私钥的本地存储：`<name>.json` 记录元数据，`<name>.key` 为用密码加密的 keystore
+#[derive(Clone)]
+pub struct AgentStore {
+    dir: PathBuf,
+    password: String,
+}
+
+// 不输出 keystore 密码
+impl fmt::Debug for AgentStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentStore")
+            .field("dir", &self.dir)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+impl AgentStore {
+    pub fn new(dir: impl Into<PathBuf>, password: String) -> Self {
+        AgentStore {
+            dir: dir.into(),
+            password,
+        }
+    }
+
+    /// 目录取 AGENT_DIR，密码取 AGENT_PASSWORD
+    pub fn from_env() -> Result<Self> {
+        let dir = env::var("AGENT_DIR").unwrap_or_else(|_| DEFAULT_AGENT_DIR.to_string());
+        let password = env::var("AGENT_PASSWORD").context("未配置 AGENT_PASSWORD")?;
+        Ok(AgentStore::new(dir, password))
+    }
+
+    // 名称用作文件名，不能包含路径分隔符或 `..` 跳出 agent 目录
+    fn path(&self, name: &str, extension: &str) -> Result<PathBuf> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+            bail!("无效的 agent 名称: {name}");
+        }
+        Ok(self.dir.join(format!("{name}.{extension}")))
+    }
+
+    fn record_path(&self, name: &str) -> Result<PathBuf> {
+        self.path(name, "json")
+    }
+
+    fn key_path(&self, name: &str) -> Result<PathBuf> {
+        self.path(name, "key")
+    }
+
+    pub fn record(&self, name: &str) -> Result<AgentRecord> {
+        let path = self.record_path(name)?;
+        let data = fs::read_to_string(&path).with_context(|| format!("读取 {path:?} 失败"))?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    /// 本地保存的全部 agent，按名称排序
+    pub fn records(&self) -> Result<Vec<AgentRecord>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut records = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                records.push(serde_json::from_str(&fs::read_to_string(&path)?)?);
+            }
+        }
+        records.sort_by(|a: &AgentRecord, b| a.name.cmp(&b.name));
+        Ok(records)
+    }
+
+    /// 解密 agent 私钥，已过期的 agent 直接报错
+    pub fn load(&self, name: &str) -> Result<(AgentRecord, LocalWallet)> {
+        let record = self.record(name)?;
+        if record.is_expired() {
+            bail!("agent {name} 已过期，请先 rotate");
+        }
+        let wallet = LocalWallet::decrypt_keystore(self.key_path(name)?, &self.password)
+            .map_err(|e| anyhow!("解密 agent {name} 失败: {e}"))?;
+        if wallet.address() != record.address {
+            bail!("agent {name} 的私钥与记录的地址不一致");
+        }
+        Ok((record, wallet))
+    }
+
+    /// 生成新的 agent 并用主账户授权，`exchange_client` 必须使用主账户私钥
+    pub async fn create(
+        &self,
+        exchange_client: &ExchangeClient,
+        name: &str,
+        valid_for: Option<Duration>,
+    ) -> Result<AgentRecord> {
+        if self.record_path(name)?.exists() {
+            bail!("agent {name} 已存在");
+        }
+        self.approve(exchange_client, name, valid_for).await
+    }
+
+    /// 用新私钥替换同名 agent，交易所会同时撤销旧 agent 的授权
+    pub async fn rotate(
+        &self,
+        exchange_client: &ExchangeClient,
+        name: &str,
+        valid_for: Option<Duration>,
+    ) -> Result<AgentRecord> {
+        let old = self.record(name)?;
+        if old.master != exchange_client.signer.address() {
+            bail!("agent {name} 由 {:?} 授权，不能用其他账户轮换", old.master);
+        }
+        self.approve(exchange_client, name, valid_for).await
+    }
+
+    async fn approve(
+        &self,
+        exchange_client: &ExchangeClient,
+        name: &str,
+        valid_for: Option<Duration>,
+    ) -> Result<AgentRecord> {
+        // 名称会用作文件名
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("agent 名称只能包含字母、数字、- 和 _: {name}");
+        }
+        fs::create_dir_all(&self.dir)?;
+
+        // 新私钥先写到临时文件，授权成功后再替换旧文件，失败时旧 agent 仍可用
+        let pending_name = format!("{name}.key.pending");
+        let pending_path = self.dir.join(&pending_name);
+        let (wallet, _) = LocalWallet::new_keystore(
+            &self.dir,
+            &mut rand::thread_rng(),
+            &self.password,
+            Some(&pending_name),
+        )
+        .map_err(|e| anyhow!("生成 agent 私钥失败: {e}"))?;
+
+        let created_at = now_ms();
+        let valid_until = valid_for.map(|valid_for| created_at + valid_for.as_millis() as u64);
+        let response = exchange_client
+            .approve_named_agent(wallet.address(), name, valid_until, None)
+            .await;
+        let error = match response {
+            Ok(ExchangeResponseStatus::Ok(_)) => None,
+            Ok(ExchangeResponseStatus::Err(e)) => Some(e),
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(error) = error {
+            let _ = fs::remove_file(&pending_path);
+            bail!("授权 agent {name} 失败: {error}");
+        }
+
+        let record = AgentRecord {
+            name: name.to_string(),
+            address: wallet.address(),
+            master: exchange_client.signer.address(),
+            created_at,
+            valid_until,
+        };
+        fs::rename(&pending_path, self.key_path(name)?)?;
+        write_atomic(
+            &self.record_path(name)?,
+            &serde_json::to_string_pretty(&record)?,
+        )?;
+        info!("agent {name} 已授权: {:?}", record.address);
+        Ok(record)
+    }
+}
+
+fn write_atomic(path: &Path, data: &str) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data)?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// 在 agent 过期前 `warn_before` 提醒轮换，过期后再提醒一次；不过期的 agent 直接返回
+pub async fn run_agent_expiry_watch(
+    record: AgentRecord,
+    warn_before: Duration,
+    notifier: Notifier,
+) {
+    let Some(valid_until) = record.valid_until else {
+        return;
+    };
+    let warn_at = valid_until.saturating_sub(warn_before.as_millis() as u64);
+    time::sleep(Duration::from_millis(warn_at.saturating_sub(now_ms()))).await;
+    warn!("agent {} 即将过期", record.name);
+    notifier.notify(NotifyEvent::Error {
+        context: "agent".to_string(),
+        error: format!(
+            "agent {} 将于 {valid_until} 过期，请执行 agent rotate {} 后重启",
+            record.name, record.name
+        ),
+    });
+
+    time::sleep(Duration::from_millis(valid_until.saturating_sub(now_ms()))).await;
+    notifier.notify(NotifyEvent::Error {
+        context: "agent".to_string(),
+        error: format!("agent {} 已过期，下单将被拒绝", record.name),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyperliquid_rust_sdk::{ClientCancelRequest, MockHyperliquid};
+
+    #[tokio::test]
+    async fn test_agent_create_rotate_and_list() -> Result<()> {
+        let (_mock, master_client) = MockHyperliquid::exchange_client().await?;
+        let master = master_client.signer.address();
+
+        let dir = env::temp_dir().join(format!("agents-{}", std::process::id()));
+        let store = AgentStore::new(&dir, "test-password".to_string());
+        let created = store
+            .create(&master_client, "bot", Some(Duration::from_secs(3600)))
+            .await?;
+        assert!(store.create(&master_client, "bot", None).await.is_err());
+
+        // 只用 agent 私钥签名，请求以主账户身份通过校验
+        let (record, agent) = store.load("bot")?;
+        assert_eq!(record.address, created.address);
+        let agent_client =
+            ExchangeClient::with_info(None, agent, master_client.info().clone(), None, None)
+                .await?;
+        let cancel = || ClientCancelRequest {
+            asset: "ETH".to_string(),
+            oid: 1,
+        };
+        assert!(matches!(
+            agent_client.cancel(cancel(), None).await?,
+            ExchangeResponseStatus::Ok(_)
+        ));
+
+        let rotated = store.rotate(&master_client, "bot", None).await?;
+        assert_ne!(rotated.address, created.address);
+        assert!(matches!(
+            agent_client.cancel(cancel(), None).await?,
+            ExchangeResponseStatus::Err(_)
+        ));
+
+        let approved = master_client.info().extra_agents(master).await?;
+        assert_eq!(approved.len(), 1);
+        assert_eq!(
+            (approved[0].name.as_str(), approved[0].address),
+            ("bot", rotated.address)
+        );
+        assert_eq!(store.records()?.len(), 1);
+        assert_eq!(store.load("bot")?.1.address(), rotated.address);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_rejects_paths_and_hides_password() {
+        let store = AgentStore::new("agents", "secret-password".to_string());
+        for name in ["../bot", "a/b", "a\\b", ".."] {
+            assert!(store.record_path(name).is_err());
+            assert!(store.key_path(name).is_err());
+        }
+        assert!(store.record_path("bot").is_ok());
+        assert!(!format!("{store:?}").contains("secret-password"));
+    }
+}
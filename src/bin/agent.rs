@@ -0,0 +1,82 @@
+//! agent 管理命令，需要主账户 PRIVATE_KEY 和 AGENT_PASSWORD：
+//!
+//! ```text
+//! cargo run --bin agent -- create <name> [有效天数]
+//! cargo run --bin agent -- rotate <name> [有效天数]
+//! cargo run --bin agent -- list
+//! ```
+//!
+//! 守护进程配置 AGENT_NAME 后只加载该 agent 的私钥，rotate 之后需要重启守护进程。
+use std::{env, process, time::Duration};
+
+use anyhow::{bail, Result};
+use dotenv::dotenv;
+use ethers::signers::{LocalWallet, Signer};
+use hype_copy_trade::{agent::AgentStore, utils::network_from_env};
+use hyperliquid_rust_sdk::{ExchangeClient, InfoClient};
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    env_logger::init();
+    if let Err(e) = run(env::args().skip(1).collect()).await {
+        eprintln!("{e:#}");
+        process::exit(1);
+    }
+}
+
+async fn run(args: Vec<String>) -> Result<()> {
+    let is_test = env::var("TEST")?.parse::<bool>()?;
+    let network = network_from_env(is_test);
+    let master: LocalWallet = env::var("PRIVATE_KEY")?.parse()?;
+    let store = AgentStore::from_env()?;
+    let valid_for = |days: Option<&String>| -> Result<Option<Duration>> {
+        Ok(days
+            .map(|days| days.parse::<u64>())
+            .transpose()?
+            .map(|days| Duration::from_secs(days * 24 * 60 * 60)))
+    };
+
+    match args.first().map(String::as_str) {
+        Some("create") | Some("rotate") if args.len() >= 2 => {
+            let exchange_client =
+                ExchangeClient::new(None, master, Some(network), None, None).await?;
+            let valid_for = valid_for(args.get(2))?;
+            let record = if args[0] == "create" {
+                store.create(&exchange_client, &args[1], valid_for).await?
+            } else {
+                store.rotate(&exchange_client, &args[1], valid_for).await?
+            };
+            println!("{}", serde_json::to_string_pretty(&record)?);
+        }
+        Some("list") => {
+            let info_client = InfoClient::new(None, Some(network)).await?;
+            let approved = info_client.extra_agents(master.address()).await?;
+            for record in store.records()? {
+                let on_chain = approved
+                    .iter()
+                    .any(|agent| agent.name == record.name && agent.address == record.address);
+                println!(
+                    "{}\t{:?}\tvalid_until={}\texpired={}\tapproved={on_chain}",
+                    record.name,
+                    record.address,
+                    record
+                        .valid_until
+                        .map_or("-".to_string(), |until| until.to_string()),
+                    record.is_expired(),
+                );
+            }
+            // 交易所上存在但本地没有私钥的 agent
+            for agent in approved {
+                if store.record(&agent.name).is_err() {
+                    println!(
+                        "{}\t{:?}\tvalid_until={}\t(本地无私钥)",
+                        agent.name, agent.address, agent.valid_until
+                    );
+                }
+            }
+        }
+        _ => bail!("用法: agent create <name> [days] | agent rotate <name> [days] | agent list"),
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! 主账户私钥的加密保存与远程签名服务：
+//!
+//! ```text
+//! cargo run --bin signer -- import <目录> <文件名>          # 把 PRIVATE_KEY 加密成 keystore
+//! cargo run --bin signer -- serve <keystore> <socket 路径>   # 通过 Unix socket 提供签名
+//! SIGNER_TOKEN=... cargo run --bin signer -- serve <keystore> http://127.0.0.1:8788
+//! ```
+//!
+//! 交易进程配置 REMOTE_SIGNER 指向该服务后，内存中只有签名服务的地址，没有私钥。
+//! socket 所在目录需只允许当前用户访问；HTTP 方式两端都需配置相同的 SIGNER_TOKEN。
+use std::{env, path::Path, process, sync::Arc};
+
+use anyhow::{anyhow, bail, Result};
+use dotenv::dotenv;
+use hype_copy_trade::signer::{bind_unix_signer, http_signer, open_keystore};
+use hyperliquid_rust_sdk::{serve_unix_signer, KeystoreSigner, Signer};
+use log::info;
+use tokio::net::TcpListener;
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    env_logger::init();
+    if let Err(e) = run(env::args().skip(1).collect()).await {
+        eprintln!("{e:#}");
+        process::exit(1);
+    }
+}
+
+async fn run(args: Vec<String>) -> Result<()> {
+    match (args.first().map(String::as_str), args.get(1), args.get(2)) {
+        (Some("import"), Some(dir), Some(name)) => {
+            let private_key = env::var("PRIVATE_KEY")?;
+            let password = rpassword::prompt_password("keystore 密码: ")?;
+            if password != rpassword::prompt_password("再次输入密码: ")? {
+                bail!("两次输入的密码不一致");
+            }
+            let signer = KeystoreSigner::import(dir, name, &private_key, password)?;
+            println!("已保存 {dir}/{name}，地址 {:?}", signer.address());
+            println!("请从 .env 中删除 PRIVATE_KEY");
+        }
+        (Some("serve"), Some(keystore), Some(endpoint)) => {
+            let signer: Arc<dyn Signer> = Arc::new(open_keystore(keystore)?);
+            info!("签名地址 {:?}", signer.address());
+            match endpoint.strip_prefix("http://") {
+                Some(addr) => {
+                    let token = env::var("SIGNER_TOKEN")
+                        .map_err(|_| anyhow!("HTTP 签名服务需要配置 SIGNER_TOKEN"))?;
+                    let app = http_signer(signer, token);
+                    axum::serve(TcpListener::bind(addr).await?, app).await?;
+                }
+                None => {
+                    let listener = bind_unix_signer(Path::new(endpoint))?;
+                    serve_unix_signer(listener, signer).await?;
+                }
+            }
+        }
+        _ => {
+            bail!("用法: signer import <dir> <name> | signer serve <keystore> <socket|http://addr>")
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use ethers::{types::H160, utils::keccak256};
+use uuid::Uuid;
+
+/// 由聪明钱地址和成交 tid 确定性地生成跟单订单的 cloid，
+/// 下单结果不明时可用 `query_order_by_cloid` 查到这笔成交已经下过的订单
+pub fn copy_cloid(leader: H160, tid: u64) -> Uuid {
+    let mut bytes = leader.as_bytes().to_vec();
+    bytes.extend(tid.to_be_bytes());
+    let hash = keccak256(bytes);
+    let mut cloid = [0u8; 16];
+    cloid.copy_from_slice(&hash[..16]);
+    Uuid::from_bytes(cloid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_copy_cloid_is_deterministic() {
+        let leader = H160::from_str("0xe4d31c2541A9cE596419879B1A46Ffc7cD202c62").unwrap();
+        let other = H160::from_str("0xe15C2b1dfA0455511d30e4E25Af72af25B7B7747").unwrap();
+
+        assert_eq!(copy_cloid(leader, 1), copy_cloid(leader, 1));
+        assert_ne!(copy_cloid(leader, 1), copy_cloid(leader, 2));
+        assert_ne!(copy_cloid(leader, 1), copy_cloid(other, 1));
+    }
+}
@@ -1,5 +1,4 @@
 // 聪明钱地址
 pub const SMART_ADDRESS: &str = "0xe4d31c2541A9cE596419879B1A46Ffc7cD202c62";
-pub const MY_ADDRESS: &str = "0xe15C2b1dfA0455511d30e4E25Af72af25B7B7747";
 // 每次跟单的 U 的数值
 pub const TRADE_AMOUNT_USDT: f64 = 30.00;
@@ -0,0 +1,226 @@
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, patch, post},
+    Json, Router,
+};
+use ethers::types::H160;
+use hyperliquid_rust_sdk::{ExchangeClient, InfoClient};
+use log::info;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{str::FromStr, sync::Arc};
+use subtle::ConstantTimeEq;
+use tokio::net::TcpListener;
+
+use crate::{
+    handler::handle_user_event::flatten_coin,
+    reconcile::reconcile,
+    state::{BotState, CopyFlagsUpdate},
+};
+
+// 控制接口默认只监听本机
+pub const DEFAULT_CONTROL_ADDR: &str = "127.0.0.1:8787";
+
+#[derive(Clone)]
+struct ControlContext {
+    state: Arc<BotState>,
+    exchange_client: Arc<ExchangeClient>,
+    query_client: Arc<InfoClient>,
+    token: Arc<str>,
+}
+
+#[derive(Deserialize)]
+struct DecisionsQuery {
+    limit: Option<usize>,
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl From<hyperliquid_rust_sdk::Error> for ApiError {
+    fn from(e: hyperliquid_rust_sdk::Error) -> Self {
+        ApiError(StatusCode::BAD_GATEWAY, e.to_string())
+    }
+}
+
+/// 启动本地 HTTP 控制接口，所有请求需携带 `Authorization: Bearer <token>`
+pub async fn serve(
+    addr: &str,
+    token: String,
+    state: Arc<BotState>,
+    exchange_client: Arc<ExchangeClient>,
+    query_client: Arc<InfoClient>,
+) -> Result<()> {
+    let ctx = ControlContext {
+        state,
+        exchange_client,
+        query_client,
+        token: token.into(),
+    };
+    let app = Router::new()
+        .route("/status", get(status))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/leaders/:leader/pause", post(pause_leader))
+        .route("/leaders/:leader/resume", post(resume_leader))
+        .route("/flags", patch(update_flags))
+        .route("/decisions", get(decisions))
+        .route("/ledger", get(ledger))
+        .route("/reconcile", post(trigger_reconcile))
+        .route("/positions", get(positions))
+        .route("/flatten/:coin", post(flatten))
+        .layer(middleware::from_fn_with_state(ctx.clone(), authorize))
+        .with_state(ctx);
+
+    let listener = TcpListener::bind(addr).await?;
+    info!("control api listening on {addr}");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn authorize(
+    State(ctx): State<ControlContext>,
+    request: Request,
+    next: Next,
+) -> std::result::Result<Response, ApiError> {
+    let authorized = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        // 常量时间比较，避免通过响应耗时逐字节猜出 token
+        .is_some_and(|token| bool::from(token.as_bytes().ct_eq(ctx.token.as_bytes())));
+    if !authorized {
+        return Err(ApiError(StatusCode::UNAUTHORIZED, "unauthorized".into()));
+    }
+    Ok(next.run(request).await)
+}
+
+fn parse_leader(leader: &str) -> std::result::Result<H160, ApiError> {
+    H160::from_str(leader)
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, format!("invalid leader: {e}")))
+}
+
+async fn status(State(ctx): State<ControlContext>) -> Json<Value> {
+    Json(json!({
+        "paused": ctx.state.is_paused(),
+        "paused_leaders": ctx.state.paused_leaders(),
+        "flags": ctx.state.flags(),
+    }))
+}
+
+async fn pause(State(ctx): State<ControlContext>) -> Json<Value> {
+    ctx.state.set_paused(true);
+    info!("copying paused via control api");
+    status(State(ctx)).await
+}
+
+async fn resume(State(ctx): State<ControlContext>) -> Json<Value> {
+    ctx.state.set_paused(false);
+    info!("copying resumed via control api");
+    status(State(ctx)).await
+}
+
+async fn pause_leader(
+    State(ctx): State<ControlContext>,
+    Path(leader): Path<String>,
+) -> std::result::Result<Json<Value>, ApiError> {
+    let leader = parse_leader(&leader)?;
+    ctx.state.set_leader_paused(leader, true);
+    info!("copying of leader {leader:?} paused via control api");
+    Ok(status(State(ctx)).await)
+}
+
+async fn resume_leader(
+    State(ctx): State<ControlContext>,
+    Path(leader): Path<String>,
+) -> std::result::Result<Json<Value>, ApiError> {
+    let leader = parse_leader(&leader)?;
+    ctx.state.set_leader_paused(leader, false);
+    info!("copying of leader {leader:?} resumed via control api");
+    Ok(status(State(ctx)).await)
+}
+
+async fn update_flags(
+    State(ctx): State<ControlContext>,
+    Json(update): Json<CopyFlagsUpdate>,
+) -> Json<Value> {
+    let flags = ctx.state.update_flags(update);
+    info!("copy flags updated via control api: {flags:?}");
+    Json(json!(flags))
+}
+
+async fn decisions(
+    State(ctx): State<ControlContext>,
+    Query(query): Query<DecisionsQuery>,
+) -> Json<Value> {
+    Json(json!(ctx.state.recent_decisions(query.limit.unwrap_or(50))))
+}
+
+async fn ledger(State(ctx): State<ControlContext>) -> Json<Value> {
+    Json(json!(ctx.state.ledger()))
+}
+
+async fn trigger_reconcile(
+    State(ctx): State<ControlContext>,
+) -> std::result::Result<Json<Value>, ApiError> {
+    info!("reconciliation triggered via control api");
+    let drifts = reconcile(ctx.exchange_client.account(), &ctx.state, &ctx.query_client).await?;
+    Ok(Json(
+        json!({ "drifts": drifts, "ledger": ctx.state.ledger() }),
+    ))
+}
+
+async fn positions(
+    State(ctx): State<ControlContext>,
+) -> std::result::Result<Json<Value>, ApiError> {
+    let address = ctx.exchange_client.account();
+    let user_state = ctx.query_client.user_state(address).await?;
+    let balances = ctx.query_client.user_token_balances(address).await?;
+    let perps: Vec<Value> = user_state
+        .asset_positions
+        .iter()
+        .map(|p| {
+            json!({
+                "coin": p.position.coin,
+                "szi": p.position.szi,
+                "entry_px": p.position.entry_px,
+                "unrealized_pnl": p.position.unrealized_pnl,
+            })
+        })
+        .collect();
+    let spot: Vec<Value> = balances
+        .balances
+        .iter()
+        .map(|b| json!({ "coin": b.coin, "total": b.total, "hold": b.hold }))
+        .collect();
+    Ok(Json(json!({
+        "account_value": user_state.margin_summary.account_value,
+        "perps": perps,
+        "spot": spot,
+    })))
+}
+
+async fn flatten(
+    State(ctx): State<ControlContext>,
+    Path(coin): Path<String>,
+) -> std::result::Result<Json<Value>, ApiError> {
+    info!("flattening {coin} via control api");
+    let oid = flatten_coin(&coin, ctx.exchange_client, ctx.query_client).await?;
+    Ok(Json(json!({ "coin": coin, "oid": oid })))
+}
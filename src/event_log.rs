@@ -0,0 +1,208 @@
+use anyhow::Result;
+use ethers::types::H160;
+use hyperliquid_rust_sdk::TradeInfo;
+use log::{info, warn};
+use serde::Serialize;
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use crate::state::{now_ms, Decision};
+
+const EVENT_LOG_FILE: &str = "copy-events.jsonl";
+// 结构化事件在 log 中使用的 target，可通过 RUST_LOG=copy_event=info 单独打开
+pub const EVENT_LOG_TARGET: &str = "copy_event";
+
+static EVENT_LOG: OnceLock<Mutex<RotatingFile>> = OnceLock::new();
+
+/// 跟单流程中的结构化事件，每条写成一行 JSON
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CopyEvent<'a> {
+    LeaderFill {
+        leader: H160,
+        tid: u64,
+        oid: u64,
+        coin: &'a str,
+        dir: &'a str,
+        side: &'a str,
+        px: &'a str,
+        sz: &'a str,
+        start_position: &'a str,
+        time: u64,
+    },
+    OrderSubmitted {
+        coin: &'a str,
+        cloid: Option<&'a str>,
+        is_buy: bool,
+        reduce_only: bool,
+        sz: f64,
+        px: f64,
+    },
+    OrderResult {
+        coin: &'a str,
+        cloid: Option<&'a str>,
+        oid: Option<u64>,
+        error: Option<String>,
+    },
+    Decision(&'a Decision),
+}
+
+impl<'a> CopyEvent<'a> {
+    pub fn leader_fill(leader: H160, fill: &'a TradeInfo) -> Self {
+        CopyEvent::LeaderFill {
+            leader,
+            tid: fill.tid,
+            oid: fill.oid,
+            coin: &fill.coin,
+            dir: &fill.dir,
+            side: &fill.side,
+            px: &fill.px,
+            sz: &fill.sz,
+            start_position: &fill.start_position,
+            time: fill.time,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EventRecord<'a> {
+    ts: u64,
+    correlation_id: &'a str,
+    #[serde(flatten)]
+    event: &'a CopyEvent<'a>,
+}
+
+/// 关联同一笔聪明钱成交产生的所有事件
+pub fn correlation_id(leader: H160, tid: u64) -> String {
+    format!("{leader:?}:{tid}")
+}
+
+/// 打开滚动事件日志文件，未调用时事件只输出到 log
+pub fn init(dir: impl AsRef<Path>, max_bytes: u64, keep: usize) -> Result<()> {
+    let file = RotatingFile::open(dir.as_ref().join(EVENT_LOG_FILE), max_bytes, keep)?;
+    if EVENT_LOG.set(Mutex::new(file)).is_err() {
+        warn!("event log already initialized");
+    }
+    Ok(())
+}
+
+/// 按 EVENT_LOG_DIR / EVENT_LOG_MAX_BYTES / EVENT_LOG_KEEP 初始化
+pub fn init_from_env() -> Result<()> {
+    let dir = env::var("EVENT_LOG_DIR").unwrap_or_else(|_| "logs".to_string());
+    let max_bytes = env::var("EVENT_LOG_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(10 * 1024 * 1024);
+    let keep = env::var("EVENT_LOG_KEEP")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5);
+    init(dir, max_bytes, keep)
+}
+
+pub fn emit(correlation_id: &str, event: CopyEvent<'_>) {
+    let record = EventRecord {
+        ts: now_ms(),
+        correlation_id,
+        event: &event,
+    };
+    let line = match serde_json::to_string(&record) {
+        Ok(line) => line,
+        Err(e) => {
+            warn!("could not serialize copy event {event:?}: {e}");
+            return;
+        }
+    };
+    info!(target: EVENT_LOG_TARGET, "{line}");
+    if let Some(file) = EVENT_LOG.get() {
+        if let Err(e) = file.lock().unwrap().write_line(&line) {
+            warn!("could not write copy event: {e}");
+        }
+    }
+}
+
+/// 按大小滚动的日志文件：超出 max_bytes 时依次改名为 .1 .. .keep
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_bytes: u64, keep: usize) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(RotatingFile {
+            path,
+            file,
+            written,
+            max_bytes,
+            keep,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            for index in (1..self.keep).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = File::create(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        if self.written > 0 && self.written + line.len() as u64 + 1 > self.max_bytes {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{line}")?;
+        self.written += line.len() as u64 + 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotating_file_keeps_newest_lines() -> Result<()> {
+        let dir = env::temp_dir().join(format!("copy-events-{}", now_ms()));
+        let mut file = RotatingFile::open(dir.join(EVENT_LOG_FILE), 20, 2)?;
+        for i in 0..4 {
+            file.write_line(&format!("{{\"line\":{i}}}"))?;
+        }
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(file.path.clone()), "{\"line\":3}\n");
+        assert_eq!(read(file.rotated_path(1)), "{\"line\":2}\n");
+        assert_eq!(read(file.rotated_path(2)), "{\"line\":1}\n");
+        assert!(!file.rotated_path(3).exists());
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,532 @@
+use ethers::types::H160;
+use hyperliquid_rust_sdk::{
+    BaseUrl, FrameRecorder, InfoClient, Message, ReconnectConfig, Subscription, TradeInfo,
+    UserData, UserFillsResponse,
+};
+use log::{debug, error, info, warn};
+use std::{
+    collections::{HashSet, VecDeque},
+    env,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    time,
+};
+
+use crate::{
+    notifier::{Notifier, NotifyEvent},
+    state::now_ms,
+};
+
+// 用于去重的最近成交 tid 数量
+const MAX_SEEN_FILLS: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct FeedConfig {
+    /// websocket 断线后由 SDK 按此配置重连并恢复订阅；连接失效时的重建也按它退避
+    pub reconnect: ReconnectConfig,
+    /// 超过该时间没有收到 pong 或数据即视为连接失效
+    pub stale_after: Duration,
+    /// 补单只补这个时间以内的成交，更早的成交跳过并告警
+    pub max_backfill_age: Duration,
+    /// 录制收到的原始 websocket 帧，用于离线回放测试
+    pub recorder: Option<Arc<FrameRecorder>>,
+}
+
+impl FeedConfig {
+    pub fn from_env() -> Self {
+        let millis = |key: &str, default: u64| {
+            Duration::from_millis(
+                env::var(key)
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default),
+            )
+        };
+        let recorder = env::var("WS_RECORD_FILE").ok().and_then(|path| {
+            FrameRecorder::create(&path)
+                .map_err(|e| error!("无法打开录制文件 {path}: {e}"))
+                .ok()
+        });
+        FeedConfig {
+            reconnect: ReconnectConfig {
+                initial_delay: millis("FEED_BACKOFF_INITIAL_MS", 500),
+                max_delay: millis("FEED_BACKOFF_MAX_MS", 30_000),
+                ..ReconnectConfig::default()
+            },
+            stale_after: millis("FEED_STALE_MS", 45_000),
+            max_backfill_age: millis("FEED_MAX_BACKFILL_AGE_MS", 300_000),
+            recorder,
+        }
+    }
+}
+
+/// 已处理过的聪明钱成交，补单时按 tid 去重
+#[derive(Debug)]
+struct SeenFills {
+    tids: HashSet<u64>,
+    order: VecDeque<u64>,
+    // 最近一笔成交的时间，断线后从这里开始补
+    last_time: u64,
+}
+
+impl SeenFills {
+    fn new(start_time: u64) -> Self {
+        SeenFills {
+            tids: HashSet::new(),
+            order: VecDeque::with_capacity(MAX_SEEN_FILLS),
+            last_time: start_time,
+        }
+    }
+
+    /// 过滤掉已经处理过的成交
+    fn retain_new(&mut self, fills: Vec<TradeInfo>) -> Vec<TradeInfo> {
+        fills
+            .into_iter()
+            .filter(|fill| {
+                if !self.tids.insert(fill.tid) {
+                    return false;
+                }
+                if self.order.len() == MAX_SEEN_FILLS {
+                    if let Some(oldest) = self.order.pop_front() {
+                        self.tids.remove(&oldest);
+                    }
+                }
+                self.order.push_back(fill.tid);
+                self.last_time = self.last_time.max(fill.time);
+                true
+            })
+            .collect()
+    }
+}
+
+fn into_trade_info(fill: UserFillsResponse) -> TradeInfo {
+    TradeInfo {
+        coin: fill.coin,
+        px: fill.px,
+        sz: fill.sz,
+        side: fill.side,
+        time: fill.time,
+        start_position: fill.start_position,
+        hash: fill.hash,
+        oid: fill.oid,
+        crossed: fill.crossed,
+        fee: fill.fee,
+        tid: fill.tid,
+        cloid: fill.cloid,
+        fee_token: fill.fee_token,
+        closed_pnl: fill.closed_pnl,
+        dir: fill.dir,
+    }
+}
+
+/// 补单时跳过早于 cutoff 的成交，返回仍需跟单的成交和跳过的笔数
+fn drop_stale(fills: Vec<TradeInfo>, cutoff: u64) -> (Vec<TradeInfo>, usize) {
+    let (fresh, stale): (Vec<_>, Vec<_>) = fills.into_iter().partition(|fill| fill.time >= cutoff);
+    (fresh, stale.len())
+}
+
+/// 持续订阅聪明钱成交：断线由 SDK 重连并恢复订阅，重连后用 user_fills_by_time 补回断线期间的成交；
+/// 连接长时间无数据时关闭并重建订阅
+pub async fn run_leader_feed(
+    leader: H160,
+    network: BaseUrl,
+    query_client: Arc<InfoClient>,
+    config: FeedConfig,
+    fills: UnboundedSender<Vec<TradeInfo>>,
+    notifier: Notifier,
+) {
+    let mut feed = LeaderFeed {
+        leader,
+        query_client,
+        config,
+        fills,
+        notifier,
+        seen: SeenFills::new(now_ms()),
+        attempt: 0,
+    };
+    loop {
+        match feed.subscribe(network.clone()).await {
+            Ok((mut info_client, receiver)) => {
+                feed.backfill().await;
+                let reason = feed.watch(receiver).await;
+                if let Err(e) = info_client.close().await {
+                    debug!("关闭聪明钱订阅失败: {e}");
+                }
+                error!("聪明钱订阅中断: {reason}");
+                feed.notifier.notify(NotifyEvent::WebsocketDisconnected);
+            }
+            Err(e) => error!("聪明钱订阅失败: {e}"),
+        }
+        feed.attempt += 1;
+        let delay = feed.config.reconnect.delay(feed.attempt);
+        info!("{}ms 后重建聪明钱订阅", delay.as_millis());
+        time::sleep(delay).await;
+    }
+}
+
+struct LeaderFeed {
+    leader: H160,
+    query_client: Arc<InfoClient>,
+    config: FeedConfig,
+    fills: UnboundedSender<Vec<TradeInfo>>,
+    notifier: Notifier,
+    seen: SeenFills,
+    // 连续重建订阅的次数，收到数据后清零
+    attempt: u32,
+}
+
+impl LeaderFeed {
+    async fn subscribe(
+        &self,
+        network: BaseUrl,
+    ) -> Result<(InfoClient, UnboundedReceiver<Message>), hyperliquid_rust_sdk::Error> {
+        let mut info_client =
+            InfoClient::with_reconnect_config(None, Some(network), self.config.reconnect.clone())
+                .await?;
+        if let Some(recorder) = &self.config.recorder {
+            info_client.record_frames(recorder.clone());
+        }
+        let (sender, receiver) = unbounded_channel();
+        info_client
+            .subscribe(Subscription::UserEvents { user: self.leader }, sender)
+            .await?;
+        Ok((info_client, receiver))
+    }
+
+    async fn backfill(&mut self) {
+        let mut missed = match self
+            .query_client
+            .user_fills_by_time(self.leader, self.seen.last_time, None)
+            .await
+        {
+            Ok(missed) => missed,
+            Err(e) => {
+                error!("补拉聪明钱成交失败: {e}");
+                return;
+            }
+        };
+        missed.sort_by_key(|fill| fill.time);
+        let missed = self
+            .seen
+            .retain_new(missed.into_iter().map(into_trade_info).collect());
+        let max_age = self.config.max_backfill_age;
+        let (missed, stale) =
+            drop_stale(missed, now_ms().saturating_sub(max_age.as_millis() as u64));
+        if stale > 0 {
+            let error = format!("skipped {stale} fills older than {}s", max_age.as_secs());
+            warn!("聪明钱成交补单: {error}");
+            self.notifier.notify(NotifyEvent::Error {
+                context: "backfill".to_string(),
+                error,
+            });
+        }
+        if !missed.is_empty() {
+            warn!("补拉到断线期间的聪明钱成交 {} 笔", missed.len());
+            let _ = self.fills.send(missed);
+        }
+    }
+
+    /// 转发实时成交，直到连接失效，返回中断原因
+    async fn watch(&mut self, mut receiver: UnboundedReceiver<Message>) -> String {
+        let stale_after = self.config.stale_after;
+        loop {
+            let message = match time::timeout(stale_after, receiver.recv()).await {
+                Ok(Some(message)) => message,
+                Ok(None) => return "channel closed".to_string(),
+                Err(_) => return format!("no data for {}ms", stale_after.as_millis()),
+            };
+            match message {
+                Message::User(user_event) => {
+                    // 收到数据说明连接正常，下次重建从头退避
+                    self.attempt = 0;
+                    if let UserData::Fills(trade_infos) = user_event.data {
+                        let trade_infos = self.seen.retain_new(trade_infos);
+                        if !trade_infos.is_empty() {
+                            let _ = self.fills.send(trade_infos);
+                        }
+                    }
+                }
+                Message::Disconnected { attempt: 0 } => {
+                    error!("聪明钱订阅断线，等待 SDK 重连");
+                    self.notifier.notify(NotifyEvent::WebsocketDisconnected);
+                }
+                Message::Disconnected { attempt } => debug!("聪明钱订阅第 {attempt} 次重连失败"),
+                Message::Reconnected { attempt } => {
+                    info!("聪明钱订阅在第 {attempt} 次尝试后恢复");
+                    self.backfill().await;
+                }
+                Message::HyperliquidError(err) => warn!("聪明钱订阅错误: {err}"),
+                Message::Pong => {
+                    self.attempt = 0;
+                    debug!("pong");
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handler::{aggregate_fills::spawn_fill_aggregator, handle_user_event::handle_user_event},
+        state::{BotState, CopyFlags, DecisionOutcome},
+    };
+    use ethers::signers::Signer;
+    use hyperliquid_rust_sdk::{
+        Actions, ExchangeClient, MockHyperliquid, RecordedFrame, ReplayServer,
+    };
+    use serde_json::json;
+
+    // 连到模拟交易所的跟单流水线：送入 fills 的成交经合并、跟单后下单，每批处理完在 handled 上通知
+    struct Pipeline {
+        leader: H160,
+        mock: MockHyperliquid,
+        base_url: BaseUrl,
+        query_client: Arc<InfoClient>,
+        state: Arc<BotState>,
+        fills: UnboundedSender<Vec<TradeInfo>>,
+        handled: UnboundedReceiver<()>,
+    }
+
+    impl Pipeline {
+        async fn start() -> anyhow::Result<Pipeline> {
+            let leader = H160::from_low_u64_be(7);
+            let (mock, base_url, wallet) = MockHyperliquid::start_local().await?;
+            mock.expect_signer(wallet.address());
+            let query_client = Arc::new(InfoClient::new(None, Some(base_url.clone())).await?);
+            let exchange_client = Arc::new(
+                ExchangeClient::with_info(None, wallet, query_client.clone(), None, None).await?,
+            );
+            let flags = CopyFlags {
+                enable_buy: true,
+                enable_sell: true,
+                enable_perps_buy: true,
+                enable_perps_sell: true,
+            };
+            let state = Arc::new(BotState::new(flags, Notifier::disabled()));
+            state.set_leverage(5);
+
+            let (done, handled) = unbounded_channel();
+            let fills = {
+                let query_client = query_client.clone();
+                let state = state.clone();
+                spawn_fill_aggregator(Duration::from_millis(50), move |trade_infos| {
+                    let (exchange_client, query_client) =
+                        (exchange_client.clone(), query_client.clone());
+                    let (state, done) = (state.clone(), done.clone());
+                    tokio::spawn(async move {
+                        let _ = handle_user_event(
+                            leader,
+                            trade_infos,
+                            exchange_client,
+                            query_client,
+                            state,
+                        )
+                        .await;
+                        let _ = done.send(());
+                    });
+                })
+            };
+            Ok(Pipeline {
+                leader,
+                mock,
+                base_url,
+                query_client,
+                state,
+                fills,
+                handled,
+            })
+        }
+
+        // 从 network 订阅聪明钱成交并送入流水线
+        fn run_feed(&self, network: BaseUrl, config: FeedConfig) {
+            tokio::spawn(run_leader_feed(
+                self.leader,
+                network,
+                self.query_client.clone(),
+                config,
+                self.fills.clone(),
+                Notifier::disabled(),
+            ));
+        }
+    }
+
+    fn fill(tid: u64, time: u64) -> TradeInfo {
+        TradeInfo {
+            coin: "ETH".to_string(),
+            px: "3000.0".to_string(),
+            sz: "1.0".to_string(),
+            side: "B".to_string(),
+            time,
+            start_position: "0.0".to_string(),
+            hash: format!("0x{tid}"),
+            oid: 1,
+            crossed: true,
+            fee: "0.01".to_string(),
+            tid,
+            cloid: None,
+            fee_token: "USDC".to_string(),
+            closed_pnl: "0.0".to_string(),
+            dir: "Open Long".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_seen_fills_dedup() {
+        let mut seen = SeenFills::new(100);
+        let first = seen.retain_new(vec![fill(1, 110), fill(2, 120)]);
+        assert_eq!(first.len(), 2);
+        assert_eq!(seen.last_time, 120);
+
+        let backfilled = seen.retain_new(vec![fill(2, 120), fill(3, 130)]);
+        assert_eq!(backfilled.len(), 1);
+        assert_eq!(backfilled[0].tid, 3);
+        assert_eq!(seen.last_time, 130);
+    }
+
+    #[test]
+    fn test_drop_stale_backfill() {
+        let (fresh, stale) = drop_stale(vec![fill(1, 90), fill(2, 100), fill(3, 110)], 100);
+        assert_eq!(stale, 1);
+        assert_eq!(
+            fresh.iter().map(|fill| fill.tid).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+    }
+
+    // 回放录制的聪明钱成交，经过订阅、合并、跟单到模拟交易所下单，不需要网络
+    #[tokio::test]
+    async fn test_replayed_fill_is_copied() -> anyhow::Result<()> {
+        let fill = json!({
+            "coin": "PURR/USDC", "px": "0.2", "sz": "500.0", "side": "B", "time": now_ms(),
+            "startPosition": "0.0", "hash": "0x1", "oid": 1, "crossed": true, "fee": "0.0",
+            "tid": 1, "feeToken": "PURR", "closedPnl": "0.0", "dir": "Buy",
+        });
+        let recording = [
+            json!({ "channel": "subscriptionResponse", "data": {} }),
+            json!({ "channel": "user", "data": { "fills": [fill] } }),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, frame)| {
+            serde_json::to_string(&RecordedFrame {
+                ts: i as i64,
+                connection: 0,
+                frame: frame.to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .join("\n");
+        let path = env::temp_dir().join(format!("leader-feed-{}.jsonl", now_ms()));
+        std::fs::write(&path, recording)?;
+        let replay = ReplayServer::start(&path, 0.0).await?;
+
+        let mut pipeline = Pipeline::start().await?;
+        let BaseUrl::Custom { http, .. } = pipeline.base_url.clone() else {
+            unreachable!("serve_local returns a custom url");
+        };
+        let network = BaseUrl::Custom {
+            http,
+            ws: replay.url().to_string(),
+            is_mainnet: false,
+        };
+        let config = FeedConfig {
+            reconnect: ReconnectConfig::default(),
+            stale_after: Duration::from_secs(60),
+            max_backfill_age: Duration::from_secs(60),
+            recorder: None,
+        };
+        pipeline.run_feed(network, config);
+
+        // 补单查询在订阅之后发出，收到后再开始回放
+        let backfilled = || {
+            pipeline
+                .mock
+                .info_requests()
+                .iter()
+                .any(|request| request["type"] == "userFillsByTime")
+        };
+        time::timeout(Duration::from_secs(5), async {
+            while !backfilled() {
+                time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await?;
+        replay.play();
+        time::timeout(Duration::from_secs(5), pipeline.handled.recv()).await?;
+
+        let decisions = pipeline.state.recent_decisions(10);
+        assert_eq!(decisions.len(), 1);
+        assert!(matches!(
+            decisions[0].outcome,
+            DecisionOutcome::Copied { .. }
+        ));
+        let actions = pipeline.mock.actions();
+        let Actions::Order(bulk_order) = &actions[0].action else {
+            panic!("expected an order, got {:?}", actions[0].action);
+        };
+        let order = &bulk_order.orders[0];
+        assert_eq!((order.asset, order.is_buy), (10_000, true));
+        assert_eq!(order.sz, "150");
+        let _ = std::fs::remove_file(path);
+        Ok(())
+    }
+
+    // 模拟交易所推送的永续开多经过完整流水线，先按配置的杠杆调整再下单
+    #[tokio::test]
+    async fn test_copy_trade_loop() -> anyhow::Result<()> {
+        let mut pipeline = Pipeline::start().await?;
+        let config = FeedConfig {
+            reconnect: ReconnectConfig {
+                initial_delay: Duration::from_millis(100),
+                max_delay: Duration::from_secs(1),
+                ..ReconnectConfig::default()
+            },
+            stale_after: Duration::from_secs(60),
+            max_backfill_age: Duration::from_secs(60),
+            recorder: None,
+        };
+        pipeline.run_feed(pipeline.base_url.clone(), config);
+
+        let mock = &pipeline.mock;
+        assert!(
+            mock.wait_for_subscription("userEvents", Duration::from_secs(5))
+                .await
+        );
+        mock.push_user_fills(json!([{
+            "coin": "ETH", "px": "3000.0", "sz": "2.0", "side": "B", "time": now_ms() + 1,
+            "startPosition": "0.0", "hash": "0x1", "oid": 1, "crossed": true, "fee": "0.1",
+            "tid": 1, "feeToken": "USDC", "closedPnl": "0.0", "dir": "Open Long",
+        }]));
+        time::timeout(Duration::from_secs(5), pipeline.handled.recv()).await?;
+
+        let decisions = pipeline.state.recent_decisions(10);
+        assert_eq!(decisions.len(), 1);
+        assert!(matches!(
+            decisions[0].outcome,
+            DecisionOutcome::Copied { oid: 1, .. }
+        ));
+        let actions = mock.actions();
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(
+            &actions[0].action,
+            Actions::UpdateLeverage(update) if update.asset == 1 && update.leverage == 5
+        ));
+        let Actions::Order(bulk_order) = &actions[1].action else {
+            panic!("expected an order, got {:?}", actions[1].action);
+        };
+        let order = &bulk_order.orders[0];
+        assert_eq!((order.asset, order.is_buy), (1, true));
+        assert_eq!(
+            (order.limit_px.as_str(), order.sz.as_str()),
+            ("3150", "0.01")
+        );
+        assert!(order.cloid.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use hyperliquid_rust_sdk::TradeInfo;
+use std::{env, time::Duration};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::{self, Instant},
+};
+
+/// 聚合窗口，从窗口内第一笔成交开始计时
+pub fn aggregation_window_from_env() -> Duration {
+    Duration::from_millis(
+        env::var("FILL_AGGREGATION_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(300),
+    )
+}
+
+/// 启动聚合任务：窗口内收到的成交按 oid + coin + dir 合并后交给 on_batch
+pub fn spawn_fill_aggregator<F>(window: Duration, on_batch: F) -> UnboundedSender<Vec<TradeInfo>>
+where
+    F: Fn(Vec<TradeInfo>) + Send + 'static,
+{
+    let (sender, mut receiver) = unbounded_channel::<Vec<TradeInfo>>();
+    tokio::spawn(async move {
+        while let Some(mut fills) = receiver.recv().await {
+            let deadline = Instant::now() + window;
+            while let Ok(Some(more)) = time::timeout_at(deadline, receiver.recv()).await {
+                fills.extend(more);
+            }
+            on_batch(aggregate_fills(&fills));
+        }
+    });
+    sender
+}
+
+/// 同一聪明钱订单拆成的多笔成交合并为一笔：数量相加，价格按成交量加权平均，
+/// 其余字段沿用该组第一笔成交，合并结果保持各组首次出现的顺序
+pub fn aggregate_fills(fills: &[TradeInfo]) -> Vec<TradeInfo> {
+    let mut groups: Vec<Vec<&TradeInfo>> = Vec::new();
+    for fill in fills {
+        let group = groups.iter_mut().find(|group| {
+            let first = group[0];
+            first.oid == fill.oid && first.coin == fill.coin && first.dir == fill.dir
+        });
+        match group {
+            Some(group) => group.push(fill),
+            None => groups.push(vec![fill]),
+        }
+    }
+    groups.into_iter().map(merge_group).collect()
+}
+
+fn merge_group(group: Vec<&TradeInfo>) -> TradeInfo {
+    let mut merged = group[0].clone();
+    if group.len() == 1 {
+        return merged;
+    }
+
+    let parse = |s: &str| s.parse::<f64>().unwrap_or_default();
+    let total_sz: f64 = group.iter().map(|fill| parse(&fill.sz)).sum();
+    let notional: f64 = group
+        .iter()
+        .map(|fill| parse(&fill.sz) * parse(&fill.px))
+        .sum();
+    let vwap = if total_sz > 0.0 {
+        notional / total_sz
+    } else {
+        parse(&merged.px)
+    };
+    let sum = |field: fn(&TradeInfo) -> &str| -> String {
+        let decimals = group.iter().map(|fill| decimals(field(fill))).max();
+        let total: f64 = group.iter().map(|fill| parse(field(fill))).sum();
+        format!("{:.*}", decimals.unwrap_or_default(), total)
+    };
+
+    let px_decimals = group.iter().map(|fill| decimals(&fill.px)).max();
+    merged.px = format!("{:.*}", px_decimals.unwrap_or_default(), vwap);
+    merged.sz = sum(|fill| &fill.sz);
+    merged.fee = sum(|fill| &fill.fee);
+    merged.closed_pnl = sum(|fill| &fill.closed_pnl);
+    merged.crossed = group.iter().any(|fill| fill.crossed);
+    merged
+}
+
+fn decimals(s: &str) -> usize {
+    s.split_once('.').map(|(_, frac)| frac.len()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(oid: u64, tid: u64, dir: &str, px: &str, sz: &str) -> TradeInfo {
+        TradeInfo {
+            coin: "ETH".to_string(),
+            px: px.to_string(),
+            sz: sz.to_string(),
+            side: "B".to_string(),
+            time: tid,
+            start_position: "0.0".to_string(),
+            hash: format!("0x{tid}"),
+            oid,
+            crossed: true,
+            fee: "0.01".to_string(),
+            tid,
+            cloid: None,
+            fee_token: "USDC".to_string(),
+            closed_pnl: "0.0".to_string(),
+            dir: dir.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_fills_by_order() {
+        let fills = vec![
+            fill(1, 10, "Open Long", "3000.0", "1.0"),
+            fill(2, 11, "Open Long", "3005.5", "0.5"),
+            fill(1, 12, "Open Long", "3003.0", "2.0"),
+            fill(1, 13, "Close Short", "3003.0", "0.25"),
+        ];
+        let merged = aggregate_fills(&fills);
+
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0].oid, 1);
+        assert_eq!(merged[0].tid, 10);
+        assert_eq!(merged[0].sz, "3.0");
+        assert_eq!(merged[0].px, "3002.0");
+        assert_eq!(merged[0].fee, "0.02");
+        assert_eq!(merged[1].oid, 2);
+        assert_eq!(merged[1].px, "3005.5");
+        assert_eq!(merged[2].dir, "Close Short");
+        assert_eq!(merged[2].sz, "0.25");
+    }
+}
@@ -1,112 +1,274 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use ethers::types::H160;
 use hyperliquid_rust_sdk::{
     ClientLimit, ClientOrder, ClientOrderRequest, ExchangeClient, ExchangeDataStatus,
-    ExchangeResponseStatus, InfoClient, SpotMeta, TradeInfo,
+    ExchangeResponseStatus, InfoClient, MarketCloseParams, SpotMeta, TradeInfo,
 };
-use std::{env, fs, path::Path, str::FromStr, sync::Arc};
+use log::{debug, warn};
+use std::{fs, path::Path, sync::Arc};
+use uuid::Uuid;
 
 use crate::{
-    constants::{MY_ADDRESS, TRADE_AMOUNT_USDT},
+    cloid::copy_cloid,
+    event_log::{self, correlation_id, CopyEvent},
+    market::MarketCache,
+    state::{now_ms, BotState, Decision, DecisionOutcome},
     utils::format_adjust_price,
 };
 
 pub async fn handle_user_event(
+    leader: H160,
     trade_infos: Vec<TradeInfo>,
     exchange_client: Arc<ExchangeClient>,
     query_client: Arc<InfoClient>,
+    state: Arc<BotState>,
 ) -> Result<()> {
-    let enable_sell = env::var("ENABLE_SELL").unwrap().parse::<bool>().unwrap();
-    let enable_buy = env::var("ENABLE_BUY").unwrap().parse::<bool>().unwrap();
-    let enable_perps_sell = env::var("ENABLE_PERPS_SELL")
-        .unwrap()
-        .parse::<bool>()
-        .unwrap();
-    let enable_perps_buy = env::var("ENABLE_PERPS_BUY")
-        .unwrap()
-        .parse::<bool>()
-        .unwrap();
-    for (_index, trade) in trade_infos.iter().enumerate() {
-        let trade_type = trade.dir.as_str();
-        println!("trade_type {}", trade_type);
-        match trade_type {
-            "Buy" => {
-                println!("===============聪明现货买入信息==================");
-                println!(
-                    "聪明钱进行现货买入订单: 代币：{} 价格：{} 数量: {}",
-                    trade.coin, trade.px, trade.sz
-                );
-                if enable_buy {
-                    execute_spot_buy_order(&trade, exchange_client.clone()).await?;
-                    // 限价单 可以挂上止盈止损单
-                    // execute_spot_limit_sell_order(&trade, exchange_client.clone()).await?;
-                }
-            }
-            "Sell" => {
-                println!("===============聪明现货卖出信息==================");
-                println!(
-                    "聪明钱进行现货卖出订单: 代币：{} 价格：{} 数量: {}",
-                    trade.coin, trade.px, trade.sz
-                );
-                if enable_sell {
-                    execute_spot_sell_order(&trade, exchange_client.clone(), query_client.clone())
-                        .await?;
-                }
+    for trade in trade_infos.iter() {
+        let copy = CopyRef {
+            correlation_id: correlation_id(leader, trade.tid),
+            cloid: Some(copy_cloid(leader, trade.tid)),
+        };
+        event_log::emit(&copy.correlation_id, CopyEvent::leader_fill(leader, trade));
+        // 之后同一币种的成交即可用缓存的盘口和可下单量
+        let is_perp = !matches!(trade.dir.as_str(), "Buy" | "Sell");
+        state.market().watch(&trade.coin, is_perp);
+        let outcome = if state.is_leader_paused(&leader) {
+            skipped("paused")
+        } else if let Some(breach) = state.check_risk(leader, trade) {
+            skipped(&format!(
+                "risk limit: {} notional {:.2} over {}",
+                breach.coin, breach.notional, breach.limit
+            ))
+        } else {
+            copy_trade(
+                &copy,
+                trade,
+                &state,
+                exchange_client.clone(),
+                query_client.clone(),
+            )
+            .await
+        };
+        let decision = Decision {
+            time: now_ms(),
+            leader,
+            tid: trade.tid,
+            coin: trade.coin.clone(),
+            dir: trade.dir.clone(),
+            px: trade.px.clone(),
+            sz: trade.sz.clone(),
+            outcome,
+        };
+        event_log::emit(&copy.correlation_id, CopyEvent::Decision(&decision));
+        state.record_decision(decision);
+    }
+    Ok(())
+}
+
+async fn copy_trade(
+    copy: &CopyRef,
+    trade: &TradeInfo,
+    state: &BotState,
+    exchange_client: Arc<ExchangeClient>,
+    query_client: Arc<InfoClient>,
+) -> DecisionOutcome {
+    let (flags, market) = (state.flags(), state.market());
+    let result = match trade.dir.as_str() {
+        "Buy" => {
+            if !flags.enable_buy {
+                return skipped("buy disabled");
             }
-            // 开多
-            "Open Long" => {
-                println!("聪明钱 Open Long: {:#?}", trade);
-                if enable_perps_buy {
-                    execute_open_long_order(&trade, exchange_client.clone()).await?;
-                }
+            // 限价单 可以挂上止盈止损单
+            // execute_spot_limit_sell_order(&trade, exchange_client.clone()).await?;
+            execute_spot_buy_order(copy, trade, market, exchange_client).await
+        }
+        "Sell" => {
+            if !flags.enable_sell {
+                return skipped("sell disabled");
             }
-            // 平多
-            "Close Long" => {
-                println!("聪明钱 Close Long: {:#?}", trade);
-                if enable_perps_buy {
-                    execute_close_long_order(&trade, exchange_client.clone(), query_client.clone())
-                        .await?
-                }
+            execute_spot_sell_order(copy, trade, exchange_client, query_client).await
+        }
+        // 开多
+        "Open Long" => {
+            if !flags.enable_perps_buy {
+                return skipped("perps buy disabled");
             }
-            // 开空
-            "Open Short" => {
-                println!("聪明钱 Open Short: {:#?}", trade);
-                if enable_perps_sell {
-                    execute_open_short_order(&trade, exchange_client.clone()).await?;
-                }
+            execute_open_long_order(copy, trade, market, state.leverage(), exchange_client).await
+        }
+        // 平多
+        "Close Long" => {
+            if !flags.enable_perps_buy {
+                return skipped("perps buy disabled");
             }
-            // 平空
-            "Close Short" => {
-                println!("聪明钱 Close Short: {:#?}", trade);
-                if enable_perps_sell {
-                    execute_close_short_order(&trade, exchange_client.clone(), query_client.clone())
-                        .await?
-                }
+            execute_close_long_order(copy, trade, exchange_client, query_client).await
+        }
+        // 开空
+        "Open Short" => {
+            if !flags.enable_perps_sell {
+                return skipped("perps sell disabled");
             }
-            _ => {
-                println!("未知类型");
+            execute_open_short_order(copy, trade, market, state.leverage(), exchange_client).await
+        }
+        // 平空
+        "Close Short" => {
+            if !flags.enable_perps_sell {
+                return skipped("perps sell disabled");
             }
+            execute_close_short_order(copy, trade, exchange_client, query_client).await
         }
+        _ => return skipped("unknown dir"),
+    };
+    match result {
+        Ok(placed) => DecisionOutcome::Copied {
+            oid: placed.oid,
+            sz: placed.sz,
+            px: placed.px,
+        },
+        Err(e) => DecisionOutcome::Failed {
+            error: e.to_string(),
+        },
+    }
+}
+
+fn skipped(reason: &str) -> DecisionOutcome {
+    DecisionOutcome::Skipped {
+        reason: reason.to_string(),
+    }
+}
+
+/// 平掉某个币种的全部仓位：永续使用市价平仓，现货卖出全部余额
+pub async fn flatten_coin(
+    coin: &str,
+    exchange_client: Arc<ExchangeClient>,
+    query_client: Arc<InfoClient>,
+) -> Result<u64> {
+    let is_perp = exchange_client
+        .meta
+        .universe
+        .iter()
+        .any(|asset| asset.name == coin);
+    if is_perp {
+        let response = exchange_client
+            .market_close(MarketCloseParams {
+                asset: coin,
+                sz: None,
+                px: None,
+                slippage: None,
+                cloid: None,
+                signer: None,
+            })
+            .await?;
+        return response_oid(response);
+    }
+
+    let all_mids = query_client.all_mids().await?;
+    let Some(mid) = all_mids.get(coin) else {
+        bail!("未找到 {} 的中间价", coin);
+    };
+    let copy = CopyRef {
+        correlation_id: format!("flatten:{coin}:{}", now_ms()),
+        cloid: None,
+    };
+    let placed = sell_spot_balance(&copy, coin, mid, exchange_client, query_client).await?;
+    Ok(placed.oid)
+}
+
+/// 一笔聪明钱成交对应的跟单标识
+#[derive(Debug, Clone)]
+pub struct CopyRef {
+    pub correlation_id: String,
+    pub cloid: Option<Uuid>,
+}
+
+/// 实际提交的跟单订单
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedOrder {
+    pub oid: u64,
+    pub sz: f64,
+    pub px: f64,
+}
+
+async fn place_order(
+    copy: &CopyRef,
+    order: ClientOrderRequest,
+    exchange_client: &ExchangeClient,
+) -> Result<PlacedOrder> {
+    let cid = copy.correlation_id.as_str();
+    let coin = order.asset.clone();
+    let (sz, px) = (order.sz, order.limit_px);
+    let cloid = order.cloid.map(|cloid| cloid.to_string());
+    event_log::emit(
+        cid,
+        CopyEvent::OrderSubmitted {
+            coin: &coin,
+            cloid: cloid.as_deref(),
+            is_buy: order.is_buy,
+            reduce_only: order.reduce_only,
+            sz,
+            px,
+        },
+    );
+    // 响应丢失后的重发由 SDK 处理：重发被拒时按 cloid 查到已下的订单，不会重复下单
+    let result = exchange_client
+        .order(order, None)
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|response| {
+            debug!("response: {response:?}");
+            response_oid(response)
+        });
+    let (oid, error) = match &result {
+        Ok(oid) => (Some(*oid), None),
+        Err(e) => {
+            warn!("交易失败: 错误详情 - {e}");
+            (None, Some(e.to_string()))
+        }
+    };
+    event_log::emit(
+        cid,
+        CopyEvent::OrderResult {
+            coin: &coin,
+            cloid: cloid.as_deref(),
+            oid,
+            error,
+        },
+    );
+    Ok(PlacedOrder {
+        oid: result?,
+        sz,
+        px,
+    })
+}
+
+fn response_oid(response: ExchangeResponseStatus) -> Result<u64> {
+    let response = match response {
+        ExchangeResponseStatus::Ok(exchange_response) => exchange_response,
+        ExchangeResponseStatus::Err(e) => bail!("error with exchange response: {e}"),
+    };
+    let Some(data) = response.data else {
+        bail!("exchange response missing data");
+    };
+    match data.statuses.first() {
+        Some(ExchangeDataStatus::Filled(order)) => Ok(order.oid),
+        Some(ExchangeDataStatus::Resting(order)) => Ok(order.oid),
+        status => bail!("oid status错误: {status:?}"),
     }
-    Ok(())
 }
 
 async fn execute_spot_buy_order(
+    copy: &CopyRef,
     trade: &TradeInfo,
+    market: &MarketCache,
     exchange_client: Arc<ExchangeClient>,
-) -> Result<()> {
-    // 调整价格精度，保持与原始价格相同的小数位数
-    let adjusted_price = format_adjust_price(&trade.px, 1.05);
-    let size = TRADE_AMOUNT_USDT / trade.px.parse::<f64>().unwrap();
-    let size_rounded = format!("{:.4}", size).parse::<f64>().unwrap();
-    println!("执行现货买入 {} {} 跟单", trade.coin, adjusted_price,);
+) -> Result<PlacedOrder> {
+    let (adjusted_price, size_rounded) = market.open_price_size(trade, true)?;
     let order = ClientOrderRequest {
         asset: trade.coin.to_string(),
         is_buy: true,
         reduce_only: false,
         limit_px: adjusted_price,
         sz: size_rounded,
-        cloid: None,
+        cloid: copy.cloid,
         // 立即成交
         // Alo 挂单  Ioc 立即成交  否则失败 Gtc 订单保持有效直到被取消或完全成交
         order_type: ClientOrder::Limit(ClientLimit {
@@ -114,73 +276,60 @@ async fn execute_spot_buy_order(
         }),
     };
 
-    let response = match exchange_client.order(order, None).await {
-        Ok(resp) => resp,
-        Err(e) => {
-            println!("交易失败: 错误详情 - {}", e);
-            bail!(e)
-        }
-    };
-    let response = match response {
-        ExchangeResponseStatus::Ok(exchange_response) => exchange_response,
-        ExchangeResponseStatus::Err(e) => panic!("error with exchange response: {e}"),
-    };
-    let status = response.data.unwrap().statuses[0].clone();
-    let oid = match status {
-        ExchangeDataStatus::Filled(order) => order.oid,
-        ExchangeDataStatus::Resting(order) => order.oid,
-        _ => panic!("oid status错误: {status:?}"),
-    };
-    println!("--限价单--跟单购买成功： 订单id {}", oid);
-    Ok(())
+    place_order(copy, order, &exchange_client).await
 }
 
 // 立即成交
 async fn execute_spot_sell_order(
+    copy: &CopyRef,
     trade: &TradeInfo,
     exchange_client: Arc<ExchangeClient>,
     query_client: Arc<InfoClient>,
-) -> Result<()> {
-    println!("执行现货卖出 {} 跟单", trade.coin);
+) -> Result<PlacedOrder> {
+    sell_spot_balance(copy, &trade.coin, &trade.px, exchange_client, query_client).await
+}
+
+// 以参考价格下浮 5% 卖出该现货的全部余额
+async fn sell_spot_balance(
+    copy: &CopyRef,
+    coin: &str,
+    px: &str,
+    exchange_client: Arc<ExchangeClient>,
+    query_client: Arc<InfoClient>,
+) -> Result<PlacedOrder> {
     let spot_meta_path = Path::new("info").join("spot-meta.json");
     let spot_meta_json = match fs::read_to_string(spot_meta_path) {
         Ok(content) => content,
-        Err(e) => {
-            println!("无法读取spot-meta.json: {}", e);
-            bail!("无法读取资产信息文件");
-        }
+        Err(e) => bail!("无法读取资产信息文件 spot-meta.json: {e}"),
     };
     let spot_meta = match serde_json::from_str::<SpotMeta>(&spot_meta_json) {
         Ok(meta) => meta,
-        Err(e) => {
-            println!("解析spot-meta.json失败: {}", e);
-            bail!("资产信息文件格式错误");
-        }
+        Err(e) => bail!("资产信息文件格式错误: {e}"),
     };
     let spot_universe_info = spot_meta
         .universe
         .iter()
-        .find(|asset| asset.name == trade.coin)
-        .ok_or_else(|| anyhow::anyhow!("未找到交易对 {}", trade.coin))?;
+        .find(|asset| asset.name == coin)
+        .ok_or_else(|| anyhow::anyhow!("未找到交易对 {}", coin))?;
 
-    let current_spot_token_info = spot_meta.tokens[&spot_universe_info.index + 1].clone();
+    let current_spot_token_info = spot_meta.tokens[spot_universe_info.index + 1].clone();
 
     let my_all_token_balances = query_client
-        .user_token_balances(H160::from_str(MY_ADDRESS).unwrap())
+        .user_token_balances(exchange_client.account())
         .await?;
     let current_spot = my_all_token_balances
         .balances
         .iter()
         .find(|token| token.coin == current_spot_token_info.name)
-        .unwrap();
+        .ok_or_else(|| anyhow::anyhow!("没有 {} 余额", current_spot_token_info.name))?;
     let current_spot_balance = &current_spot.total;
 
-    println!(
-        "当前代币 {:#?} 余额：{}",
+    debug!(
+        "当前代币 {:?} 余额：{}",
         current_spot_token_info, current_spot_balance
     );
 
-    let adjusted_price = format_adjust_price(&trade.px, 0.95);
+    let adjusted_price = format_adjust_price(px, 0.95);
 
     // 根据基础代币的szDecimals调整数量
     let adjusted_size = if current_spot_token_info.sz_decimals == 0 {
@@ -190,53 +339,36 @@ async fn execute_spot_sell_order(
         (current_spot_balance.parse::<f64>()? * factor).floor() / factor
     };
 
-    println!("交易价格 {}  交易sz {}", adjusted_price, adjusted_size);
     let order = ClientOrderRequest {
-        asset: trade.coin.to_string(),
+        asset: coin.to_string(),
         is_buy: false,
         reduce_only: false,
         limit_px: adjusted_price,
         sz: adjusted_size,
-        cloid: None,
+        cloid: copy.cloid,
         // Alo 挂单  Ioc 立即成交  否则失败 Gtc 订单保持有效直到被取消或完全成交
         order_type: ClientOrder::Limit(ClientLimit {
             tif: "Ioc".to_string(),
         }),
     };
-    let response = match exchange_client.order(order, None).await {
-        Ok(resp) => resp,
-        Err(e) => {
-            println!("交易失败: 错误详情 - {}", e);
-            bail!(e)
-        }
-    };
-    println!("response:  {:#?}", response);
-    let response = match response {
-        ExchangeResponseStatus::Ok(exchange_response) => exchange_response,
-        ExchangeResponseStatus::Err(e) => panic!("error with exchange response: {e}"),
-    };
-    let status = response.data.unwrap().statuses[0].clone();
-    let oid = match status {
-        ExchangeDataStatus::Filled(order) => order.oid,
-        ExchangeDataStatus::Resting(order) => order.oid,
-        _ => panic!("oid status错误: {status:?}"),
-    };
-    println!("跟单卖出清仓： 订单id {}", oid);
-    Ok(())
+    place_order(copy, order, &exchange_client).await
 }
 
 async fn execute_open_long_order(
+    copy: &CopyRef,
     trade: &TradeInfo,
+    market: &MarketCache,
+    leverage: u32,
     exchange_client: Arc<ExchangeClient>,
-) -> Result<()> {
-    let leverage = env::var("LEVERAGE").unwrap().parse::<u32>().unwrap();
-    exchange_client
+) -> Result<PlacedOrder> {
+    let response = exchange_client
         .update_leverage(leverage, &trade.coin, false, None)
         .await
-        .unwrap();
-    let adjusted_price = format_adjust_price(&trade.px, 1.05);
-    let size = TRADE_AMOUNT_USDT / trade.px.parse::<f64>().unwrap();
-    let size_rounded = format!("{:.4}", size).parse::<f64>().unwrap();
+        .context("设置杠杆失败")?;
+    if let ExchangeResponseStatus::Err(e) = response {
+        bail!("设置杠杆失败: {e}");
+    }
+    let (adjusted_price, size_rounded) = market.open_price_size(trade, true)?;
 
     let order = ClientOrderRequest {
         asset: trade.coin.to_string(),
@@ -244,96 +376,64 @@ async fn execute_open_long_order(
         reduce_only: false,
         limit_px: adjusted_price,
         sz: size_rounded,
-        cloid: None,
+        cloid: copy.cloid,
         order_type: ClientOrder::Limit(ClientLimit {
             tif: "Ioc".to_string(),
         }),
     };
 
-    let response = match exchange_client.order(order, None).await {
-        Ok(resp) => resp,
-        Err(e) => {
-            println!("交易失败: 错误详情 - {}", e);
-            bail!(e)
-        }
-    };
-
-    let response = match response {
-        ExchangeResponseStatus::Ok(exchange_response) => exchange_response,
-        ExchangeResponseStatus::Err(e) => panic!("error with exchange response: {e}"),
-    };
-    let status = response.data.unwrap().statuses[0].clone();
-    let oid = match status {
-        ExchangeDataStatus::Filled(order) => order.oid,
-        ExchangeDataStatus::Resting(order) => order.oid,
-        _ => panic!("oid status错误: {status:?}"),
-    };
-    println!("--合约--做多： 订单id {}", oid);
-    Ok(())
+    place_order(copy, order, &exchange_client).await
 }
 
 async fn execute_close_long_order(
+    copy: &CopyRef,
     trade: &TradeInfo,
     exchange_client: Arc<ExchangeClient>,
     query_client: Arc<InfoClient>,
-) -> Result<()> {
+) -> Result<PlacedOrder> {
     let user_state: Vec<hyperliquid_rust_sdk::AssetPosition> = query_client
-        .user_state(MY_ADDRESS.parse()?)
+        .user_state(exchange_client.account())
         .await?
         .asset_positions;
-    println!("这是用户永续头寸资产 {:#?}", user_state);
+    debug!("这是用户永续头寸资产 {:?}", user_state);
     let position_size = user_state
         .iter()
         .find(|p| p.position.coin == trade.coin)
-        .unwrap()
+        .ok_or_else(|| anyhow::anyhow!("没有 {} 仓位", trade.coin))?
         .position
         .szi
-        .clone();
+        .parse::<f64>()
+        .context("仓位数量格式错误")?;
 
     let order = ClientOrderRequest {
         asset: trade.coin.clone(),
         is_buy: false,
         reduce_only: true,
-        limit_px: trade.px.parse::<f64>().unwrap(),
-        sz: position_size.parse::<f64>().unwrap(),
-        cloid: None,
+        limit_px: trade.px.parse::<f64>().context("成交价格式错误")?,
+        sz: position_size.abs(),
+        cloid: copy.cloid,
         order_type: ClientOrder::Limit(ClientLimit {
             tif: "Gtc".to_string(),
         }),
     };
-    let response = match exchange_client.order(order, None).await {
-        Ok(resp) => resp,
-        Err(e) => {
-            println!("交易失败: 错误详情 - {}", e);
-            bail!(e)
-        }
-    };
-    println!("response:  {:#?}", response);
-    let response = match response {
-        ExchangeResponseStatus::Ok(exchange_response) => exchange_response,
-        ExchangeResponseStatus::Err(e) => panic!("error with exchange response: {e}"),
-    };
-    let status = response.data.unwrap().statuses[0].clone();
-    let oid = match status {
-        ExchangeDataStatus::Filled(order) => order.oid,
-        ExchangeDataStatus::Resting(order) => order.oid,
-        _ => panic!("oid status错误: {status:?}"),
-    };
-    Ok(())
+    place_order(copy, order, &exchange_client).await
 }
 
 async fn execute_open_short_order(
+    copy: &CopyRef,
     trade: &TradeInfo,
+    market: &MarketCache,
+    leverage: u32,
     exchange_client: Arc<ExchangeClient>,
-) -> Result<()> {
-    let leverage = env::var("LEVERAGE").unwrap().parse::<u32>().unwrap();
-    exchange_client
+) -> Result<PlacedOrder> {
+    let response = exchange_client
         .update_leverage(leverage, &trade.coin, false, None)
         .await
-        .unwrap();
-    let adjusted_price = format_adjust_price(&trade.px, 1.05);
-    let size = TRADE_AMOUNT_USDT / trade.px.parse::<f64>().unwrap();
-    let size_rounded = format!("{:.4}", size).parse::<f64>().unwrap();
+        .context("设置杠杆失败")?;
+    if let ExchangeResponseStatus::Err(e) = response {
+        bail!("设置杠杆失败: {e}");
+    }
+    let (adjusted_price, size_rounded) = market.open_price_size(trade, false)?;
 
     let order = ClientOrderRequest {
         asset: trade.coin.to_string(),
@@ -341,78 +441,126 @@ async fn execute_open_short_order(
         reduce_only: false,
         limit_px: adjusted_price,
         sz: size_rounded,
-        cloid: None,
+        cloid: copy.cloid,
         order_type: ClientOrder::Limit(ClientLimit {
             tif: "Ioc".to_string(),
         }),
     };
 
-    let response = match exchange_client.order(order, None).await {
-        Ok(resp) => resp,
-        Err(e) => {
-            println!("交易失败: 错误详情 - {}", e);
-            bail!(e)
-        }
-    };
-
-    let response = match response {
-        ExchangeResponseStatus::Ok(exchange_response) => exchange_response,
-        ExchangeResponseStatus::Err(e) => panic!("error with exchange response: {e}"),
-    };
-    let status = response.data.unwrap().statuses[0].clone();
-    let oid = match status {
-        ExchangeDataStatus::Filled(order) => order.oid,
-        ExchangeDataStatus::Resting(order) => order.oid,
-        _ => panic!("oid status错误: {status:?}"),
-    };
-    println!("--合约--做多： 订单id {}", oid);
-    Ok(())
+    place_order(copy, order, &exchange_client).await
 }
+
 async fn execute_close_short_order(
+    copy: &CopyRef,
     trade: &TradeInfo,
     exchange_client: Arc<ExchangeClient>,
     query_client: Arc<InfoClient>,
-) -> Result<()> {
+) -> Result<PlacedOrder> {
     let user_state: Vec<hyperliquid_rust_sdk::AssetPosition> = query_client
-        .user_state(MY_ADDRESS.parse()?)
+        .user_state(exchange_client.account())
         .await?
         .asset_positions;
     let position_size = user_state
         .iter()
         .find(|p| p.position.coin == trade.coin)
-        .unwrap()
+        .ok_or_else(|| anyhow::anyhow!("没有 {} 仓位", trade.coin))?
         .position
         .szi
-        .clone();
+        .parse::<f64>()
+        .context("仓位数量格式错误")?;
 
     let order = ClientOrderRequest {
         asset: trade.coin.clone(),
         is_buy: true,
         reduce_only: true,
-        limit_px: trade.px.parse::<f64>().unwrap(),
-        sz: position_size.parse::<f64>().unwrap(),
-        cloid: None,
+        limit_px: trade.px.parse::<f64>().context("成交价格式错误")?,
+        sz: position_size.abs(),
+        cloid: copy.cloid,
         order_type: ClientOrder::Limit(ClientLimit {
             tif: "Gtc".to_string(),
         }),
     };
-    let response = match exchange_client.order(order, None).await {
-        Ok(resp) => resp,
-        Err(e) => {
-            println!("交易失败: 错误详情 - {}", e);
-            bail!(e)
-        }
-    };
-    println!("response:  {:#?}", response);
-    let response = match response {
-        ExchangeResponseStatus::Ok(exchange_response) => exchange_response,
-        ExchangeResponseStatus::Err(e) => panic!("error with exchange response: {e}"),
-    };
-    let status = response.data.unwrap().statuses[0].clone();
-    let oid = match status {
-        ExchangeDataStatus::Filled(order) => order.oid,
-        ExchangeDataStatus::Resting(order) => order.oid,
-        _ => panic!("oid status错误: {status:?}"),
-    };
-    Ok(())
+    place_order(copy, order, &exchange_client).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{notifier::Notifier, state::CopyFlags};
+    use ethers::signers::{LocalWallet, Signer};
+    use hyperliquid_rust_sdk::{Actions, MockHyperliquid};
+    use serde_json::json;
+
+    // 下单响应丢失后 SDK 重发同一请求，重发因 nonce 被拒时按 cloid 查到已下的订单，不会重复下单
+    #[tokio::test]
+    async fn test_lost_response_is_not_resubmitted() -> Result<()> {
+        let (mock, exchange_client) = MockHyperliquid::exchange_client().await?;
+        let query_client = exchange_client.info().clone();
+        let exchange_client = Arc::new(exchange_client);
+        let flags = CopyFlags {
+            enable_buy: true,
+            enable_sell: true,
+            enable_perps_buy: true,
+            enable_perps_sell: true,
+        };
+        let state = Arc::new(BotState::new(flags, Notifier::disabled()));
+        let trade: TradeInfo = serde_json::from_value(json!({
+            "coin": "PURR/USDC", "px": "0.2", "sz": "500.0", "side": "B", "time": now_ms(),
+            "startPosition": "0.0", "hash": "0x1", "oid": 1, "crossed": true, "fee": "0.0",
+            "tid": 1, "feeToken": "PURR", "closedPnl": "0.0", "dir": "Buy",
+        }))?;
+
+        mock.lose_responses(1);
+        handle_user_event(
+            H160::from_low_u64_be(7),
+            vec![trade],
+            exchange_client,
+            query_client,
+            state.clone(),
+        )
+        .await?;
+
+        let orders = mock
+            .actions()
+            .into_iter()
+            .filter(|action| matches!(action.action, Actions::Order(_)))
+            .count();
+        assert_eq!(orders, 1);
+        assert!(matches!(
+            state.recent_decisions(1)[0].outcome,
+            DecisionOutcome::Copied { .. }
+        ));
+        Ok(())
+    }
+
+    // 只用 agent 签名的客户端按主账户的仓位平仓
+    #[tokio::test]
+    async fn test_flatten_through_agent_uses_master_position() -> Result<()> {
+        let (mock, master_client) = MockHyperliquid::exchange_client().await?;
+        let master = master_client.signer.address();
+        let agent = LocalWallet::new(&mut rand::thread_rng());
+        mock.set_agent(master, agent.address());
+        mock.set_position(master, "ETH", -0.2);
+        let query_client = master_client.info().clone();
+        let mut agent_client =
+            ExchangeClient::with_info(None, agent.clone(), query_client.clone(), None, None)
+                .await?;
+        agent_client.set_account(master);
+
+        flatten_coin("ETH", Arc::new(agent_client), query_client).await?;
+
+        let actions = mock.actions();
+        let [action] = actions.as_slice() else {
+            panic!("expected one close order: {actions:?}");
+        };
+        assert_eq!(action.signer, agent.address());
+        assert!(matches!(
+            &action.action,
+            Actions::Order(bulk) if matches!(
+                bulk.orders.as_slice(),
+                [order] if order.is_buy && order.reduce_only && order.sz == "0.2"
+            )
+        ));
+        Ok(())
+    }
 }
@@ -1 +1,2 @@
+pub mod aggregate_fills;
 pub mod handle_user_event;
@@ -0,0 +1,119 @@
+use hyperliquid_rust_sdk::{ExchangeClient, ExchangeResponseStatus};
+use log::{debug, error, warn};
+use std::{env, sync::Arc, time::Duration};
+use tokio::time;
+
+use crate::{
+    notifier::{Notifier, NotifyEvent},
+    state::now_ms,
+};
+
+// 交易所要求撤单时间至少在 5 秒之后，留出请求延迟的余量
+const MIN_CANCEL_AFTER: Duration = Duration::from_secs(10);
+
+/// 撤单心跳配置：进程存活时不断把 scheduleCancel 的时间往后推，进程挂掉后所有挂单自动撤销
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatConfig {
+    /// 最后一次心跳之后多久撤销全部挂单
+    pub cancel_after: Duration,
+    pub interval: Duration,
+}
+
+impl HeartbeatConfig {
+    /// 未配置 CANCEL_AFTER_SECS 时不启用；心跳间隔默认取 cancel_after 的三分之一
+    pub fn from_env() -> Option<Self> {
+        let secs = |key: &str| env::var(key).ok().and_then(|v| v.parse::<u64>().ok());
+        let cancel_after = Duration::from_secs(secs("CANCEL_AFTER_SECS")?);
+        let interval = secs("CANCEL_HEARTBEAT_SECS")
+            .map(Duration::from_secs)
+            .unwrap_or(cancel_after / 3);
+        Some(HeartbeatConfig {
+            cancel_after,
+            interval,
+        })
+    }
+}
+
+/// 周期性调用 schedule_cancel，把撤单时间设为 now + cancel_after
+pub async fn run_cancel_heartbeat(
+    exchange_client: Arc<ExchangeClient>,
+    mut config: HeartbeatConfig,
+    notifier: Notifier,
+) {
+    if config.cancel_after < MIN_CANCEL_AFTER {
+        warn!(
+            "CANCEL_AFTER_SECS 过小，调整为 {}s",
+            MIN_CANCEL_AFTER.as_secs()
+        );
+        config.cancel_after = MIN_CANCEL_AFTER;
+    }
+    // 间隔必须明显小于 cancel_after，否则两次心跳之间挂单就会被撤掉
+    let interval = config
+        .interval
+        .min(config.cancel_after / 2)
+        .max(Duration::from_secs(1));
+    let mut ticker = time::interval(interval);
+    ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let cancel_time = now_ms() + config.cancel_after.as_millis() as u64;
+        let error = match exchange_client
+            .schedule_cancel(Some(cancel_time), None)
+            .await
+        {
+            Ok(ExchangeResponseStatus::Ok(_)) => {
+                debug!("撤单时间推迟到 {cancel_time}");
+                continue;
+            }
+            Ok(ExchangeResponseStatus::Err(e)) => e,
+            Err(e) => e.to_string(),
+        };
+        error!("撤单心跳失败: {error}");
+        notifier.notify(NotifyEvent::Error {
+            context: "schedule_cancel".to_string(),
+            error,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyperliquid_rust_sdk::{Actions, MockHyperliquid};
+
+    #[tokio::test]
+    async fn test_heartbeat_pushes_cancel_time() -> anyhow::Result<()> {
+        let (mock, exchange_client) = MockHyperliquid::exchange_client().await?;
+        let exchange_client = Arc::new(exchange_client);
+
+        let config = HeartbeatConfig {
+            cancel_after: Duration::from_secs(30),
+            interval: Duration::from_secs(1),
+        };
+        let started = now_ms();
+        let heartbeat = tokio::spawn(run_cancel_heartbeat(
+            exchange_client,
+            config,
+            Notifier::disabled(),
+        ));
+        let schedule_times = || -> Vec<u64> {
+            mock.actions()
+                .iter()
+                .filter_map(|action| match &action.action {
+                    Actions::ScheduleCancel(schedule) => schedule.time,
+                    _ => None,
+                })
+                .collect()
+        };
+        let deadline = time::Instant::now() + Duration::from_secs(5);
+        while schedule_times().len() < 2 && time::Instant::now() < deadline {
+            time::sleep(Duration::from_millis(50)).await;
+        }
+        heartbeat.abort();
+
+        let times = schedule_times();
+        assert!(times.len() >= 2);
+        assert!(times[0] >= started + 30_000 && times[1] > times[0]);
+        Ok(())
+    }
+}
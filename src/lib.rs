@@ -1,3 +1,15 @@
+pub mod agent;
+pub mod cloid;
 pub mod constants;
+pub mod control;
+pub mod event_log;
+pub mod feed;
 pub mod handler;
+pub mod heartbeat;
+pub mod market;
+pub mod notifier;
+pub mod reconcile;
+pub mod risk;
+pub mod signer;
+pub mod state;
 pub mod utils;
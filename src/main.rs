@@ -1,81 +1,164 @@
 use std::{env, str::FromStr, sync::Arc, time::Duration};
 
-use ethers::{signers::LocalWallet, types::H160};
-use hype_copy_trade::{handler::handle_user_event::handle_user_event, utils::info_init};
-use hyperliquid_rust_sdk::{BaseUrl, ExchangeClient, InfoClient, Message, Subscription};
+use ethers::types::H160;
+use hype_copy_trade::{
+    agent::run_agent_expiry_watch,
+    control, event_log,
+    feed::{run_leader_feed, FeedConfig},
+    handler::{
+        aggregate_fills::{aggregation_window_from_env, spawn_fill_aggregator},
+        handle_user_event::handle_user_event,
+    },
+    heartbeat::{run_cancel_heartbeat, HeartbeatConfig},
+    notifier::{Notifier, NotifierConfig, NotifyEvent},
+    risk::RiskLimits,
+    signer::{signer_from_env, LoadedSigner},
+    state::{BotState, CopyFlags},
+    utils::{info_init, network_from_env},
+};
+use hyperliquid_rust_sdk::{ExchangeClient, FileNonces, InfoClient, Signer};
 
 use dotenv::dotenv;
-use log::debug;
-use tokio::sync::mpsc::unbounded_channel;
+use log::{debug, error};
+
+// agent 过期前多久开始提醒轮换
+const AGENT_EXPIRY_WARNING: Duration = Duration::from_secs(24 * 60 * 60);
+
 #[tokio::main]
 async fn main() {
     dotenv().ok();
     env_logger::init();
+    if let Err(e) = event_log::init_from_env() {
+        error!("无法打开事件日志文件: {e}");
+    }
     let is_test = env::var("TEST").unwrap().parse::<bool>().unwrap();
-    let enable_sell = env::var("ENABLE_SELL").unwrap().parse::<bool>().unwrap();
-    let enable_buy = env::var("ENABLE_BUY").unwrap().parse::<bool>().unwrap();
+    let flags = CopyFlags::from_env().unwrap();
     let smart_address = env::var("SMART_ADDRESS").unwrap();
     println!(
         "是否为测试环境: {} 是否跟卖: {} 是否跟买: {}",
-        is_test, enable_sell, enable_buy
+        is_test, flags.enable_sell, flags.enable_buy
     );
-    let network = match is_test {
-        true => BaseUrl::Testnet,
-        false => BaseUrl::Mainnet,
-    };
-    let mut info_client = InfoClient::new(None, Some(network)).await.unwrap();
-    let query_client: InfoClient = InfoClient::new(None, Some(network)).await.unwrap();
+    let notifier = Notifier::start(NotifierConfig::from_env());
+    let state = Arc::new(BotState::new(flags, notifier.clone()));
+    state.set_risk_limits(RiskLimits::from_env());
+    state.set_leverage(env::var("LEVERAGE").unwrap().parse::<u32>().unwrap());
+    let network = network_from_env(is_test);
+    let query_client: InfoClient = InfoClient::new(None, Some(network.clone())).await.unwrap();
     let query_client: Arc<InfoClient> = Arc::new(query_client);
     let user = H160::from_str(&smart_address).unwrap();
+    // 签名方式见 signer_from_env，配置 keystore、远程签名或 agent 后进程中不再有明文主账户私钥
+    let LoadedSigner { signer, agent } = signer_from_env().await.unwrap();
+    // 使用 agent 时按地址计算的请求额度属于主账户
+    let account = agent
+        .as_ref()
+        .map_or(signer.address(), |record| record.master);
+    if let Some(record) = agent {
+        tokio::spawn(run_agent_expiry_watch(
+            record,
+            AGENT_EXPIRY_WARNING,
+            notifier.clone(),
+        ));
+    }
 
-    let (sender, mut receiver) = unbounded_channel();
-
-    let _ = info_client
-        .subscribe(Subscription::UserEvents { user }, sender.clone())
-        .await
-        .unwrap();
-    let wallet: LocalWallet = env::var("PRIVATE_KEY").unwrap().parse().unwrap();
+    // 与查询共用同一个 InfoClient，市价单不再每次新建连接
+    let mut exchange_client =
+        ExchangeClient::with_info(None, signer, query_client.clone(), None, None)
+            .await
+            .unwrap();
+    // agent 下的订单属于主账户，发送结果不明时按主账户查询订单
+    exchange_client.set_account(account);
+    // 多个进程共用同一签名地址时，nonce 记录在加锁的文件中，避免重复
+    if let Ok(path) = env::var("NONCE_FILE") {
+        exchange_client.set_nonce_provider(Arc::new(FileNonces::new(path)));
+    }
+    let exchange_client = Arc::new(exchange_client);
 
-    let exchange_client = ExchangeClient::new(None, wallet, Some(network), None, None)
+    // 盘口与最大可下单量走 websocket 推送，下单定量时直接读缓存
+    let market_client = InfoClient::with_reconnect(None, Some(network.clone()))
         .await
         .unwrap();
-    let exchange_client = Arc::new(exchange_client);
+    state.market().start(market_client, account);
 
     // 更新Info数据
     let query_info_client = query_client.clone();
+    let info_notifier = notifier.clone();
     tokio::spawn(async move {
         loop {
             if let Err(e) = info_init(query_info_client.clone()).await {
                 eprintln!("更新spot_meta失败: {}", e);
-            } else {
+                info_notifier.notify(NotifyEvent::Error {
+                    context: "info_init".to_string(),
+                    error: e.to_string(),
+                });
+            }
+            // 同步按地址计算的请求额度，额度用完时下单在本地直接失败而不是被交易所拒绝
+            match query_info_client.sync_rate_limit(account).await {
+                Ok(limit) => debug!(
+                    "请求额度 {}/{}，IP 权重剩余 {}",
+                    limit.n_requests_used,
+                    limit.n_requests_cap,
+                    query_info_client.http_client.rate_limiter.remaining()
+                ),
+                Err(e) => error!("同步请求额度失败: {e}"),
             }
             tokio::time::sleep(Duration::from_secs(15)).await;
         }
     });
 
-    // this loop ends when we unsubscribe
-    while let Some(message) = receiver.recv().await {
-        match message {
-            Message::User(user) => match user.data {
-                hyperliquid_rust_sdk::UserData::Fills(trade_infos) => {
-                    let exchange_client_clone = exchange_client.clone();
-                    let trade_infos_clone = trade_infos.clone();
-                    let query_client_clone = query_client.clone();
-                    tokio::spawn(async {
-                        let _ = handle_user_event(
-                            trade_infos_clone,
-                            exchange_client_clone,
-                            query_client_clone,
-                        )
-                        .await;
-                    });
-                }
-                _ => {}
-            },
-            Message::Pong => {
-                debug!("pong");
+    // 撤单心跳：进程异常退出后，交易所在 CANCEL_AFTER_SECS 秒后撤销全部挂单
+    if let Some(config) = HeartbeatConfig::from_env() {
+        tokio::spawn(run_cancel_heartbeat(
+            exchange_client.clone(),
+            config,
+            notifier.clone(),
+        ));
+    }
+
+    // 本地控制接口，未配置 CONTROL_TOKEN 时不启动
+    if let Ok(token) = env::var("CONTROL_TOKEN") {
+        let addr =
+            env::var("CONTROL_ADDR").unwrap_or_else(|_| control::DEFAULT_CONTROL_ADDR.to_string());
+        let state = state.clone();
+        let exchange_client = exchange_client.clone();
+        let query_client = query_client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = control::serve(&addr, token, state, exchange_client, query_client).await
+            {
+                error!("控制接口退出: {e}");
             }
-            _ => {}
-        }
+        });
     }
+
+    // 同一订单拆出的多笔成交先在窗口内合并，再逐组跟单
+    let fill_sender = {
+        let exchange_client = exchange_client.clone();
+        let query_client = query_client.clone();
+        let state = state.clone();
+        spawn_fill_aggregator(aggregation_window_from_env(), move |trade_infos| {
+            let exchange_client_clone = exchange_client.clone();
+            let query_client_clone = query_client.clone();
+            let state_clone = state.clone();
+            tokio::spawn(async move {
+                let _ = handle_user_event(
+                    user,
+                    trade_infos,
+                    exchange_client_clone,
+                    query_client_clone,
+                    state_clone,
+                )
+                .await;
+            });
+        })
+    };
+
+    // 断线重连、超时检测与补单都在 run_leader_feed 中处理
+    run_leader_feed(
+        user,
+        network,
+        query_client,
+        FeedConfig::from_env(),
+        fill_sender,
+        notifier,
+    )
+    .await;
 }
@@ -0,0 +1,176 @@
+use anyhow::{bail, Result};
+use ethers::types::H160;
+use futures::{Stream, StreamExt};
+use hyperliquid_rust_sdk::{BboData, InfoClient, TradeInfo, UserActiveAssetData};
+use log::{debug, error, warn};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, RwLock},
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+use crate::{constants::TRADE_AMOUNT_USDT, state::now_ms, utils::format_adjust_price};
+
+// 超过该时间未更新的盘口不再用于定价
+const MAX_BBO_AGE_MS: u64 = 5_000;
+
+/// websocket 推送的盘口与本账户可下单数量，下单前定价和定量时读取，省去每笔一次查询
+#[derive(Debug, Default)]
+pub struct MarketCache {
+    bbo: RwLock<HashMap<String, BboData>>,
+    asset_data: RwLock<HashMap<String, UserActiveAssetData>>,
+    watched: Mutex<HashSet<String>>,
+    // 未调用 start 时为空，watch 不做任何事，定价退回聪明钱成交价
+    subscribe: Mutex<Option<UnboundedSender<(String, bool)>>>,
+}
+
+impl MarketCache {
+    /// 用单独的 InfoClient 订阅被 watch 的币种，account 是下单账户，用于 activeAssetData
+    pub fn start(self: &Arc<Self>, mut info_client: InfoClient, account: H160) {
+        let (sender, mut coins) = unbounded_channel::<(String, bool)>();
+        *self.subscribe.lock().unwrap() = Some(sender);
+        let cache = self.clone();
+        tokio::spawn(async move {
+            while let Some((coin, is_perp)) = coins.recv().await {
+                match info_client.subscribe_bbo(coin.clone()).await {
+                    Ok(stream) => cache.forward(stream, MarketCache::set_bbo),
+                    Err(e) => {
+                        error!("订阅 {coin} 盘口失败: {e}");
+                        cache.watched.lock().unwrap().remove(&coin);
+                        continue;
+                    }
+                }
+                // 杠杆与最大可下单量只有永续才有
+                if !is_perp {
+                    continue;
+                }
+                match info_client
+                    .subscribe_active_asset_data(account, coin.clone())
+                    .await
+                {
+                    Ok(stream) => cache.forward(stream, MarketCache::set_asset_data),
+                    Err(e) => error!("订阅 {coin} 可交易数量失败: {e}"),
+                }
+            }
+        });
+    }
+
+    fn forward<T: Send + 'static>(
+        self: &Arc<Self>,
+        mut stream: impl Stream<Item = T> + Unpin + Send + 'static,
+        store: fn(&MarketCache, T),
+    ) {
+        let cache = self.clone();
+        tokio::spawn(async move {
+            while let Some(item) = stream.next().await {
+                store(&cache, item);
+            }
+            debug!("行情订阅结束");
+        });
+    }
+
+    /// 开始缓存该币种的行情，第一次调用时订阅，之后的成交即可使用缓存
+    pub fn watch(&self, coin: &str, is_perp: bool) {
+        if !self.watched.lock().unwrap().insert(coin.to_string()) {
+            return;
+        }
+        if let Some(subscribe) = self.subscribe.lock().unwrap().as_ref() {
+            let _ = subscribe.send((coin.to_string(), is_perp));
+        }
+    }
+
+    /// 最近的盘口，过期时返回 None
+    pub fn bbo(&self, coin: &str) -> Option<BboData> {
+        self.bbo
+            .read()
+            .unwrap()
+            .get(coin)
+            .filter(|bbo| now_ms().saturating_sub(bbo.time) <= MAX_BBO_AGE_MS)
+            .cloned()
+    }
+
+    pub fn asset_data(&self, coin: &str) -> Option<UserActiveAssetData> {
+        self.asset_data.read().unwrap().get(coin).cloned()
+    }
+
+    pub fn set_bbo(&self, bbo: BboData) {
+        self.bbo.write().unwrap().insert(bbo.coin.clone(), bbo);
+    }
+
+    pub fn set_asset_data(&self, data: UserActiveAssetData) {
+        self.asset_data
+            .write()
+            .unwrap()
+            .insert(data.coin.clone(), data);
+    }
+
+    /// 开仓的限价和数量：有新鲜盘口时以对手价为参考价，否则用聪明钱成交价；
+    /// 数量不超过 activeAssetData 给出的该方向最大可下单量
+    pub fn open_price_size(&self, trade: &TradeInfo, is_buy: bool) -> Result<(f64, f64)> {
+        let bbo = self.bbo(&trade.coin);
+        let level = bbo
+            .as_ref()
+            .and_then(|bbo| if is_buy { bbo.ask() } else { bbo.bid() });
+        let reference_px = level.map_or(trade.px.as_str(), |level| level.px.as_str());
+        let limit_px = format_adjust_price(reference_px, 1.05);
+        let size = TRADE_AMOUNT_USDT / reference_px.parse::<f64>()?;
+        let mut size = format!("{:.4}", size).parse::<f64>()?;
+        if let Some(data) = self.asset_data(&trade.coin) {
+            let max_size = data.max_trade_szs[usize::from(!is_buy)].parse::<f64>()?;
+            if size > max_size {
+                warn!("{} 下单数量 {size} 超过最大可下单量 {max_size}", trade.coin);
+                size = max_size;
+            }
+        }
+        if size <= 0.0 {
+            bail!("{} 没有可下单数量", trade.coin);
+        }
+        Ok((limit_px, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn trade() -> TradeInfo {
+        serde_json::from_value(json!({
+            "coin": "ETH", "side": "B", "px": "3000.0", "sz": "1.0", "time": 0,
+            "hash": "0x0", "startPosition": "0.0", "dir": "Open Long", "closedPnl": "0.0",
+            "oid": 1, "crossed": true, "fee": "0.0", "tid": 1, "feeToken": "USDC",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_open_price_size_uses_bbo_and_max_size() {
+        let market = MarketCache::default();
+        // 没有行情时按聪明钱成交价
+        assert_eq!(
+            market.open_price_size(&trade(), true).unwrap(),
+            (3150.0, 0.01)
+        );
+
+        market.set_bbo(
+            serde_json::from_value(json!({
+                "coin": "ETH", "time": now_ms(),
+                "bbo": [{"px": "2990.0", "sz": "1", "n": 1}, {"px": "3000.0", "sz": "1", "n": 1}],
+            }))
+            .unwrap(),
+        );
+        let (px, sz) = market.open_price_size(&trade(), false).unwrap();
+        assert_eq!((px, sz), (3139.5, 0.01));
+
+        market.set_asset_data(
+            serde_json::from_value(json!({
+                "user": H160::zero(), "coin": "ETH",
+                "leverage": {"type": "cross", "value": 5},
+                "maxTradeSzs": ["0.004", "0.0"], "availableToTrade": ["12.0", "0.0"],
+            }))
+            .unwrap(),
+        );
+        assert_eq!(market.open_price_size(&trade(), true).unwrap().1, 0.004);
+        assert!(market.open_price_size(&trade(), false).is_err());
+    }
+}
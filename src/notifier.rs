@@ -0,0 +1,374 @@
+use log::{error, warn};
+use reqwest::{Client, Url};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{env, fmt, time::Duration};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    time::{self, Instant},
+};
+
+use crate::{
+    risk::RiskBreach,
+    state::{Decision, DecisionOutcome},
+};
+
+pub const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+// Telegram 与 Discord 单条消息的长度上限
+const TELEGRAM_MAX_TEXT: usize = 4096;
+const DISCORD_MAX_CONTENT: usize = 2000;
+
+/// 需要推送的通知事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifyEvent {
+    CopyDecision(Decision),
+    Error { context: String, error: String },
+    RiskBreach(RiskBreach),
+    WebsocketDisconnected,
+}
+
+impl NotifyEvent {
+    pub fn render(&self) -> String {
+        match self {
+            NotifyEvent::CopyDecision(decision) => {
+                let leader = format!(
+                    "leader {:?} {} {} px {} sz {}",
+                    decision.leader, decision.dir, decision.coin, decision.px, decision.sz
+                );
+                match &decision.outcome {
+                    DecisionOutcome::Copied { oid, sz, px } => {
+                        format!("[copied] {leader} | ours px {px} sz {sz} oid {oid}")
+                    }
+                    DecisionOutcome::Skipped { reason } => {
+                        format!("[skipped] {leader} | {reason}")
+                    }
+                    DecisionOutcome::Failed { error } => {
+                        format!("[copy failed] {leader} | {error}")
+                    }
+                }
+            }
+            NotifyEvent::Error { context, error } => format!("[error] {context}: {error}"),
+            NotifyEvent::RiskBreach(breach) => format!(
+                "[risk] leader {:?} {} {} | notional {:.2} would exceed limit {}",
+                breach.leader, breach.dir, breach.coin, breach.notional, breach.limit
+            ),
+            NotifyEvent::WebsocketDisconnected => "[ws] leader feed disconnected".to_string(),
+        }
+    }
+}
+
+/// 通知的推送目标，Debug 输出只含类型和名称，不含 token 与 webhook 地址
+#[derive(Clone)]
+pub enum NotifySink {
+    /// 通用 JSON webhook，推送 `{"text": ..., "events": [...]}`
+    Webhook {
+        url: String,
+    },
+    Telegram {
+        api_url: String,
+        bot_token: String,
+        chat_id: String,
+    },
+    Discord {
+        url: String,
+    },
+}
+
+impl fmt::Debug for NotifySink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotifySink::Webhook { url } => write!(f, "webhook {}", host(url)),
+            NotifySink::Telegram { chat_id, .. } => write!(f, "telegram chat {chat_id}"),
+            NotifySink::Discord { .. } => write!(f, "discord"),
+        }
+    }
+}
+
+fn host(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+impl NotifySink {
+    fn request(&self, events: &[NotifyEvent]) -> (String, Value) {
+        let text = events
+            .iter()
+            .map(NotifyEvent::render)
+            .collect::<Vec<_>>()
+            .join("\n");
+        match self {
+            NotifySink::Webhook { url } => (url.clone(), json!({ "text": text, "events": events })),
+            NotifySink::Telegram {
+                api_url,
+                bot_token,
+                chat_id,
+            } => (
+                format!("{api_url}/bot{bot_token}/sendMessage"),
+                json!({
+                    "chat_id": chat_id,
+                    "text": truncate(&text, TELEGRAM_MAX_TEXT),
+                }),
+            ),
+            NotifySink::Discord { url } => (
+                url.clone(),
+                json!({ "content": truncate(&text, DISCORD_MAX_CONTENT) }),
+            ),
+        }
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[derive(Debug, Clone)]
+pub struct NotifierConfig {
+    pub sinks: Vec<NotifySink>,
+    /// 收到第一条事件后等待多久再合并发送
+    pub batch_window: Duration,
+    /// 两次发送之间的最小间隔
+    pub min_interval: Duration,
+    pub max_batch: usize,
+}
+
+impl NotifierConfig {
+    pub fn from_env() -> Self {
+        let mut sinks = Vec::new();
+        if let Ok(url) = env::var("NOTIFY_WEBHOOK_URL") {
+            sinks.push(NotifySink::Webhook { url });
+        }
+        if let (Ok(bot_token), Ok(chat_id)) = (
+            env::var("NOTIFY_TELEGRAM_BOT_TOKEN"),
+            env::var("NOTIFY_TELEGRAM_CHAT_ID"),
+        ) {
+            sinks.push(NotifySink::Telegram {
+                api_url: TELEGRAM_API_URL.to_string(),
+                bot_token,
+                chat_id,
+            });
+        }
+        if let Ok(url) = env::var("NOTIFY_DISCORD_WEBHOOK_URL") {
+            sinks.push(NotifySink::Discord { url });
+        }
+        let millis = |key: &str, default: u64| {
+            Duration::from_millis(
+                env::var(key)
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default),
+            )
+        };
+        NotifierConfig {
+            sinks,
+            batch_window: millis("NOTIFY_BATCH_MS", 2000),
+            min_interval: millis("NOTIFY_MIN_INTERVAL_MS", 1000),
+            max_batch: 20,
+        }
+    }
+}
+
+/// 通知句柄，事件在后台任务中合并、限流后推送到各个目标
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+    sender: Option<UnboundedSender<NotifyEvent>>,
+}
+
+impl Notifier {
+    pub fn disabled() -> Self {
+        Notifier { sender: None }
+    }
+
+    pub fn start(config: NotifierConfig) -> Self {
+        if config.sinks.is_empty() {
+            return Self::disabled();
+        }
+        let (sender, receiver) = unbounded_channel();
+        tokio::spawn(run(config, receiver));
+        Notifier {
+            sender: Some(sender),
+        }
+    }
+
+    pub fn notify(&self, event: NotifyEvent) {
+        if let Some(sender) = &self.sender {
+            if sender.send(event).is_err() {
+                warn!("notifier task stopped, dropping event");
+            }
+        }
+    }
+}
+
+async fn run(config: NotifierConfig, mut receiver: UnboundedReceiver<NotifyEvent>) {
+    let client = Client::new();
+    let mut last_sent: Option<Instant> = None;
+    while let Some(first) = receiver.recv().await {
+        let mut batch = vec![first];
+        let deadline = Instant::now() + config.batch_window;
+        while batch.len() < config.max_batch {
+            match time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(event)) => batch.push(event),
+                _ => break,
+            }
+        }
+        if let Some(last_sent) = last_sent {
+            time::sleep_until(last_sent + config.min_interval).await;
+        }
+        for sink in &config.sinks {
+            let (url, body) = sink.request(&batch);
+            match client.post(&url).json(&body).send().await {
+                Ok(response) if !response.status().is_success() => {
+                    warn!("notify sink {sink:?} returned {}", response.status());
+                }
+                Ok(_) => {}
+                // 请求地址里带有 token，不能出现在日志中
+                Err(e) => error!("notify sink {sink:?} failed: {}", e.without_url()),
+            }
+        }
+        last_sent = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::Uri, routing::post, Json, Router};
+    use ethers::types::H160;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    type Received = Arc<Mutex<Vec<(String, Value)>>>;
+
+    // 本地 HTTP 替身，记录收到的每个请求
+    async fn stand_in() -> (String, Received) {
+        let received: Received = Arc::default();
+        let app = Router::new()
+            .fallback(post(
+                |State(received): State<Received>, uri: Uri, Json(body): Json<Value>| async move {
+                    received
+                        .lock()
+                        .unwrap()
+                        .push((uri.path().to_string(), body));
+                },
+            ))
+            .with_state(received.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{addr}"), received)
+    }
+
+    async fn wait_for(received: &Received, count: usize) -> Vec<(String, Value)> {
+        for _ in 0..100 {
+            if received.lock().unwrap().len() >= count {
+                break;
+            }
+            time::sleep(Duration::from_millis(20)).await;
+        }
+        received.lock().unwrap().clone()
+    }
+
+    fn config(sinks: Vec<NotifySink>) -> NotifierConfig {
+        NotifierConfig {
+            sinks,
+            batch_window: Duration::from_millis(100),
+            min_interval: Duration::from_millis(0),
+            max_batch: 20,
+        }
+    }
+
+    fn copied() -> NotifyEvent {
+        NotifyEvent::CopyDecision(Decision {
+            time: 1,
+            leader: H160::zero(),
+            tid: 7,
+            coin: "ETH".to_string(),
+            dir: "Open Long".to_string(),
+            px: "3000".to_string(),
+            sz: "2".to_string(),
+            outcome: DecisionOutcome::Copied {
+                oid: 42,
+                sz: 0.01,
+                px: 3150.0,
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_webhook_batches_events() {
+        let (url, received) = stand_in().await;
+        let notifier = Notifier::start(config(vec![NotifySink::Webhook {
+            url: format!("{url}/hook"),
+        }]));
+        notifier.notify(copied());
+        notifier.notify(NotifyEvent::WebsocketDisconnected);
+        notifier.notify(NotifyEvent::Error {
+            context: "info_init".to_string(),
+            error: "timeout".to_string(),
+        });
+        notifier.notify(NotifyEvent::RiskBreach(RiskBreach {
+            leader: H160::zero(),
+            coin: "ETH".to_string(),
+            dir: "Open Long".to_string(),
+            notional: 130.0,
+            limit: 100.0,
+        }));
+
+        let received = wait_for(&received, 1).await;
+        assert_eq!(received.len(), 1);
+        let (path, body) = &received[0];
+        assert_eq!(path, "/hook");
+        let events = body["events"].as_array().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[3]["type"], "risk_breach");
+        assert_eq!(events[3]["limit"], 100.0);
+        assert_eq!(events[0]["type"], "copy_decision");
+        assert_eq!(events[0]["outcome"], "copied");
+        assert_eq!(events[0]["oid"], 42);
+        assert_eq!(events[1]["type"], "websocket_disconnected");
+        assert!(body["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("[copied] leader 0x0000000000000000000000000000000000000000 Open Long ETH px 3000 sz 2 | ours px 3150 sz 0.01 oid 42"));
+    }
+
+    #[tokio::test]
+    async fn test_telegram_and_discord_format() {
+        let (url, received) = stand_in().await;
+        let sinks = vec![
+            NotifySink::Telegram {
+                api_url: url.clone(),
+                bot_token: "123:abc".to_string(),
+                chat_id: "-100".to_string(),
+            },
+            NotifySink::Discord {
+                url: format!("{url}/discord"),
+            },
+        ];
+        // 日志里只出现类型和名称
+        assert_eq!(format!("{sinks:?}"), "[telegram chat -100, discord]");
+        let notifier = Notifier::start(config(sinks));
+        notifier.notify(NotifyEvent::WebsocketDisconnected);
+
+        let received = wait_for(&received, 2).await;
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].0, "/bot123:abc/sendMessage");
+        assert_eq!(received[0].1["chat_id"], "-100");
+        assert_eq!(received[0].1["text"], "[ws] leader feed disconnected");
+        assert_eq!(received[1].0, "/discord");
+        assert_eq!(received[1].1["content"], "[ws] leader feed disconnected");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("abc", 3), "abc");
+        assert_eq!(truncate("跟单通知", 3), "跟单…");
+    }
+}
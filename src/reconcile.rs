@@ -0,0 +1,172 @@
+use anyhow::Result;
+use ethers::types::H160;
+use hyperliquid_rust_sdk::InfoClient;
+use log::warn;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::state::BotState;
+
+// 小于该值的差异视为舍入误差
+const DRIFT_TOLERANCE: f64 = 1e-8;
+
+/// 账本与交易所实际仓位不一致的币种
+#[derive(Debug, Clone, Serialize)]
+pub struct Drift {
+    pub coin: String,
+    pub ledger: f64,
+    pub exchange: f64,
+}
+
+/// 对比账本与交易所的永续仓位和现货余额，返回不一致的币种并以交易所为准更新账本。
+/// 现货按交易对记账，对账时换算成基础代币的余额。`address` 为下单账户
+pub async fn reconcile(
+    address: H160,
+    state: &BotState,
+    query_client: &InfoClient,
+) -> Result<Vec<Drift>> {
+    let user_state = query_client.user_state(address).await?;
+    let mut exchange: HashMap<String, f64> = user_state
+        .asset_positions
+        .iter()
+        .map(|p| {
+            (
+                p.position.coin.clone(),
+                p.position.szi.parse().unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    let ledger = state.ledger();
+    if ledger
+        .iter()
+        .any(|entry| !exchange.contains_key(&entry.coin))
+    {
+        let spot_meta = query_client.spot_meta().await?;
+        let balances = query_client.user_token_balances(address).await?;
+        for entry in &ledger {
+            let Some(pair) = spot_meta
+                .universe
+                .iter()
+                .find(|pair| pair.name == entry.coin)
+            else {
+                continue;
+            };
+            let Some(token) = spot_meta.tokens.get(pair.tokens[0]) else {
+                continue;
+            };
+            let total = balances
+                .balances
+                .iter()
+                .find(|balance| balance.coin == token.name)
+                .map_or(0.0, |balance| balance.total.parse().unwrap_or_default());
+            exchange.insert(entry.coin.clone(), total);
+        }
+    }
+
+    let mut coins: Vec<&String> = exchange.keys().collect();
+    for entry in &ledger {
+        if !exchange.contains_key(&entry.coin) {
+            coins.push(&entry.coin);
+        }
+    }
+    let mut drifts = Vec::new();
+    for coin in coins {
+        let ledger_position = ledger
+            .iter()
+            .find(|entry| &entry.coin == coin)
+            .map_or(0.0, |entry| entry.position);
+        let exchange_position = exchange.get(coin).copied().unwrap_or_default();
+        if (ledger_position - exchange_position).abs() > DRIFT_TOLERANCE {
+            warn!("{coin} 账本仓位 {ledger_position} 与交易所 {exchange_position} 不一致");
+            state.set_ledger_position(coin, exchange_position);
+            drifts.push(Drift {
+                coin: coin.clone(),
+                ledger: ledger_position,
+                exchange: exchange_position,
+            });
+        }
+    }
+    drifts.sort_by(|a, b| a.coin.cmp(&b.coin));
+    Ok(drifts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        notifier::Notifier,
+        state::{now_ms, CopyFlags, Decision, DecisionOutcome},
+    };
+    use hyperliquid_rust_sdk::MockHyperliquid;
+    use serde_json::json;
+
+    fn copied(coin: &str, dir: &str, sz: f64) -> Decision {
+        Decision {
+            time: now_ms(),
+            leader: H160::zero(),
+            tid: 1,
+            coin: coin.to_string(),
+            dir: dir.to_string(),
+            px: "1.0".to_string(),
+            sz: sz.to_string(),
+            outcome: DecisionOutcome::Copied {
+                oid: 1,
+                sz,
+                px: 1.0,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_resets_drifted_positions() -> Result<()> {
+        let mock = MockHyperliquid::new(false);
+        let account = H160::from_low_u64_be(1);
+        let query_client = InfoClient::new(None, Some(mock.serve_local().await?)).await?;
+        let summary = json!({
+            "accountValue": "1000.0", "totalMarginUsed": "0.0",
+            "totalNtlPos": "0.0", "totalRawUsd": "1000.0",
+        });
+        mock.set_info(
+            "clearinghouseState",
+            json!({
+                "assetPositions": [{ "type": "oneWay", "position": {
+                    "coin": "ETH", "entryPx": "3000.0", "liquidationPx": null,
+                    "leverage": { "type": "cross", "value": 5 },
+                    "marginUsed": "300.0", "positionValue": "1500.0", "returnOnEquity": "0.0",
+                    "szi": "0.5", "unrealizedPnl": "0.0", "maxLeverage": 50,
+                    "cumFunding": { "allTime": "0.0", "sinceOpen": "0.0", "sinceChange": "0.0" },
+                }}],
+                "crossMarginSummary": summary,
+                "marginSummary": summary,
+                "withdrawable": "1000.0",
+            }),
+        );
+        mock.set_info(
+            "spotClearinghouseState",
+            json!({ "balances": [{ "coin": "PURR", "hold": "0.0", "total": "3.0" }] }),
+        );
+        let flags = CopyFlags {
+            enable_buy: true,
+            enable_sell: true,
+            enable_perps_buy: true,
+            enable_perps_sell: true,
+        };
+        let state = BotState::new(flags, Notifier::disabled());
+        state.record_decision(copied("ETH", "Open Long", 0.4));
+        state.record_decision(copied("ETH", "Close Long", 0.1));
+        state.record_decision(copied("PURR/USDC", "Buy", 3.0));
+
+        let drifts = reconcile(account, &state, &query_client).await?;
+        assert_eq!(drifts.len(), 1);
+        assert_eq!(drifts[0].coin, "ETH");
+        assert!((drifts[0].ledger - 0.3).abs() < 1e-9);
+        assert_eq!(drifts[0].exchange, 0.5);
+        let ledger = state.ledger();
+        assert_eq!(ledger[0].coin, "ETH");
+        assert_eq!(ledger[0].position, 0.5);
+        assert_eq!(ledger[0].copies, 2);
+        assert!(reconcile(account, &state, &query_client).await?.is_empty());
+        Ok(())
+    }
+}
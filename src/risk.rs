@@ -0,0 +1,78 @@
+use ethers::types::H160;
+use hyperliquid_rust_sdk::TradeInfo;
+use serde::Serialize;
+use std::env;
+
+use crate::constants::TRADE_AMOUNT_USDT;
+
+/// 风控上限，未配置的项不检查
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct RiskLimits {
+    /// 单个币种跟单仓位的最大名义价值（USDC）
+    pub max_coin_notional: Option<f64>,
+}
+
+impl RiskLimits {
+    pub fn from_env() -> Self {
+        RiskLimits {
+            max_coin_notional: env::var("MAX_COIN_NOTIONAL_USD")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+        }
+    }
+
+    /// 按账本仓位检查一笔开仓跟单，超出上限时返回越限详情；平仓和卖出只会减仓，不检查
+    pub fn check(&self, leader: H160, trade: &TradeInfo, position: f64) -> Option<RiskBreach> {
+        let limit = self.max_coin_notional?;
+        if !matches!(trade.dir.as_str(), "Buy" | "Open Long" | "Open Short") {
+            return None;
+        }
+        let px: f64 = trade.px.parse().ok()?;
+        let notional = position.abs() * px + TRADE_AMOUNT_USDT;
+        (notional > limit).then(|| RiskBreach {
+            leader,
+            coin: trade.coin.clone(),
+            dir: trade.dir.clone(),
+            notional,
+            limit,
+        })
+    }
+}
+
+/// 跟单后仓位将超出风控上限
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskBreach {
+    pub leader: H160,
+    pub coin: String,
+    pub dir: String,
+    /// 跟单后该币种的预计名义价值
+    pub notional: f64,
+    pub limit: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_coin_notional() {
+        let limits = RiskLimits {
+            max_coin_notional: Some(100.0),
+        };
+        let mut trade: TradeInfo = serde_json::from_value(serde_json::json!({
+            "coin": "ETH", "side": "B", "px": "3000.0", "sz": "1.0", "time": 0,
+            "hash": "0x0", "startPosition": "0.0", "dir": "Open Long", "closedPnl": "0.0",
+            "oid": 1, "crossed": true, "fee": "0.0", "tid": 1, "feeToken": "USDC",
+        }))
+        .unwrap();
+        assert!(limits.check(H160::zero(), &trade, 0.0).is_none());
+        let breach = limits.check(H160::zero(), &trade, 0.03).unwrap();
+        assert_eq!(breach.notional, 0.03 * 3000.0 + TRADE_AMOUNT_USDT);
+        // 平仓不受上限限制
+        trade.dir = "Close Long".to_string();
+        assert!(limits.check(H160::zero(), &trade, 0.03).is_none());
+        assert!(RiskLimits::default()
+            .check(H160::zero(), &trade, 1.0)
+            .is_none());
+    }
+}
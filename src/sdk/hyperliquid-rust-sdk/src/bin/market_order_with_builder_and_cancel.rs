@@ -27,7 +27,7 @@ async fn main() {
         px: None,
         slippage: Some(0.01), // 1% slippage
         cloid: None,
-        wallet: None,
+        signer: None,
     };
 
     let fee = 1;
@@ -66,7 +66,7 @@ async fn main() {
         px: None,
         slippage: Some(0.01), // 1% slippage
         cloid: None,
-        wallet: None,
+        signer: None,
     };
 
     let response = exchange_client
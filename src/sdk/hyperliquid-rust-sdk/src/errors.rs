@@ -17,6 +17,10 @@ pub enum Error {
     },
     #[error("Generic request error: {0:?}")]
     GenericRequest(String),
+    #[error("Connection failed or timed out: {0:?}")]
+    Connection(String),
+    #[error("Rate limited: {0:?}")]
+    RateLimited(String),
     #[error("Chain type not allowed for this function")]
     ChainNotAllowed,
     #[error("Asset not found")]
@@ -37,6 +41,8 @@ pub enum Error {
     WsManagerNotFound,
     #[error("WS send error: {0:?}")]
     WsSend(String),
+    #[error("WS post error: {0:?}")]
+    WsPost(String),
     #[error("Reader data not found")]
     ReaderDataNotFound,
     #[error("Reader error: {0:?}")]
@@ -49,8 +55,6 @@ pub enum Error {
     RandGen(String),
     #[error("Private key parse error: {0:?}")]
     PrivateKeyParse(String),
-    #[error("Cannot subscribe to multiple user events")]
-    UserEvents,
     #[error("Rmp parse error: {0:?}")]
     RmpParse(String),
     #[error("Invalid input number")]
@@ -59,6 +63,12 @@ pub enum Error {
     NoCloid,
     #[error("ECDSA signature failed: {0:?}")]
     SignatureFailure(String),
+    #[error("Remote signer error: {0:?}")]
+    RemoteSigner(String),
+    #[error("Replay recording error: {0:?}")]
+    Replay(String),
+    #[error("Nonce error: {0:?}")]
+    Nonce(String),
     #[error("Vault address not found")]
     VaultAddressNotFound,
 }
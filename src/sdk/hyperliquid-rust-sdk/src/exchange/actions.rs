@@ -1,4 +1,6 @@
-use crate::exchange::{cancel::CancelRequest, modify::ModifyRequest, order::OrderRequest};
+use crate::exchange::{
+    cancel::CancelRequest, modify::ModifyRequest, order::OrderRequest, twap::TwapRequest,
+};
 pub(crate) use ethers::{
     abi::{encode, ParamType, Tokenizable},
     types::{
@@ -290,12 +292,56 @@ pub struct VaultTransfer {
     pub usd: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateSubAccount {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SubAccountUsdTransfer {
+    pub sub_account_user: H160,
+    pub is_deposit: bool,
+    /// USDC without decimals, i.e. 1_000_000 is 1 USDC.
+    pub usd: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SubAccountSpotTransfer {
+    pub sub_account_user: H160,
+    pub is_deposit: bool,
+    pub token: String,
+    pub amount: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SetReferrer {
     pub code: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwapOrder {
+    pub twap: TwapRequest,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwapCancel {
+    #[serde(rename = "a", alias = "asset")]
+    pub asset: u32,
+    #[serde(rename = "t", alias = "twapId")]
+    pub twap_id: u64,
+}
+
+/// Cancels all open orders at `time` (ms), unless pushed back by a later action. `None` removes
+/// the scheduled cancel.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduleCancel {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ApproveBuilderFee {
@@ -305,3 +351,44 @@ pub struct ApproveBuilderFee {
     pub signature_chain_id: U256,
     pub hyperliquid_chain: String,
 }
+
+impl Eip712 for ApproveBuilderFee {
+    type Error = Eip712Error;
+
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Ok(eip_712_domain(self.signature_chain_id))
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(eip712::make_type_hash(
+            format!("{HYPERLIQUID_EIP_PREFIX}ApproveBuilderFee"),
+            &[
+                ("hyperliquidChain".to_string(), ParamType::String),
+                ("maxFeeRate".to_string(), ParamType::String),
+                ("builder".to_string(), ParamType::Address),
+                ("nonce".to_string(), ParamType::Uint(64)),
+            ],
+        ))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        let Self {
+            max_fee_rate,
+            builder,
+            nonce,
+            signature_chain_id: _,
+            hyperliquid_chain,
+        } = self;
+        let builder: H160 = builder
+            .parse()
+            .map_err(|e| Eip712Error::Message(format!("invalid builder address: {e}")))?;
+        let items = vec![
+            ethers::abi::Token::Uint(Self::type_hash()?.into()),
+            encode_eip712_type(hyperliquid_chain.clone().into_token()),
+            encode_eip712_type(max_fee_rate.clone().into_token()),
+            encode_eip712_type(builder.into_token()),
+            encode_eip712_type(nonce.into_token()),
+        ];
+        Ok(keccak256(encode(&items)))
+    }
+}
@@ -1,53 +1,68 @@
-use crate::signature::sign_typed_data;
 use crate::{
+    default_nonce_provider,
     exchange::{
         actions::{
-            ApproveAgent, ApproveBuilderFee, BulkCancel, BulkModify, BulkOrder, SetReferrer,
-            UpdateIsolatedMargin, UpdateLeverage, UsdSend,
+            ApproveAgent, ApproveBuilderFee, BulkCancel, BulkModify, BulkOrder, CreateSubAccount,
+            ScheduleCancel, SetReferrer, SubAccountSpotTransfer, SubAccountUsdTransfer, TwapCancel,
+            TwapOrder, UpdateIsolatedMargin, UpdateLeverage, UsdSend,
         },
         cancel::{CancelRequest, CancelRequestCloid},
         modify::{ClientModifyRequest, ModifyRequest},
         ClientCancelRequest, ClientOrderRequest,
     },
-    helpers::{generate_random_key, next_nonce, uuid_to_hex_string},
+    helpers::{generate_random_key, uuid_to_hex_string},
     info::info_client::InfoClient,
     meta::Meta,
     prelude::*,
     req::HttpClient,
-    signature::sign_l1_action,
-    BaseUrl, BulkCancelCloid, Error, ExchangeResponseStatus,
+    BaseUrl, BulkCancelCloid, ClientTwapRequest, CreateSubAccountResponse, Error,
+    ExchangeResponseStatus, NonceProvider, OrderInfo, PriceSource, Signer, UserFillsResponse,
 };
-use crate::{ClassTransfer, SpotSend, SpotUser, VaultTransfer, Withdraw3};
+use crate::{ClassTransfer, SpotSend, SpotUser, Transport, VaultTransfer, Withdraw3};
 use ethers::{
     abi::AbiEncode,
-    signers::{LocalWallet, Signer},
+    signers::LocalWallet,
     types::{Signature, H160, H256},
 };
-use log::debug;
+use log::{debug, warn};
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
 
 use super::cancel::ClientCancelRequestCloid;
 use super::order::{MarketCloseParams, MarketOrderParams};
 use super::{BuilderInfo, ClientLimit, ClientOrder};
 
-#[derive(Debug)]
+// Retries of an action whose nonce the exchange rejected, e.g. one another process already used
+const MAX_NONCE_RETRIES: usize = 2;
+
+#[derive(Debug, Clone)]
 pub struct ExchangeClient {
     pub http_client: HttpClient,
-    pub wallet: LocalWallet,
+    pub signer: Arc<dyn Signer>,
     pub meta: Meta,
     pub vault_address: Option<H160>,
     pub coin_to_asset: HashMap<String, u32>,
+    info: Arc<InfoClient>,
+    // Master account when `signer` is an agent, see `set_account`
+    account: Option<H160>,
+    // Perp and spot size decimals by coin, cached so market orders need no meta request
+    sz_decimals: HashMap<String, u32>,
+    price_source: Option<Arc<dyn PriceSource>>,
+    nonces: Arc<dyn NonceProvider>,
+    transport: Transport,
 }
 
-#[derive(Serialize, Deserialize)]
+/// Body of an `/exchange` request, see [`ExchangePayload::verify`] for checking a recorded one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
-struct ExchangePayload {
-    action: serde_json::Value,
-    signature: Signature,
-    nonce: u64,
-    vault_address: Option<H160>,
+pub struct ExchangePayload {
+    pub action: serde_json::Value,
+    pub signature: Signature,
+    pub nonce: u64,
+    pub vault_address: Option<H160>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -68,10 +83,17 @@ pub enum Actions {
     SpotSend(SpotSend),
     SetReferrer(SetReferrer),
     ApproveBuilderFee(ApproveBuilderFee),
+    TwapOrder(TwapOrder),
+    TwapCancel(TwapCancel),
+    ScheduleCancel(ScheduleCancel),
+    CreateSubAccount(CreateSubAccount),
+    SubAccountTransfer(SubAccountUsdTransfer),
+    SubAccountSpotTransfer(SubAccountSpotTransfer),
 }
 
 impl Actions {
-    fn hash(&self, timestamp: u64, vault_address: Option<H160>) -> Result<H256> {
+    /// Connection id signed for L1 actions, see [`crate::recover_l1_action_signer`].
+    pub fn hash(&self, timestamp: u64, vault_address: Option<H160>) -> Result<H256> {
         let mut bytes =
             rmp_serde::to_vec_named(self).map_err(|e| Error::RmpParse(e.to_string()))?;
         bytes.extend(timestamp.to_be_bytes());
@@ -88,15 +110,25 @@ impl Actions {
 impl ExchangeClient {
     pub async fn new(
         client: Option<Client>,
-        wallet: LocalWallet,
+        signer: impl Signer + 'static,
         base_url: Option<BaseUrl>,
         meta: Option<Meta>,
         vault_address: Option<H160>,
     ) -> Result<ExchangeClient> {
-        let client = client.unwrap_or_default();
-        let base_url = base_url.unwrap_or(BaseUrl::Mainnet);
+        let info = InfoClient::new(None, base_url).await?;
+        Self::with_info(client, signer, Arc::new(info), meta, vault_address).await
+    }
 
-        let info = InfoClient::new(None, Some(base_url)).await?;
+    /// Builds a client that shares `info`, e.g. the caller's own query client, for the
+    /// metadata and account lookups done by market orders.
+    pub async fn with_info(
+        client: Option<Client>,
+        signer: impl Signer + 'static,
+        info: Arc<InfoClient>,
+        meta: Option<Meta>,
+        vault_address: Option<H160>,
+    ) -> Result<ExchangeClient> {
+        let client = client.unwrap_or_default();
         let meta = if let Some(meta) = meta {
             meta
         } else {
@@ -104,100 +136,241 @@ impl ExchangeClient {
         };
 
         let mut coin_to_asset = HashMap::new();
+        let mut sz_decimals = HashMap::new();
         for (asset_ind, asset) in meta.universe.iter().enumerate() {
             coin_to_asset.insert(asset.name.clone(), asset_ind as u32);
+            sz_decimals.insert(asset.name.clone(), asset.sz_decimals);
         }
 
-        coin_to_asset = info
-            .spot_meta()
-            .await?
-            .add_pair_and_name_to_index_map(coin_to_asset);
+        let spot_meta = info.spot_meta().await?;
+        coin_to_asset = spot_meta.add_pair_and_name_to_index_map(coin_to_asset);
+        sz_decimals = spot_meta.add_pair_and_name_to_sz_decimals_map(sz_decimals);
 
         Ok(ExchangeClient {
-            wallet,
+            signer: Arc::new(signer),
             meta,
             vault_address,
+            // same endpoint, retry policy and timeouts as `info`
             http_client: HttpClient {
                 client,
-                base_url: base_url.get_url(),
+                ..info.http_client.clone()
             },
             coin_to_asset,
+            info,
+            account: None,
+            sz_decimals,
+            price_source: None,
+            nonces: default_nonce_provider(),
+            transport: Transport::Http,
         })
     }
 
-    async fn post(
+    pub fn info(&self) -> &Arc<InfoClient> {
+        &self.info
+    }
+
+    /// Prices market orders from `price_source` when it has a mid, e.g. [`LiveMids`].
+    pub fn set_price_source(&mut self, price_source: Arc<dyn PriceSource>) {
+        self.price_source = Some(price_source);
+    }
+
+    /// Sends actions over `transport` from now on, e.g. a websocket to skip the HTTP round trip.
+    ///
+    /// [`Transport::Ws`] sends each action once: it doesn't apply the retry policy, and a post
+    /// whose response is lost to a dropped socket returns an error without looking the orders up
+    /// by cloid, so the outcome is unknown. Keep orders that must not be lost or duplicated on
+    /// [`Transport::Http`].
+    pub fn set_transport(&mut self, transport: Transport) {
+        self.transport = transport;
+    }
+
+    /// A client that trades as `sub_account` (or a vault), for passing to a single call.
+    pub fn for_sub_account(&self, sub_account: H160) -> ExchangeClient {
+        ExchangeClient {
+            vault_address: Some(sub_account),
+            ..self.clone()
+        }
+    }
+
+    /// Sets the account that owns the orders when `signer` is an agent approved by it, so
+    /// order lookups after an ambiguous send query the right user.
+    pub fn set_account(&mut self, account: H160) {
+        self.account = Some(account);
+    }
+
+    /// The account that owns this client's orders and positions: the vault or sub-account if
+    /// set, else the master set by [`ExchangeClient::set_account`], else the signer.
+    pub fn account(&self) -> H160 {
+        self.vault_address
+            .or(self.account)
+            .unwrap_or_else(|| self.signer.address())
+    }
+
+    /// Uses `nonces` instead of the process-wide counter, e.g. [`FileNonces`] when several
+    /// processes sign for the same address.
+    pub fn set_nonce_provider(&mut self, nonces: Arc<dyn NonceProvider>) {
+        self.nonces = nonces;
+    }
+
+    /// Signs the action built for a fresh nonce and posts it. A rejected nonce, e.g. one already
+    /// used by another process, is retried with the next nonce. If the payload had been resent
+    /// the rejection may come from an earlier copy that went through, so an order is only
+    /// signed again once its cloids are confirmed unknown.
+    async fn send_action<T: DeserializeOwned>(
         &self,
-        action: serde_json::Value,
-        signature: Signature,
-        nonce: u64,
-    ) -> Result<ExchangeResponseStatus> {
-        let exchange_payload = ExchangePayload {
-            action,
-            signature,
-            nonce,
-            vault_address: self.vault_address,
+        signer: &dyn Signer,
+        vault_address: Option<H160>,
+        action: impl Fn(u64) -> Actions,
+    ) -> Result<T> {
+        let is_mainnet = self.http_client.is_mainnet();
+        // actions count against the account they're for, an agent's against its master
+        let account = match vault_address {
+            Some(vault_address) => vault_address,
+            None if signer.address() == self.signer.address() => self.account(),
+            None => signer.address(),
         };
-        let res = serde_json::to_string(&exchange_payload)
-            .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let mut attempt = 0;
+        loop {
+            let nonce = self.nonces.next_nonce(signer.address())?;
+            let action = action(nonce);
+            let hash = action.signing_hash(nonce, vault_address, is_mainnet)?;
+            let exchange_payload = ExchangePayload {
+                action: serde_json::to_value(&action)
+                    .map_err(|e| Error::JsonParse(e.to_string()))?,
+                signature: signer.sign_hash(hash).await?,
+                nonce,
+                vault_address,
+            };
+            let (output, sent) = self.post(&exchange_payload, account).await?;
+            if attempt < MAX_NONCE_RETRIES && is_nonce_rejection(&output) {
+                // An earlier copy of a resent request may have been placed
+                if sent > 1 {
+                    match self.lookup_orders(&action, signer, vault_address).await? {
+                        Some((_, orders)) if orders.iter().all(Option::is_none) => {}
+                        Some((user, orders)) if orders.iter().all(Option::is_some) => {
+                            let fills = self.info.user_fills(user).await?;
+                            let output =
+                                placed_orders_response(orders.into_iter().flatten(), &fills);
+                            return serde_json::from_value(output)
+                                .map_err(|e| Error::JsonParse(e.to_string()));
+                        }
+                        _ => {
+                            return Err(Error::Nonce(format!(
+                                "nonce {nonce} was used by an earlier copy of this request"
+                            )))
+                        }
+                    }
+                }
+                attempt += 1;
+                warn!("Nonce {nonce} rejected, retrying: {}", output["response"]);
+                continue;
+            }
+            return serde_json::from_value(output).map_err(|e| Error::JsonParse(e.to_string()));
+        }
+    }
+
+    // The exchange's record of each order in the action by cloid, `None` when the action isn't
+    // an order with cloids or its owner is unknown
+    async fn lookup_orders(
+        &self,
+        action: &Actions,
+        signer: &dyn Signer,
+        vault_address: Option<H160>,
+    ) -> Result<Option<(H160, Vec<Option<OrderInfo>>)>> {
+        let Actions::Order(bulk_order) = action else {
+            return Ok(None);
+        };
+        // Orders of an agent belong to its master. For a signer passed to a single call the
+        // client can't tell, so the order isn't signed again.
+        let user = match vault_address {
+            Some(vault_address) => vault_address,
+            None if signer.address() == self.signer.address() => self.account(),
+            None => return Ok(None),
+        };
+        let mut orders = Vec::with_capacity(bulk_order.orders.len());
+        for order in &bulk_order.orders {
+            let Some(cloid) = order
+                .cloid
+                .as_deref()
+                .and_then(|cloid| Uuid::parse_str(cloid.trim_start_matches("0x")).ok())
+            else {
+                return Ok(None);
+            };
+            orders.push(self.info.query_order_by_cloid(user, cloid).await?.order);
+        }
+        Ok(Some((user, orders)))
+    }
+
+    // The response and how many times the payload was sent
+    async fn post(
+        &self,
+        exchange_payload: &ExchangePayload,
+        account: H160,
+    ) -> Result<(serde_json::Value, u32)> {
+        if let Transport::Ws(ws_post_client) = &self.transport {
+            let payload = serde_json::to_value(exchange_payload)
+                .map_err(|e| Error::JsonParse(e.to_string()))?;
+            debug!("Sending request over websocket {payload:?}");
+            // posts use the same IP and address budgets as HTTP requests
+            self.http_client
+                .rate_limiter
+                .acquire("/exchange", &payload.to_string(), Some(account))
+                .await?;
+            return Ok((ws_post_client.post_action(&payload).await?, 1));
+        }
+
+        let res =
+            serde_json::to_string(exchange_payload).map_err(|e| Error::JsonParse(e.to_string()))?;
         debug!("Sending request {res:?}");
 
-        let output = &self
+        let (output, sent) = self
             .http_client
-            .post("/exchange", res)
-            .await
-            .map_err(|e| Error::JsonParse(e.to_string()))?;
-        serde_json::from_str(output).map_err(|e| Error::JsonParse(e.to_string()))
+            .post_counting("/exchange", res, Some(account))
+            .await?;
+        let output = serde_json::from_str(&output).map_err(|e| Error::JsonParse(e.to_string()))?;
+        Ok((output, sent))
     }
 
     pub async fn usdc_transfer(
         &self,
         amount: &str,
         destination: &str,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let signer = signer.unwrap_or(&*self.signer);
         let hyperliquid_chain = if self.http_client.is_mainnet() {
             "Mainnet".to_string()
         } else {
             "Testnet".to_string()
         };
 
-        let timestamp = next_nonce();
-        let usd_send = UsdSend {
-            signature_chain_id: 421614.into(),
-            hyperliquid_chain,
-            destination: destination.to_string(),
-            amount: amount.to_string(),
-            time: timestamp,
-        };
-        let signature = sign_typed_data(&usd_send, wallet)?;
-        let action = serde_json::to_value(Actions::UsdSend(usd_send))
-            .map_err(|e| Error::JsonParse(e.to_string()))?;
-
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |nonce| {
+            Actions::UsdSend(UsdSend {
+                signature_chain_id: 421614.into(),
+                hyperliquid_chain: hyperliquid_chain.clone(),
+                destination: destination.to_string(),
+                amount: amount.to_string(),
+                time: nonce,
+            })
+        })
+        .await
     }
 
     pub async fn class_transfer(
         &self,
         usdc: f64,
         to_perp: bool,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
         // payload expects usdc without decimals
         let usdc = (usdc * 1e6).round() as u64;
-        let wallet = wallet.unwrap_or(&self.wallet);
-
-        let timestamp = next_nonce();
+        let signer = signer.unwrap_or(&*self.signer);
 
         let action = Actions::SpotUser(SpotUser {
             class_transfer: ClassTransfer { usdc, to_perp },
         });
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
     }
 
     pub async fn vault_transfer(
@@ -205,27 +378,21 @@ impl ExchangeClient {
         is_deposit: bool,
         usd: String,
         vault_address: Option<H160>,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
         let vault_address = self
             .vault_address
             .or(vault_address)
             .ok_or(Error::VaultAddressNotFound)?;
-        let wallet = wallet.unwrap_or(&self.wallet);
-
-        let timestamp = next_nonce();
+        let signer = signer.unwrap_or(&*self.signer);
 
         let action = Actions::VaultTransfer(VaultTransfer {
             vault_address,
             is_deposit,
             usd,
         });
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
     }
 
     pub async fn market_open(
@@ -249,7 +416,7 @@ impl ExchangeClient {
             }),
         };
 
-        self.order(order, params.wallet).await
+        self.order(order, params.signer).await
     }
 
     pub async fn market_open_with_builder(
@@ -274,7 +441,7 @@ impl ExchangeClient {
             }),
         };
 
-        self.order_with_builder(order, params.wallet, builder).await
+        self.order_with_builder(order, params.signer, builder).await
     }
 
     pub async fn market_close(
@@ -282,15 +449,14 @@ impl ExchangeClient {
         params: MarketCloseParams<'_>,
     ) -> Result<ExchangeResponseStatus> {
         let slippage = params.slippage.unwrap_or(0.05); // Default 5% slippage
-        let wallet = params.wallet.unwrap_or(&self.wallet);
-
-        let base_url = match self.http_client.base_url.as_str() {
-            "https://api.hyperliquid.xyz" => BaseUrl::Mainnet,
-            "https://api.hyperliquid-testnet.xyz" => BaseUrl::Testnet,
-            _ => return Err(Error::GenericRequest("Invalid base URL".to_string())),
+        let signer = params.signer.unwrap_or(&*self.signer);
+        // the position is held by the account the order is placed for, not by an agent signer
+        let account = match params.signer {
+            Some(signer) => self.vault_address.unwrap_or_else(|| signer.address()),
+            None => self.account(),
         };
-        let info_client = InfoClient::new(None, Some(base_url)).await?;
-        let user_state = info_client.user_state(wallet.address()).await?;
+
+        let user_state = self.info.user_state(account).await?;
 
         let position = user_state
             .asset_positions
@@ -322,7 +488,7 @@ impl ExchangeClient {
             }),
         };
 
-        self.order(order, Some(wallet)).await
+        self.order(order, Some(signer)).await
     }
 
     async fn calculate_slippage_price(
@@ -332,21 +498,7 @@ impl ExchangeClient {
         slippage: f64,
         px: Option<f64>,
     ) -> Result<(f64, u32)> {
-        let base_url = match self.http_client.base_url.as_str() {
-            "https://api.hyperliquid.xyz" => BaseUrl::Mainnet,
-            "https://api.hyperliquid-testnet.xyz" => BaseUrl::Testnet,
-            _ => return Err(Error::GenericRequest("Invalid base URL".to_string())),
-        };
-        let info_client = InfoClient::new(None, Some(base_url)).await?;
-        let meta = info_client.meta().await?;
-
-        let asset_meta = meta
-            .universe
-            .iter()
-            .find(|a| a.name == asset)
-            .ok_or(Error::AssetNotFound)?;
-
-        let sz_decimals = asset_meta.sz_decimals;
+        let sz_decimals = *self.sz_decimals.get(asset).ok_or(Error::AssetNotFound)?;
         let max_decimals: u32 = if self.coin_to_asset[asset] < 10000 {
             6
         } else {
@@ -356,8 +508,10 @@ impl ExchangeClient {
 
         let px = if let Some(px) = px {
             px
+        } else if let Some(mid) = self.price_source.as_ref().and_then(|s| s.mid(asset)) {
+            mid
         } else {
-            let all_mids = info_client.all_mids().await?;
+            let all_mids = self.info.all_mids().await?;
             all_mids
                 .get(asset)
                 .ok_or(Error::AssetNotFound)?
@@ -383,29 +537,27 @@ impl ExchangeClient {
     pub async fn order(
         &self,
         order: ClientOrderRequest,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        self.bulk_order(vec![order], wallet).await
+        self.bulk_order(vec![order], signer).await
     }
 
     pub async fn order_with_builder(
         &self,
         order: ClientOrderRequest,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
         builder: BuilderInfo,
     ) -> Result<ExchangeResponseStatus> {
-        self.bulk_order_with_builder(vec![order], wallet, builder)
+        self.bulk_order_with_builder(vec![order], signer, builder)
             .await
     }
 
     pub async fn bulk_order(
         &self,
         orders: Vec<ClientOrderRequest>,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
-        let timestamp = next_nonce();
-
+        let signer = signer.unwrap_or(&*self.signer);
         let mut transformed_orders = Vec::new();
 
         for order in orders {
@@ -417,23 +569,17 @@ impl ExchangeClient {
             grouping: "na".to_string(),
             builder: None,
         });
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
     }
 
     pub async fn bulk_order_with_builder(
         &self,
         orders: Vec<ClientOrderRequest>,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
         mut builder: BuilderInfo,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
-        let timestamp = next_nonce();
-
+        let signer = signer.unwrap_or(&*self.signer);
         builder.builder = builder.builder.to_lowercase();
 
         let mut transformed_orders = Vec::new();
@@ -447,30 +593,24 @@ impl ExchangeClient {
             grouping: "na".to_string(),
             builder: Some(builder),
         });
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
     }
 
     pub async fn cancel(
         &self,
         cancel: ClientCancelRequest,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        self.bulk_cancel(vec![cancel], wallet).await
+        self.bulk_cancel(vec![cancel], signer).await
     }
 
     pub async fn bulk_cancel(
         &self,
         cancels: Vec<ClientCancelRequest>,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
-        let timestamp = next_nonce();
-
+        let signer = signer.unwrap_or(&*self.signer);
         let mut transformed_cancels = Vec::new();
         for cancel in cancels.into_iter() {
             let &asset = self
@@ -486,31 +626,24 @@ impl ExchangeClient {
         let action = Actions::Cancel(BulkCancel {
             cancels: transformed_cancels,
         });
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
     }
 
     pub async fn modify(
         &self,
         modify: ClientModifyRequest,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        self.bulk_modify(vec![modify], wallet).await
+        self.bulk_modify(vec![modify], signer).await
     }
 
     pub async fn bulk_modify(
         &self,
         modifies: Vec<ClientModifyRequest>,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
-        let timestamp = next_nonce();
-
+        let signer = signer.unwrap_or(&*self.signer);
         let mut transformed_modifies = Vec::new();
         for modify in modifies.into_iter() {
             transformed_modifies.push(ModifyRequest {
@@ -522,31 +655,24 @@ impl ExchangeClient {
         let action = Actions::BatchModify(BulkModify {
             modifies: transformed_modifies,
         });
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
     }
 
     pub async fn cancel_by_cloid(
         &self,
         cancel: ClientCancelRequestCloid,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        self.bulk_cancel_by_cloid(vec![cancel], wallet).await
+        self.bulk_cancel_by_cloid(vec![cancel], signer).await
     }
 
     pub async fn bulk_cancel_by_cloid(
         &self,
         cancels: Vec<ClientCancelRequestCloid>,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
-        let timestamp = next_nonce();
-
+        let signer = signer.unwrap_or(&*self.signer);
         let mut transformed_cancels: Vec<CancelRequestCloid> = Vec::new();
         for cancel in cancels.into_iter() {
             let &asset = self
@@ -563,12 +689,8 @@ impl ExchangeClient {
             cancels: transformed_cancels,
         });
 
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
     }
 
     pub async fn update_leverage(
@@ -576,11 +698,9 @@ impl ExchangeClient {
         leverage: u32,
         coin: &str,
         is_cross: bool,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
-
-        let timestamp = next_nonce();
+        let signer = signer.unwrap_or(&*self.signer);
 
         let &asset_index = self.coin_to_asset.get(coin).ok_or(Error::AssetNotFound)?;
         let action = Actions::UpdateLeverage(UpdateLeverage {
@@ -588,44 +708,33 @@ impl ExchangeClient {
             is_cross,
             leverage,
         });
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
     }
 
     pub async fn update_isolated_margin(
         &self,
         amount: f64,
         coin: &str,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let signer = signer.unwrap_or(&*self.signer);
 
         let amount = (amount * 1_000_000.0).round() as i64;
-        let timestamp = next_nonce();
-
         let &asset_index = self.coin_to_asset.get(coin).ok_or(Error::AssetNotFound)?;
         let action = Actions::UpdateIsolatedMargin(UpdateIsolatedMargin {
             asset: asset_index,
             is_buy: true,
             ntli: amount,
         });
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
     }
 
     pub async fn approve_agent(
         &self,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<(String, ExchangeResponseStatus)> {
-        let wallet = wallet.unwrap_or(&self.wallet);
         let key = H256::from(generate_random_key()?).encode_hex()[2..].to_string();
 
         let address = key
@@ -633,52 +742,77 @@ impl ExchangeClient {
             .map_err(|e| Error::PrivateKeyParse(e.to_string()))?
             .address();
 
+        let response = self.send_approve_agent(address, None, signer).await?;
+        Ok((key, response))
+    }
+
+    /// Approves `agent_address` under `name`, replacing any agent previously approved under
+    /// that name. With `valid_until` (ms) the exchange stops accepting the agent's signatures
+    /// after that time.
+    pub async fn approve_named_agent(
+        &self,
+        agent_address: H160,
+        name: &str,
+        valid_until: Option<u64>,
+        signer: Option<&dyn Signer>,
+    ) -> Result<ExchangeResponseStatus> {
+        // the expiry is carried in the agent name
+        let agent_name = match valid_until {
+            Some(valid_until) => format!("{name} valid_until {valid_until}"),
+            None => name.to_string(),
+        };
+        self.send_approve_agent(agent_address, Some(agent_name), signer)
+            .await
+    }
+
+    async fn send_approve_agent(
+        &self,
+        agent_address: H160,
+        agent_name: Option<String>,
+        signer: Option<&dyn Signer>,
+    ) -> Result<ExchangeResponseStatus> {
+        let signer = signer.unwrap_or(&*self.signer);
         let hyperliquid_chain = if self.http_client.is_mainnet() {
             "Mainnet".to_string()
         } else {
             "Testnet".to_string()
         };
 
-        let nonce = next_nonce();
-        let approve_agent = ApproveAgent {
-            signature_chain_id: 421614.into(),
-            hyperliquid_chain,
-            agent_address: address,
-            agent_name: None,
-            nonce,
-        };
-        let signature = sign_typed_data(&approve_agent, wallet)?;
-        let action = serde_json::to_value(Actions::ApproveAgent(approve_agent))
-            .map_err(|e| Error::JsonParse(e.to_string()))?;
-        Ok((key, self.post(action, signature, nonce).await?))
+        self.send_action(signer, self.vault_address, |nonce| {
+            Actions::ApproveAgent(ApproveAgent {
+                signature_chain_id: 421614.into(),
+                hyperliquid_chain: hyperliquid_chain.clone(),
+                agent_address,
+                agent_name: agent_name.clone(),
+                nonce,
+            })
+        })
+        .await
     }
 
     pub async fn withdraw_from_bridge(
         &self,
         amount: &str,
         destination: &str,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let signer = signer.unwrap_or(&*self.signer);
         let hyperliquid_chain = if self.http_client.is_mainnet() {
             "Mainnet".to_string()
         } else {
             "Testnet".to_string()
         };
 
-        let timestamp = next_nonce();
-        let withdraw = Withdraw3 {
-            signature_chain_id: 421614.into(),
-            hyperliquid_chain,
-            destination: destination.to_string(),
-            amount: amount.to_string(),
-            time: timestamp,
-        };
-        let signature = sign_typed_data(&withdraw, wallet)?;
-        let action = serde_json::to_value(Actions::Withdraw3(withdraw))
-            .map_err(|e| Error::JsonParse(e.to_string()))?;
-
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |nonce| {
+            Actions::Withdraw3(Withdraw3 {
+                signature_chain_id: 421614.into(),
+                hyperliquid_chain: hyperliquid_chain.clone(),
+                destination: destination.to_string(),
+                amount: amount.to_string(),
+                time: nonce,
+            })
+        })
+        .await
     }
 
     pub async fn spot_transfer(
@@ -686,79 +820,191 @@ impl ExchangeClient {
         amount: &str,
         destination: &str,
         token: &str,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let signer = signer.unwrap_or(&*self.signer);
         let hyperliquid_chain = if self.http_client.is_mainnet() {
             "Mainnet".to_string()
         } else {
             "Testnet".to_string()
         };
 
-        let timestamp = next_nonce();
-        let spot_send = SpotSend {
-            signature_chain_id: 421614.into(),
-            hyperliquid_chain,
-            destination: destination.to_string(),
-            amount: amount.to_string(),
-            time: timestamp,
-            token: token.to_string(),
-        };
-        let signature = sign_typed_data(&spot_send, wallet)?;
-        let action = serde_json::to_value(Actions::SpotSend(spot_send))
-            .map_err(|e| Error::JsonParse(e.to_string()))?;
-
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |nonce| {
+            Actions::SpotSend(SpotSend {
+                signature_chain_id: 421614.into(),
+                hyperliquid_chain: hyperliquid_chain.clone(),
+                destination: destination.to_string(),
+                amount: amount.to_string(),
+                time: nonce,
+                token: token.to_string(),
+            })
+        })
+        .await
     }
 
     pub async fn set_referrer(
         &self,
         code: String,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
-        let timestamp = next_nonce();
-
+        let signer = signer.unwrap_or(&*self.signer);
         let action = Actions::SetReferrer(SetReferrer { code });
 
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-        self.post(action, signature, timestamp).await
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
     }
 
     pub async fn approve_builder_fee(
         &self,
         builder: String,
         max_fee_rate: String,
-        wallet: Option<&LocalWallet>,
+        signer: Option<&dyn Signer>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
-        let timestamp = next_nonce();
-
+        let signer = signer.unwrap_or(&*self.signer);
         let hyperliquid_chain = if self.http_client.is_mainnet() {
             "Mainnet".to_string()
         } else {
             "Testnet".to_string()
         };
 
-        let action = Actions::ApproveBuilderFee(ApproveBuilderFee {
-            signature_chain_id: 421614.into(),
-            hyperliquid_chain,
-            builder,
-            max_fee_rate,
-            nonce: timestamp,
+        self.send_action(signer, self.vault_address, |nonce| {
+            Actions::ApproveBuilderFee(ApproveBuilderFee {
+                signature_chain_id: 421614.into(),
+                hyperliquid_chain: hyperliquid_chain.clone(),
+                builder: builder.clone(),
+                max_fee_rate: max_fee_rate.clone(),
+                nonce,
+            })
+        })
+        .await
+    }
+
+    pub async fn create_sub_account(
+        &self,
+        name: String,
+        signer: Option<&dyn Signer>,
+    ) -> Result<CreateSubAccountResponse> {
+        let signer = signer.unwrap_or(&*self.signer);
+        let action = Actions::CreateSubAccount(CreateSubAccount { name });
+        self.send_action(signer, None, |_| action.clone()).await
+    }
+
+    /// Moves USDC from the master account to `sub_account` (`is_deposit`) or back.
+    pub async fn sub_account_transfer(
+        &self,
+        sub_account: H160,
+        is_deposit: bool,
+        usdc: f64,
+        signer: Option<&dyn Signer>,
+    ) -> Result<ExchangeResponseStatus> {
+        let signer = signer.unwrap_or(&*self.signer);
+        let action = Actions::SubAccountTransfer(SubAccountUsdTransfer {
+            sub_account_user: sub_account,
+            is_deposit,
+            usd: (usdc * 1e6).round() as u64,
         });
+        self.send_action(signer, None, |_| action.clone()).await
+    }
 
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
+    /// Moves a spot token between the master account and `sub_account`. `token` is in
+    /// `name:tokenId` form, e.g. "PURR:0xc1fb593aeffbeb02f85e0308e9956a90".
+    pub async fn sub_account_spot_transfer(
+        &self,
+        sub_account: H160,
+        is_deposit: bool,
+        token: &str,
+        amount: &str,
+        signer: Option<&dyn Signer>,
+    ) -> Result<ExchangeResponseStatus> {
+        let signer = signer.unwrap_or(&*self.signer);
+        let action = Actions::SubAccountSpotTransfer(SubAccountSpotTransfer {
+            sub_account_user: sub_account,
+            is_deposit,
+            token: token.to_string(),
+            amount: amount.to_string(),
+        });
+        self.send_action(signer, None, |_| action.clone()).await
+    }
 
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-        self.post(action, signature, timestamp).await
+    /// Dead man's switch: all open orders are cancelled at `time` (ms, at least 5 seconds out)
+    /// unless it is pushed back by another call. `None` removes the scheduled cancel.
+    pub async fn schedule_cancel(
+        &self,
+        time: Option<u64>,
+        signer: Option<&dyn Signer>,
+    ) -> Result<ExchangeResponseStatus> {
+        let signer = signer.unwrap_or(&*self.signer);
+        let action = Actions::ScheduleCancel(ScheduleCancel { time });
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
     }
+
+    pub async fn twap_order(
+        &self,
+        twap: ClientTwapRequest,
+        signer: Option<&dyn Signer>,
+    ) -> Result<ExchangeResponseStatus> {
+        let signer = signer.unwrap_or(&*self.signer);
+        let action = Actions::TwapOrder(TwapOrder {
+            twap: twap.convert(&self.coin_to_asset)?,
+        });
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
+    }
+
+    pub async fn twap_cancel(
+        &self,
+        coin: &str,
+        twap_id: u64,
+        signer: Option<&dyn Signer>,
+    ) -> Result<ExchangeResponseStatus> {
+        let signer = signer.unwrap_or(&*self.signer);
+        let &asset = self.coin_to_asset.get(coin).ok_or(Error::AssetNotFound)?;
+        let action = Actions::TwapCancel(TwapCancel { asset, twap_id });
+        self.send_action(signer, self.vault_address, |_| action.clone())
+            .await
+    }
+}
+
+fn is_nonce_rejection(output: &serde_json::Value) -> bool {
+    output["status"] == "err"
+        && output["response"]
+            .as_str()
+            .is_some_and(|e| e.to_lowercase().contains("nonce"))
+}
+
+// An order response built from the order statuses of orders whose response was lost. Filled
+// size and average price come from the user's fills, so a partially filled IOC order, canceled
+// after matching, is reported as filled for what it got.
+fn placed_orders_response(
+    orders: impl Iterator<Item = OrderInfo>,
+    fills: &[UserFillsResponse],
+) -> serde_json::Value {
+    let statuses: Vec<serde_json::Value> = orders
+        .map(|info| {
+            let oid = info.order.oid;
+            let (mut total_sz, mut notional) = (0.0, 0.0);
+            for fill in fills.iter().filter(|fill| fill.oid == oid) {
+                let sz: f64 = fill.sz.parse().unwrap_or_default();
+                total_sz += sz;
+                notional += sz * fill.px.parse::<f64>().unwrap_or_default();
+            }
+            match info.status.as_str() {
+                "open" | "triggered" => json!({ "resting": { "oid": oid } }),
+                _ if total_sz > 0.0 => json!({ "filled": {
+                    "totalSz": total_sz.to_string(),
+                    "avgPx": (notional / total_sz).to_string(),
+                    "oid": oid,
+                }}),
+                "filled" => json!({ "error": format!("Order {oid} is filled, fills not found") }),
+                status => json!({ "error": format!("Order {oid} is {status}") }),
+            }
+        })
+        .collect();
+    json!({
+        "status": "ok",
+        "response": { "type": "order", "data": { "statuses": statuses } },
+    })
 }
 
 fn round_to_decimals(value: f64, decimals: u32) -> f64 {
@@ -781,7 +1027,8 @@ mod tests {
     use super::*;
     use crate::{
         exchange::order::{Limit, OrderRequest, Trigger},
-        Order,
+        signature::sign_l1_action,
+        ExchangeDataStatus, MockHyperliquid, MockOrderStatus, Order, TwapRequest,
     };
 
     fn get_wallet() -> Result<LocalWallet> {
@@ -791,8 +1038,8 @@ mod tests {
             .map_err(|e| Error::Wallet(e.to_string()))
     }
 
-    #[test]
-    fn test_limit_order_action_hashing() -> Result<()> {
+    #[tokio::test]
+    async fn test_limit_order_action_hashing() -> Result<()> {
         let wallet = get_wallet()?;
         let action = Actions::Order(BulkOrder {
             orders: vec![OrderRequest {
@@ -811,17 +1058,135 @@ mod tests {
         });
         let connection_id = action.hash(1583838, None)?;
 
-        let signature = sign_l1_action(&wallet, connection_id, true)?;
+        let signature = sign_l1_action(&wallet, connection_id, true).await?;
         assert_eq!(signature.to_string(), "77957e58e70f43b6b68581f2dc42011fc384538a2e5b7bf42d5b936f19fbb67360721a8598727230f67080efee48c812a6a4442013fd3b0eed509171bef9f23f1c");
 
-        let signature = sign_l1_action(&wallet, connection_id, false)?;
+        let signature = sign_l1_action(&wallet, connection_id, false).await?;
         assert_eq!(signature.to_string(), "cd0925372ff1ed499e54883e9a6205ecfadec748f80ec463fe2f84f1209648776377961965cb7b12414186b1ea291e95fd512722427efcbcfb3b0b2bcd4d79d01c");
 
         Ok(())
     }
 
-    #[test]
-    fn test_limit_order_action_hashing_with_cloid() -> Result<()> {
+    #[tokio::test]
+    async fn test_twap_order_action_hashing() -> Result<()> {
+        let wallet = get_wallet()?;
+        let action = Actions::TwapOrder(TwapOrder {
+            twap: TwapRequest {
+                asset: 1,
+                is_buy: true,
+                sz: "3.5".to_string(),
+                reduce_only: false,
+                minutes: 30,
+                randomize: false,
+            },
+        });
+        // {"type": "twapOrder", "twap": {"a": 1, "b": true, "s": "3.5", "r": false, "m": 30, "t": false}}
+        let expected_msgpack = "82a474797065a9747761704f72646572a47477617086a16101a162c3a173a3332e35a172c2a16d1ea174c2";
+        assert_eq!(
+            hex::encode(rmp_serde::to_vec_named(&action).unwrap()),
+            expected_msgpack
+        );
+        let connection_id = action.hash(1583838, None)?;
+
+        let signature = sign_l1_action(&wallet, connection_id, true).await?;
+        assert_eq!(signature.to_string(), "564840d8ca8e2ad868a513170889303e16568b4812b99f809f7f0e90a2db24e04b6184a9afd9578e4fa12533e5ddaf110035ba55233f495b40f607fed5b5d5d61c");
+
+        let signature = sign_l1_action(&wallet, connection_id, false).await?;
+        assert_eq!(signature.to_string(), "05e3eef24b9d0485bb9bc551a2e8164e45e831e42d965c5fafb115114a0a1dc82d404d8790c8d53a1149b7b09aac7d2ab64237d963f9c5f0eea8b9d5181dc8091b");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_twap_cancel_action_hashing() -> Result<()> {
+        let wallet = get_wallet()?;
+        let action = Actions::TwapCancel(TwapCancel {
+            asset: 1,
+            twap_id: 42,
+        });
+        // {"type": "twapCancel", "a": 1, "t": 42}
+        let expected_msgpack = "83a474797065aa7477617043616e63656ca16101a1742a";
+        assert_eq!(
+            hex::encode(rmp_serde::to_vec_named(&action).unwrap()),
+            expected_msgpack
+        );
+        let connection_id = action.hash(1583838, None)?;
+
+        let signature = sign_l1_action(&wallet, connection_id, true).await?;
+        assert_eq!(signature.to_string(), "ad6ef19e657f541c6a0aa2b339e81c6b66676585f317e2a7edcb3e987b85589f496a9f77e78974d1e480746a02d65e3037526951a937a56da9d37c3d95d6d7d01b");
+
+        let signature = sign_l1_action(&wallet, connection_id, false).await?;
+        assert_eq!(signature.to_string(), "ccf8a68c5e6f334d61b13e27d302f7a30d1498ba5beda85824787d341f4568ec76d93eeff84b2dd46b4a6c0f8a88bafb97dea71e44c91f2e83feb8fecc4251511c");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_schedule_cancel_action_hashing() -> Result<()> {
+        let wallet = get_wallet()?;
+        let action = Actions::ScheduleCancel(ScheduleCancel {
+            time: Some(1583848),
+        });
+        // {"type": "scheduleCancel", "time": 1583848}
+        let expected_msgpack = "82a474797065ae7363686564756c6543616e63656ca474696d65ce00182ae8";
+        assert_eq!(
+            hex::encode(rmp_serde::to_vec_named(&action).unwrap()),
+            expected_msgpack
+        );
+        let connection_id = action.hash(1583838, None)?;
+
+        let signature = sign_l1_action(&wallet, connection_id, true).await?;
+        assert_eq!(signature.to_string(), "b41fe7bf990a0b17485cb1643d1444e1cf8286cd9e53cb31c4e04fe0dd595480388908c0112de78e5fe22ca7abcca89b9629390fa1ba7d29f158fca60ca9c3361b");
+
+        let signature = sign_l1_action(&wallet, connection_id, false).await?;
+        assert_eq!(signature.to_string(), "e07028dd763c1949c6b52bc45c86edf1c331c52f33ed573923dc5e5593ce62ce0a472ba08deeef53300fa1b3917bb37cc9306fbefeafd246974261d42e88443e1c");
+
+        // Without a time the scheduled cancel is removed
+        let action = Actions::ScheduleCancel(ScheduleCancel { time: None });
+        assert_eq!(
+            hex::encode(rmp_serde::to_vec_named(&action).unwrap()),
+            "81a474797065ae7363686564756c6543616e63656c"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sub_account_actions_hashing() -> Result<()> {
+        let wallet = get_wallet()?;
+        let create = Actions::CreateSubAccount(CreateSubAccount {
+            name: "leader-1".to_string(),
+        });
+        // {"type": "createSubAccount", "name": "leader-1"}
+        assert_eq!(
+            hex::encode(rmp_serde::to_vec_named(&create).unwrap()),
+            "82a474797065b06372656174655375624163636f756e74a46e616d65a86c65616465722d31"
+        );
+
+        let transfer = Actions::SubAccountTransfer(SubAccountUsdTransfer {
+            sub_account_user: H160::from_str("0x1d9470d4b963f552e6f671a81619d395877bf409")
+                .map_err(|e| Error::GenericParse(e.to_string()))?,
+            is_deposit: true,
+            usd: 1_000_000,
+        });
+        // {"type": "subAccountTransfer", "subAccountUser": "0x1d94...", "isDeposit": true, "usd": 1000000}
+        let expected_msgpack = "84a474797065b27375624163636f756e745472616e73666572ae7375624163636f756e7455736572d92a307831643934373064346239363366353532653666363731613831363139643339353837376266343039a969734465706f736974c3a3757364ce000f4240";
+        assert_eq!(
+            hex::encode(rmp_serde::to_vec_named(&transfer).unwrap()),
+            expected_msgpack
+        );
+        let connection_id = transfer.hash(1583838, None)?;
+
+        let signature = sign_l1_action(&wallet, connection_id, true).await?;
+        assert_eq!(signature.to_string(), "d766ff2eb59b1ab6d33c474fa22830e302c5c116aadb9b7c3cdc08ea3e4f08ba0ba874106de56d86501e1eaefce781413880775c3a632940149788e688ba00201c");
+
+        let signature = sign_l1_action(&wallet, connection_id, false).await?;
+        assert_eq!(signature.to_string(), "a839b60fb8d30ff896b9e7c2b249afedc073ccc81984add5d4b964ada7d3e4c53ba2846c9355abb210c0ec90680e364320efa679e80972b5262150908137a72f1b");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_limit_order_action_hashing_with_cloid() -> Result<()> {
         let cloid = uuid::Uuid::from_str("1e60610f-0b3d-4205-97c8-8c1fed2ad5ee")
             .map_err(|_e| uuid::Uuid::new_v4());
         let wallet = get_wallet()?;
@@ -842,17 +1207,17 @@ mod tests {
         });
         let connection_id = action.hash(1583838, None)?;
 
-        let signature = sign_l1_action(&wallet, connection_id, true)?;
+        let signature = sign_l1_action(&wallet, connection_id, true).await?;
         assert_eq!(signature.to_string(), "d3e894092eb27098077145714630a77bbe3836120ee29df7d935d8510b03a08f456de5ec1be82aa65fc6ecda9ef928b0445e212517a98858cfaa251c4cd7552b1c");
 
-        let signature = sign_l1_action(&wallet, connection_id, false)?;
+        let signature = sign_l1_action(&wallet, connection_id, false).await?;
         assert_eq!(signature.to_string(), "3768349dbb22a7fd770fc9fc50c7b5124a7da342ea579b309f58002ceae49b4357badc7909770919c45d850aabb08474ff2b7b3204ae5b66d9f7375582981f111c");
 
         Ok(())
     }
 
-    #[test]
-    fn test_tpsl_order_action_hashing() -> Result<()> {
+    #[tokio::test]
+    async fn test_tpsl_order_action_hashing() -> Result<()> {
         for (tpsl, mainnet_signature, testnet_signature) in [
             (
                 "tp",
@@ -887,17 +1252,17 @@ mod tests {
             });
             let connection_id = action.hash(1583838, None)?;
 
-            let signature = sign_l1_action(&wallet, connection_id, true)?;
+            let signature = sign_l1_action(&wallet, connection_id, true).await?;
             assert_eq!(signature.to_string(), mainnet_signature);
 
-            let signature = sign_l1_action(&wallet, connection_id, false)?;
+            let signature = sign_l1_action(&wallet, connection_id, false).await?;
             assert_eq!(signature.to_string(), testnet_signature);
         }
         Ok(())
     }
 
-    #[test]
-    fn test_cancel_action_hashing() -> Result<()> {
+    #[tokio::test]
+    async fn test_cancel_action_hashing() -> Result<()> {
         let wallet = get_wallet()?;
         let action = Actions::Cancel(BulkCancel {
             cancels: vec![CancelRequest {
@@ -907,12 +1272,253 @@ mod tests {
         });
         let connection_id = action.hash(1583838, None)?;
 
-        let signature = sign_l1_action(&wallet, connection_id, true)?;
+        let signature = sign_l1_action(&wallet, connection_id, true).await?;
         assert_eq!(signature.to_string(), "02f76cc5b16e0810152fa0e14e7b219f49c361e3325f771544c6f54e157bf9fa17ed0afc11a98596be85d5cd9f86600aad515337318f7ab346e5ccc1b03425d51b");
 
-        let signature = sign_l1_action(&wallet, connection_id, false)?;
+        let signature = sign_l1_action(&wallet, connection_id, false).await?;
         assert_eq!(signature.to_string(), "6ffebadfd48067663390962539fbde76cfa36f53be65abe2ab72c9db6d0db44457720db9d7c4860f142a484f070c84eb4b9694c3a617c83f0d698a27e55fd5e01c");
 
         Ok(())
     }
+
+    #[derive(Debug)]
+    struct FixedMid(f64);
+
+    impl PriceSource for FixedMid {
+        fn mid(&self, _coin: &str) -> Option<f64> {
+            Some(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spot_market_open_without_info_requests() -> Result<()> {
+        let (mock, mut exchange_client) = MockHyperliquid::exchange_client().await?;
+        exchange_client.set_price_source(Arc::new(FixedMid(0.2)));
+        let setup_requests = mock.info_requests().len();
+
+        exchange_client
+            .market_open(MarketOrderParams {
+                asset: "PURR/USDC",
+                is_buy: true,
+                sz: 10.7,
+                px: None,
+                slippage: None,
+                cloid: None,
+                signer: None,
+            })
+            .await?;
+
+        assert_eq!(mock.info_requests().len(), setup_requests);
+        let Actions::Order(bulk_order) = &mock.actions()[0].action else {
+            panic!("expected an order");
+        };
+        // PURR has 0 szDecimals, the price is rounded with the 8 decimal spot rule
+        assert_eq!(bulk_order.orders[0].asset, 10_000);
+        assert_eq!(bulk_order.orders[0].sz, "11");
+        assert_eq!(bulk_order.orders[0].limit_px, "0.21");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_twap_order_and_cancel() -> Result<()> {
+        let (mock, exchange_client) = MockHyperliquid::exchange_client().await?;
+
+        let response = exchange_client
+            .twap_order(
+                ClientTwapRequest {
+                    asset: "ETH".to_string(),
+                    is_buy: true,
+                    sz: 1.5,
+                    reduce_only: false,
+                    minutes: 30,
+                    randomize: true,
+                },
+                None,
+            )
+            .await?;
+        let ExchangeResponseStatus::Ok(response) = response else {
+            panic!("twap order rejected: {response:?}");
+        };
+        let Some(ExchangeDataStatus::Running(running)) = response.data.and_then(|d| d.status)
+        else {
+            panic!("twap not running");
+        };
+        exchange_client
+            .twap_cancel("ETH", running.twap_id, None)
+            .await?;
+
+        let actions = mock.actions();
+        assert!(matches!(
+            &actions[0].action,
+            Actions::TwapOrder(order) if order.twap.sz == "1.5" && order.twap.minutes == 30
+        ));
+        assert!(matches!(
+            &actions[1].action,
+            Actions::TwapCancel(cancel) if cancel.asset == 1 && cancel.twap_id == running.twap_id
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sub_account_lifecycle() -> Result<()> {
+        let (mock, exchange_client) = MockHyperliquid::exchange_client().await?;
+        let master = exchange_client.signer.address();
+
+        let response = exchange_client
+            .create_sub_account("leader-1".to_string(), None)
+            .await?;
+        let CreateSubAccountResponse::Ok(created) = response else {
+            panic!("sub account not created: {response:?}");
+        };
+        let sub_account = created.data;
+        exchange_client
+            .sub_account_transfer(sub_account, true, 12.5, None)
+            .await?;
+        exchange_client
+            .for_sub_account(sub_account)
+            .order(
+                ClientOrderRequest {
+                    asset: "ETH".to_string(),
+                    is_buy: true,
+                    reduce_only: false,
+                    limit_px: 3000.0,
+                    sz: 0.01,
+                    cloid: None,
+                    order_type: ClientOrder::Limit(ClientLimit {
+                        tif: "Gtc".to_string(),
+                    }),
+                },
+                None,
+            )
+            .await?;
+
+        let actions = mock.actions();
+        assert_eq!(actions.len(), 3);
+        assert!(actions.iter().all(|action| action.signer == master));
+        assert!(matches!(
+            &actions[0].action,
+            Actions::CreateSubAccount(create) if create.name == "leader-1"
+        ));
+        // transfers are sent by the master, without vaultAddress
+        assert_eq!(actions[1].vault_address, None);
+        assert!(matches!(
+            &actions[1].action,
+            Actions::SubAccountTransfer(transfer)
+                if transfer.sub_account_user == sub_account
+                    && transfer.is_deposit
+                    && transfer.usd == 12_500_000
+        ));
+        assert_eq!(actions[2].vault_address, Some(sub_account));
+        assert!(matches!(&actions[2].action, Actions::Order(_)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_market_close_reads_the_account_position() -> Result<()> {
+        let (mock, exchange_client) = MockHyperliquid::exchange_client().await?;
+        // only the sub-account holds the position, so the signer's own state has nothing to close
+        let sub_account = H160::from_low_u64_be(9);
+        mock.set_position(sub_account, "ETH", 0.5);
+        exchange_client
+            .for_sub_account(sub_account)
+            .market_close(MarketCloseParams {
+                asset: "ETH",
+                sz: None,
+                px: None,
+                slippage: None,
+                cloid: None,
+                signer: None,
+            })
+            .await?;
+
+        let actions = mock.actions();
+        let [action] = actions.as_slice() else {
+            panic!("expected one close order: {actions:?}");
+        };
+        assert_eq!(action.vault_address, Some(sub_account));
+        assert!(matches!(
+            &action.action,
+            Actions::Order(bulk) if matches!(
+                bulk.orders.as_slice(),
+                [order] if !order.is_buy && order.reduce_only && order.sz == "0.5"
+            )
+        ));
+        Ok(())
+    }
+
+    fn eth_limit_order(cloid: Option<uuid::Uuid>) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: 3000.0,
+            sz: 0.01,
+            cloid,
+            order_type: ClientOrder::Limit(ClientLimit {
+                tif: "Gtc".to_string(),
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_resend_the_same_request() -> Result<()> {
+        let (mock, exchange_client) = MockHyperliquid::exchange_client().await?;
+        let account = exchange_client.signer.address();
+        mock.fail_requests("/info", 2);
+        exchange_client.info.meta().await?;
+
+        mock.fail_requests("/exchange", 1);
+        let response = exchange_client.order(eth_limit_order(None), None).await?;
+        assert!(matches!(response, ExchangeResponseStatus::Ok(_)));
+        assert_eq!(mock.actions().len(), 1);
+
+        // The resent copy of a request whose response was lost is rejected for its nonce. The
+        // order is found by cloid, so its status is returned instead of placing it again.
+        mock.lose_responses(1);
+        let cloid = Uuid::new_v4();
+        let response = exchange_client
+            .order(eth_limit_order(Some(cloid)), None)
+            .await?;
+        assert_eq!(mock.actions().len(), 2);
+        let placed = exchange_client
+            .info
+            .query_order_by_cloid(account, cloid)
+            .await?
+            .order
+            .expect("order placed");
+        let ExchangeResponseStatus::Ok(response) = response else {
+            panic!("order rejected: {response:?}");
+        };
+        let statuses = response.data.map(|data| data.statuses).unwrap_or_default();
+        assert!(matches!(
+            statuses.as_slice(),
+            [ExchangeDataStatus::Filled(filled)]
+                if filled.oid == placed.order.oid && filled.total_sz == "0.01"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lost_partial_fill_reports_the_filled_size() -> Result<()> {
+        let (mock, exchange_client) = MockHyperliquid::exchange_client().await?;
+        mock.set_order_status(MockOrderStatus::PartiallyFilled(0.004));
+        mock.lose_responses(1);
+        let mut order = eth_limit_order(Some(Uuid::new_v4()));
+        order.order_type = ClientOrder::Limit(ClientLimit {
+            tif: "Ioc".to_string(),
+        });
+
+        // the order status is canceled, its fills give what it got
+        let response = exchange_client.order(order, None).await?;
+        let ExchangeResponseStatus::Ok(response) = response else {
+            panic!("order rejected: {response:?}");
+        };
+        let statuses = response.data.map(|data| data.statuses).unwrap_or_default();
+        assert!(matches!(
+            statuses.as_slice(),
+            [ExchangeDataStatus::Filled(filled)]
+                if filled.total_sz == "0.004" && filled.avg_px == "3000"
+        ));
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+use ethers::types::H160;
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug, Clone)]
@@ -13,6 +14,12 @@ pub struct FilledOrder {
     pub oid: u64,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningTwap {
+    pub twap_id: u64,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum ExchangeDataStatus {
@@ -22,11 +29,16 @@ pub enum ExchangeDataStatus {
     Error(String),
     Resting(RestingOrder),
     Filled(FilledOrder),
+    Running(RunningTwap),
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ExchangeDataStatuses {
+    #[serde(default)]
     pub statuses: Vec<ExchangeDataStatus>,
+    /// The single status of a twapOrder or twapCancel response.
+    #[serde(default)]
+    pub status: Option<ExchangeDataStatus>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -43,3 +55,19 @@ pub enum ExchangeResponseStatus {
     Ok(ExchangeResponse),
     Err(String),
 }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateSubAccountData {
+    #[serde(rename = "type")]
+    pub response_type: String,
+    /// Address of the new sub-account.
+    pub data: H160,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "status", content = "response")]
+pub enum CreateSubAccountResponse {
+    Ok(CreateSubAccountData),
+    Err(String),
+}
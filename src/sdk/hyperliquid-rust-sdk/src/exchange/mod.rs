@@ -5,6 +5,9 @@ mod exchange_client;
 mod exchange_responses;
 mod modify;
 mod order;
+mod price_source;
+mod twap;
+mod verify;
 
 pub use actions::*;
 pub use builder::*;
@@ -14,5 +17,8 @@ pub use exchange_responses::*;
 pub use modify::{ClientModifyRequest, ModifyRequest};
 pub use order::{
     ClientLimit, ClientOrder, ClientOrderRequest, ClientTrigger, MarketCloseParams,
-    MarketOrderParams, Order,
+    MarketOrderParams, Order, OrderRequest,
 };
+pub use price_source::{LiveMids, PriceSource};
+pub use twap::{ClientTwapRequest, TwapRequest};
+pub use verify::VerifiedPayload;
@@ -0,0 +1,91 @@
+use crate::{prelude::*, InfoClient};
+use futures_util::StreamExt;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+/// Reference prices for market orders, used instead of an `allMids` request when available.
+pub trait PriceSource: Debug + Send + Sync {
+    /// Current mid of `coin`, `None` if unknown or too old to trade on.
+    fn mid(&self, coin: &str) -> Option<f64>;
+}
+
+#[derive(Debug, Default)]
+struct Mids {
+    mids: HashMap<String, f64>,
+    updated_at: Option<Instant>,
+}
+
+/// Mids kept up to date from the `allMids` websocket channel.
+#[derive(Debug)]
+pub struct LiveMids {
+    mids: RwLock<Mids>,
+    max_age: Duration,
+}
+
+impl LiveMids {
+    /// Subscribes `info_client` to `allMids`. Mids older than `max_age` are not returned, so a
+    /// stalled feed falls back to a fresh request instead of pricing off a stale mid.
+    pub async fn start(info_client: &mut InfoClient, max_age: Duration) -> Result<Arc<LiveMids>> {
+        let mut stream = info_client.subscribe_all_mids().await?;
+        let live_mids = Arc::new(LiveMids {
+            mids: RwLock::new(Mids::default()),
+            max_age,
+        });
+
+        let updated = Arc::downgrade(&live_mids);
+        tokio::spawn(async move {
+            while let Some(all_mids) = stream.next().await {
+                let Some(live_mids) = updated.upgrade() else {
+                    return;
+                };
+                live_mids.update(all_mids.mids);
+            }
+        });
+        Ok(live_mids)
+    }
+
+    fn update(&self, mids: HashMap<String, String>) {
+        let mut current = self.mids.write().unwrap();
+        for (coin, mid) in mids {
+            if let Ok(mid) = mid.parse::<f64>() {
+                current.mids.insert(coin, mid);
+            }
+        }
+        current.updated_at = Some(Instant::now());
+    }
+}
+
+impl PriceSource for LiveMids {
+    fn mid(&self, coin: &str) -> Option<f64> {
+        let mids = self.mids.read().unwrap();
+        if mids.updated_at?.elapsed() > self.max_age {
+            return None;
+        }
+        mids.mids.get(coin).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_mids_expire() {
+        let live_mids = LiveMids {
+            mids: RwLock::new(Mids::default()),
+            max_age: Duration::from_millis(20),
+        };
+        assert_eq!(live_mids.mid("ETH"), None);
+
+        live_mids.update(HashMap::from([("ETH".to_string(), "3000.5".to_string())]));
+        assert_eq!(live_mids.mid("ETH"), Some(3000.5));
+        assert_eq!(live_mids.mid("BTC"), None);
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(live_mids.mid("ETH"), None);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{
+    prelude::*,
+    signature::{l1_action_hash, typed_data_hash},
+    Actions, Error, ExchangePayload,
+};
+use ethers::types::{H160, H256};
+
+/// A signed `/exchange` payload, decoded and checked by [`ExchangePayload::verify`].
+#[derive(Debug, Clone)]
+pub struct VerifiedPayload {
+    pub action: Actions,
+    /// Address recovered from the signature
+    pub signer: H160,
+    /// Whether `signer` is one of the expected addresses
+    pub is_expected: bool,
+}
+
+impl Actions {
+    /// Digest covered by the signature of this action: the EIP-712 hash of the action itself
+    /// for user-signed actions, of the phantom agent wrapping [`Actions::hash`] otherwise.
+    pub fn signing_hash(
+        &self,
+        nonce: u64,
+        vault_address: Option<H160>,
+        is_mainnet: bool,
+    ) -> Result<H256> {
+        match self {
+            Actions::UsdSend(usd_send) => typed_data_hash(usd_send),
+            Actions::Withdraw3(withdraw) => typed_data_hash(withdraw),
+            Actions::SpotSend(spot_send) => typed_data_hash(spot_send),
+            Actions::ApproveAgent(approve_agent) => typed_data_hash(approve_agent),
+            Actions::ApproveBuilderFee(approve_builder_fee) => typed_data_hash(approve_builder_fee),
+            _ => l1_action_hash(self.hash(nonce, vault_address)?, is_mainnet),
+        }
+    }
+}
+
+impl ExchangePayload {
+    pub fn decode_action(&self) -> Result<Actions> {
+        serde_json::from_value(self.action.clone()).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    /// Address that signed this payload. An action that does not round-trip through
+    /// [`Actions`], e.g. with unknown fields, hashes differently and recovers a wrong address.
+    pub fn recover_signer(&self, is_mainnet: bool) -> Result<H160> {
+        Ok(self.verify(is_mainnet, &[])?.signer)
+    }
+
+    /// Decodes the action and recovers its signer, checking it against `expected`, e.g. the
+    /// master account and its agents.
+    pub fn verify(&self, is_mainnet: bool, expected: &[H160]) -> Result<VerifiedPayload> {
+        let action = self.decode_action()?;
+        let hash = action.signing_hash(self.nonce, self.vault_address, is_mainnet)?;
+        let signer = self
+            .signature
+            .recover(hash)
+            .map_err(|e| Error::SignatureFailure(e.to_string()))?;
+        Ok(VerifiedPayload {
+            action,
+            signer,
+            is_expected: expected.contains(&signer),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        exchange::cancel::CancelRequest,
+        signature::{sign_l1_action, sign_typed_data},
+        ApproveBuilderFee, BulkCancel, Signer, UsdSend,
+    };
+    use ethers::signers::LocalWallet;
+
+    fn get_wallet() -> Result<LocalWallet> {
+        "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+            .parse::<LocalWallet>()
+            .map_err(|e| Error::Wallet(e.to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_verify_l1_action() -> Result<()> {
+        let wallet = get_wallet()?;
+        let vault_address = Some(H160::from_low_u64_be(9));
+        let action = Actions::Cancel(BulkCancel {
+            cancels: vec![CancelRequest { asset: 1, oid: 42 }],
+        });
+        let connection_id = action.hash(1583838, vault_address)?;
+        let mut payload = ExchangePayload {
+            action: serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?,
+            signature: sign_l1_action(&wallet, connection_id, false).await?,
+            nonce: 1583838,
+            vault_address,
+        };
+
+        let verified = payload.verify(false, &[wallet.address()])?;
+        assert_eq!(verified.signer, wallet.address());
+        assert!(verified.is_expected);
+        assert!(matches!(verified.action, Actions::Cancel(_)));
+        assert!(!payload.verify(true, &[wallet.address()])?.is_expected);
+
+        payload.nonce += 1;
+        assert_ne!(payload.recover_signer(false)?, wallet.address());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_user_signed_action() -> Result<()> {
+        let wallet = get_wallet()?;
+        let usd_send = UsdSend {
+            signature_chain_id: 421614.into(),
+            hyperliquid_chain: "Testnet".to_string(),
+            destination: "0x0D1d9635D0640821d15e323ac8AdADfA9c111414".to_string(),
+            amount: "1".to_string(),
+            time: 1690393044548,
+        };
+        let payload = ExchangePayload {
+            signature: sign_typed_data(&usd_send, &wallet).await?,
+            action: serde_json::to_value(Actions::UsdSend(usd_send))
+                .map_err(|e| Error::JsonParse(e.to_string()))?,
+            nonce: 1690393044548,
+            vault_address: None,
+        };
+
+        // the signing network is part of the action, not of the request
+        assert_eq!(payload.recover_signer(true)?, wallet.address());
+        assert!(!payload.verify(false, &[H160::zero()])?.is_expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_approve_builder_fee() -> Result<()> {
+        let wallet = get_wallet()?;
+        let approve_builder_fee = ApproveBuilderFee {
+            max_fee_rate: "0.001%".to_string(),
+            builder: "0x0D1d9635D0640821d15e323ac8AdADfA9c111414".to_string(),
+            nonce: 1690393044548,
+            signature_chain_id: 421614.into(),
+            hyperliquid_chain: "Testnet".to_string(),
+        };
+        let payload = ExchangePayload {
+            signature: sign_typed_data(&approve_builder_fee, &wallet).await?,
+            action: serde_json::to_value(Actions::ApproveBuilderFee(approve_builder_fee))
+                .map_err(|e| Error::JsonParse(e.to_string()))?,
+            nonce: 1690393044548,
+            vault_address: None,
+        };
+
+        assert_eq!(payload.recover_signer(false)?, wallet.address());
+        assert_eq!(payload.recover_signer(true)?, wallet.address());
+        Ok(())
+    }
+}
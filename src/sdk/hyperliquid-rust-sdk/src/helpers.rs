@@ -1,29 +1,13 @@
 use crate::{consts::*, prelude::*, Error};
 use chrono::prelude::Utc;
-use lazy_static::lazy_static;
-use log::info;
 use rand::{thread_rng, Rng};
-use std::sync::atomic::{AtomicU64, Ordering};
 use uuid::Uuid;
 
-fn now_timestamp_ms() -> u64 {
+pub(crate) fn now_timestamp_ms() -> u64 {
     let now = Utc::now();
     now.timestamp_millis() as u64
 }
 
-pub(crate) fn next_nonce() -> u64 {
-    let nonce = CUR_NONCE.fetch_add(1, Ordering::Relaxed);
-    let now_ms = now_timestamp_ms();
-    if nonce > now_ms + 1000 {
-        info!("nonce progressed too far ahead {nonce} {now_ms}");
-    }
-    // more than 300 seconds behind
-    if nonce + 300000 < now_ms {
-        CUR_NONCE.fetch_max(now_ms, Ordering::Relaxed);
-    }
-    nonce
-}
-
 pub(crate) const WIRE_DECIMALS: u8 = 8;
 
 pub(crate) fn float_to_string_for_hashing(x: f64) -> String {
@@ -76,31 +60,81 @@ pub fn bps_diff(x: f64, y: f64) -> u16 {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum BaseUrl {
     Localhost,
     Testnet,
     Mainnet,
+    /// A proxy, self-hosted node API or mock. `is_mainnet` picks the signing source, since it
+    /// can't be inferred from the URL.
+    Custom {
+        http: String,
+        ws: String,
+        is_mainnet: bool,
+    },
 }
 
 impl BaseUrl {
+    /// Custom endpoint serving the websocket API at `/ws` on the same host, like the public API.
+    pub fn custom(http: impl Into<String>, is_mainnet: bool) -> BaseUrl {
+        let http = http.into().trim_end_matches('/').to_string();
+        let ws = ws_url_for(&http);
+        BaseUrl::Custom {
+            http,
+            ws,
+            is_mainnet,
+        }
+    }
+
     pub(crate) fn get_url(&self) -> String {
         match self {
             BaseUrl::Localhost => LOCAL_API_URL.to_string(),
             BaseUrl::Mainnet => MAINNET_API_URL.to_string(),
             BaseUrl::Testnet => TESTNET_API_URL.to_string(),
+            BaseUrl::Custom { http, .. } => http.clone(),
+        }
+    }
+
+    pub(crate) fn get_ws_url(&self) -> String {
+        match self {
+            BaseUrl::Custom { ws, .. } => ws.clone(),
+            _ => ws_url_for(&self.get_url()),
+        }
+    }
+
+    pub(crate) fn is_mainnet(&self) -> bool {
+        match self {
+            BaseUrl::Mainnet => true,
+            BaseUrl::Localhost | BaseUrl::Testnet => false,
+            BaseUrl::Custom { is_mainnet, .. } => *is_mainnet,
         }
     }
 }
 
-lazy_static! {
-    static ref CUR_NONCE: AtomicU64 = AtomicU64::new(now_timestamp_ms());
+// http -> ws, https -> wss
+fn ws_url_for(http: &str) -> String {
+    format!("ws{}/ws", http.strip_prefix("http").unwrap_or(http))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn base_url_test() {
+        assert_eq!(
+            BaseUrl::Mainnet.get_ws_url(),
+            "wss://api.hyperliquid.xyz/ws"
+        );
+        assert_eq!(BaseUrl::Localhost.get_ws_url(), "ws://localhost:3001/ws");
+        assert!(BaseUrl::Mainnet.is_mainnet() && !BaseUrl::Testnet.is_mainnet());
+
+        let proxy = BaseUrl::custom("https://hl-proxy.internal/", true);
+        assert_eq!(proxy.get_url(), "https://hl-proxy.internal");
+        assert_eq!(proxy.get_ws_url(), "wss://hl-proxy.internal/ws");
+        assert!(proxy.is_mainnet());
+    }
+
     #[test]
     fn float_to_string_for_hashing_test() {
         assert_eq!(float_to_string_for_hashing(0.), "0".to_string());
@@ -1,21 +1,30 @@
 use crate::{
+    consts::LOCAL_API_URL,
+    helpers::uuid_to_hex_string,
     info::{
-        CandlesSnapshotResponse, FundingHistoryResponse, L2SnapshotResponse, OpenOrdersResponse,
-        OrderInfo, RecentTradesResponse, UserFillsResponse, UserStateResponse,
+        CandlesSnapshotResponse, ExtraAgent, FundingHistoryResponse, L2SnapshotResponse,
+        OpenOrdersResponse, OrderInfo, RecentTradesResponse, SubAccount, UserFillsResponse,
+        UserRateLimit, UserStateResponse,
     },
     meta::{Meta, SpotMeta, SpotMetaAndAssetCtxs},
     prelude::*,
     req::HttpClient,
-    ws::{Subscription, WsManager},
-    BaseUrl, Error, Message, OrderStatusResponse, ReferralResponse, UserFeesResponse,
-    UserFundingResponse, UserTokenBalanceResponse,
+    ws::{
+        bounded, FrameRecorder, MessageSender, OverflowPolicy, ReconnectConfig, ReplayServer,
+        Subscription, SubscriptionReceiver, SubscriptionStream, Transport, WsManager,
+    },
+    ActiveAssetCtxData, AllMidsData, BaseUrl, BboData, CandleData, Error, L2BookData, Message,
+    OrderStatusResponse, OrderUpdate, ReferralResponse, Trade, TwapHistory, TwapSliceFill,
+    UserActiveAssetData, UserData, UserFeesResponse, UserFillsData, UserFundingResponse,
+    UserFundingsData, UserTokenBalanceResponse, UserTwapHistoryData, UserTwapSliceFillsData,
 };
 
 use ethers::types::H160;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use tokio::sync::mpsc::UnboundedSender;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use uuid::Uuid;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -26,6 +35,14 @@ pub struct CandleSnapshotRequest {
     end_time: u64,
 }
 
+/// Orders can be looked up either by exchange oid or by the hex-encoded cloid
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum OrderStatusId {
+    Oid(u64),
+    Cloid(String),
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "camelCase")]
@@ -50,7 +67,7 @@ pub enum InfoRequest {
     },
     OrderStatus {
         user: H160,
-        oid: u64,
+        oid: OrderStatusId,
     },
     Meta,
     SpotMeta,
@@ -60,6 +77,12 @@ pub enum InfoRequest {
         user: H160,
     },
     #[serde(rename_all = "camelCase")]
+    UserFillsByTime {
+        user: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
     FundingHistory {
         coin: String,
         start_time: u64,
@@ -87,87 +110,438 @@ pub enum InfoRequest {
     HistoricalOrders {
         user: H160,
     },
+    TwapHistory {
+        user: H160,
+    },
+    UserTwapSliceFills {
+        user: H160,
+    },
+    SubAccounts {
+        user: H160,
+    },
+    ExtraAgents {
+        user: H160,
+    },
+    UserRateLimit {
+        user: H160,
+    },
 }
 
 #[derive(Debug)]
 pub struct InfoClient {
     pub http_client: HttpClient,
     pub(crate) ws_manager: Option<WsManager>,
-    reconnect: bool,
+    // Connections dedicated to a single user's userEvents, orderUpdates and notification channels
+    user_ws_managers: HashMap<H160, WsManager>,
+    subscription_users: HashMap<u32, Option<H160>>,
+    next_subscription_id: u32,
+    reconnect: Option<ReconnectConfig>,
+    transport: Transport,
+    ws_url: String,
+    recorder: Option<Arc<FrameRecorder>>,
 }
 
 impl InfoClient {
     pub async fn new(client: Option<Client>, base_url: Option<BaseUrl>) -> Result<InfoClient> {
-        Self::new_internal(client, base_url, false).await
+        Self::new_internal(client, base_url, None).await
     }
 
     pub async fn with_reconnect(
         client: Option<Client>,
         base_url: Option<BaseUrl>,
     ) -> Result<InfoClient> {
-        Self::new_internal(client, base_url, true).await
+        Self::new_internal(client, base_url, Some(ReconnectConfig::default())).await
+    }
+
+    pub async fn with_reconnect_config(
+        client: Option<Client>,
+        base_url: Option<BaseUrl>,
+        reconnect: ReconnectConfig,
+    ) -> Result<InfoClient> {
+        Self::new_internal(client, base_url, Some(reconnect)).await
     }
 
     async fn new_internal(
         client: Option<Client>,
         base_url: Option<BaseUrl>,
-        reconnect: bool,
+        reconnect: Option<ReconnectConfig>,
     ) -> Result<InfoClient> {
         let client = client.unwrap_or_default();
-        let base_url = base_url.unwrap_or(BaseUrl::Mainnet).get_url();
+        let base_url = base_url.unwrap_or(BaseUrl::Mainnet);
 
         Ok(InfoClient {
-            http_client: HttpClient { client, base_url },
+            http_client: HttpClient::new(client, base_url.get_url(), base_url.is_mainnet()),
             ws_manager: None,
+            user_ws_managers: HashMap::new(),
+            subscription_users: HashMap::new(),
+            next_subscription_id: 0,
             reconnect,
+            transport: Transport::Http,
+            ws_url: base_url.get_ws_url(),
+            recorder: None,
         })
     }
 
+    /// Client whose subscriptions are fed from a recording instead of the network.
+    pub async fn replay(replay: &ReplayServer) -> Result<InfoClient> {
+        let base_url = BaseUrl::Custom {
+            http: LOCAL_API_URL.to_string(),
+            ws: replay.url().to_string(),
+            is_mainnet: false,
+        };
+        Self::new_internal(None, Some(base_url), None).await
+    }
+
+    /// Records the raw frames of websocket connections opened from now on.
+    pub fn record_frames(&mut self, recorder: Arc<FrameRecorder>) {
+        self.recorder = Some(recorder);
+    }
+
     pub async fn subscribe(
         &mut self,
         subscription: Subscription,
         sender_channel: UnboundedSender<Message>,
     ) -> Result<u32> {
-        if self.ws_manager.is_none() {
-            let ws_manager = WsManager::new(
-                format!("ws{}/ws", &self.http_client.base_url[4..]),
-                self.reconnect,
-            )
+        self.add_subscription(subscription, MessageSender::Unbounded(sender_channel))
+            .await
+    }
+
+    /// Like [`InfoClient::subscribe`], but buffers at most `capacity` messages for a slow consumer
+    /// and resolves overflow with `policy` instead of growing without bound.
+    pub async fn subscribe_bounded(
+        &mut self,
+        subscription: Subscription,
+        capacity: usize,
+        policy: OverflowPolicy,
+    ) -> Result<(u32, SubscriptionReceiver)> {
+        let (sender, receiver) = bounded(capacity, policy);
+        let subscription_id = self
+            .add_subscription(subscription, MessageSender::Bounded(sender))
             .await?;
-            self.ws_manager = Some(ws_manager);
-        }
+        Ok((subscription_id, receiver))
+    }
+
+    pub async fn subscribe_all_mids(&mut self) -> Result<SubscriptionStream<AllMidsData>> {
+        self.subscribe_typed(Subscription::AllMids, |message| match message {
+            Message::AllMids(all_mids) => Some(all_mids.data),
+            _ => None,
+        })
+        .await
+    }
+
+    pub async fn subscribe_l2_book(
+        &mut self,
+        coin: String,
+    ) -> Result<SubscriptionStream<L2BookData>> {
+        self.subscribe_typed(Subscription::L2Book { coin }, |message| match message {
+            Message::L2Book(l2_book) => Some(l2_book.data),
+            _ => None,
+        })
+        .await
+    }
+
+    pub async fn subscribe_trades(
+        &mut self,
+        coin: String,
+    ) -> Result<SubscriptionStream<Vec<Trade>>> {
+        self.subscribe_typed(Subscription::Trades { coin }, |message| match message {
+            Message::Trades(trades) => Some(trades.data),
+            _ => None,
+        })
+        .await
+    }
+
+    pub async fn subscribe_candle(
+        &mut self,
+        coin: String,
+        interval: String,
+    ) -> Result<SubscriptionStream<CandleData>> {
+        self.subscribe_typed(
+            Subscription::Candle { coin, interval },
+            |message| match message {
+                Message::Candle(candle) => Some(candle.data),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    pub async fn subscribe_user_events(
+        &mut self,
+        user: H160,
+    ) -> Result<SubscriptionStream<UserData>> {
+        self.subscribe_typed(Subscription::UserEvents { user }, |message| match message {
+            Message::User(user_event) => Some(user_event.data),
+            _ => None,
+        })
+        .await
+    }
+
+    pub async fn subscribe_user_fills(
+        &mut self,
+        user: H160,
+    ) -> Result<SubscriptionStream<UserFillsData>> {
+        self.subscribe_typed(Subscription::UserFills { user }, |message| match message {
+            Message::UserFills(user_fills) => Some(user_fills.data),
+            _ => None,
+        })
+        .await
+    }
+
+    pub async fn subscribe_order_updates(
+        &mut self,
+        user: H160,
+    ) -> Result<SubscriptionStream<Vec<OrderUpdate>>> {
+        self.subscribe_typed(
+            Subscription::OrderUpdates { user },
+            |message| match message {
+                Message::OrderUpdates(order_updates) => Some(order_updates.data),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    pub async fn subscribe_user_fundings(
+        &mut self,
+        user: H160,
+    ) -> Result<SubscriptionStream<UserFundingsData>> {
+        self.subscribe_typed(
+            Subscription::UserFundings { user },
+            |message| match message {
+                Message::UserFundings(user_fundings) => Some(user_fundings.data),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    pub async fn subscribe_active_asset_ctx(
+        &mut self,
+        coin: String,
+    ) -> Result<SubscriptionStream<ActiveAssetCtxData>> {
+        self.subscribe_typed(
+            Subscription::ActiveAssetCtx { coin },
+            |message| match message {
+                Message::ActiveAssetCtx(active_asset_ctx) => Some(active_asset_ctx.data),
+                _ => None,
+            },
+        )
+        .await
+    }
 
+    pub async fn subscribe_bbo(&mut self, coin: String) -> Result<SubscriptionStream<BboData>> {
+        self.subscribe_typed(Subscription::Bbo { coin }, |message| match message {
+            Message::Bbo(bbo) => Some(bbo.data),
+            _ => None,
+        })
+        .await
+    }
+
+    pub async fn subscribe_active_asset_data(
+        &mut self,
+        user: H160,
+        coin: String,
+    ) -> Result<SubscriptionStream<UserActiveAssetData>> {
+        self.subscribe_typed(
+            Subscription::ActiveAssetData { user, coin },
+            |message| match message {
+                Message::ActiveAssetData(active_asset_data) => Some(active_asset_data.data),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    pub async fn subscribe_user_twap_slice_fills(
+        &mut self,
+        user: H160,
+    ) -> Result<SubscriptionStream<UserTwapSliceFillsData>> {
+        self.subscribe_typed(
+            Subscription::UserTwapSliceFills { user },
+            |message| match message {
+                Message::UserTwapSliceFills(slice_fills) => Some(slice_fills.data),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    pub async fn subscribe_user_twap_history(
+        &mut self,
+        user: H160,
+    ) -> Result<SubscriptionStream<UserTwapHistoryData>> {
+        self.subscribe_typed(
+            Subscription::UserTwapHistory { user },
+            |message| match message {
+                Message::UserTwapHistory(twap_history) => Some(twap_history.data),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_typed<T>(
+        &mut self,
+        subscription: Subscription,
+        extract: fn(Message) -> Option<T>,
+    ) -> Result<SubscriptionStream<T>> {
+        let user = subscription.connection_user();
+        let (sender, receiver) = unbounded_channel();
+        let subscription_id = self
+            .add_subscription(subscription, MessageSender::Unbounded(sender))
+            .await?;
+        let guard = self
+            .ws_manager_for(user)
+            .await?
+            .subscription_guard(subscription_id);
+        Ok(SubscriptionStream::new(
+            receiver,
+            extract,
+            subscription_id,
+            guard,
+        ))
+    }
+
+    async fn add_subscription(
+        &mut self,
+        subscription: Subscription,
+        sender_channel: MessageSender,
+    ) -> Result<u32> {
+        let user = subscription.connection_user();
         let identifier =
             serde_json::to_string(&subscription).map_err(|e| Error::JsonParse(e.to_string()))?;
 
-        self.ws_manager
-            .as_mut()
-            .ok_or(Error::WsManagerNotFound)?
-            .add_subscription(identifier, sender_channel)
-            .await
+        let subscription_id = self.next_subscription_id;
+        self.ws_manager_for(user)
+            .await?
+            .add_subscription(identifier, sender_channel, subscription_id)
+            .await?;
+        self.next_subscription_id += 1;
+        self.subscription_users.insert(subscription_id, user);
+        Ok(subscription_id)
     }
 
     pub async fn unsubscribe(&mut self, subscription_id: u32) -> Result<()> {
-        if self.ws_manager.is_none() {
-            let ws_manager = WsManager::new(
-                format!("ws{}/ws", &self.http_client.base_url[4..]),
-                self.reconnect,
-            )
-            .await?;
-            self.ws_manager = Some(ws_manager);
+        let user = self
+            .subscription_users
+            .remove(&subscription_id)
+            .ok_or(Error::SubscriptionNotFound)?;
+        match user {
+            Some(user) => {
+                let ws_manager = self
+                    .user_ws_managers
+                    .get_mut(&user)
+                    .ok_or(Error::WsManagerNotFound)?;
+                match ws_manager.remove_subscription(subscription_id).await {
+                    // already pruned by the manager once its receiver was dropped
+                    Ok(()) | Err(Error::SubscriptionNotFound) => {}
+                    Err(err) => return Err(err),
+                }
+                if ws_manager.is_empty().await {
+                    if let Some(mut ws_manager) = self.user_ws_managers.remove(&user) {
+                        ws_manager.close().await?;
+                    }
+                }
+                Ok(())
+            }
+            None => match self
+                .ws_manager
+                .as_mut()
+                .ok_or(Error::WsManagerNotFound)?
+                .remove_subscription(subscription_id)
+                .await
+            {
+                Ok(()) | Err(Error::SubscriptionNotFound) => Ok(()),
+                Err(err) => Err(err),
+            },
         }
+    }
 
-        self.ws_manager
-            .as_mut()
-            .ok_or(Error::WsManagerNotFound)?
-            .remove_subscription(subscription_id)
-            .await
+    /// Closes all websocket connections and waits for their background tasks to stop.
+    pub async fn close(&mut self) -> Result<()> {
+        let mut res = Ok(());
+        let user_ws_managers = self.user_ws_managers.drain().map(|(_, m)| m);
+        for mut ws_manager in self.ws_manager.take().into_iter().chain(user_ws_managers) {
+            if let Err(err) = ws_manager.close().await {
+                res = Err(err);
+            }
+        }
+        self.subscription_users.clear();
+        res
+    }
+
+    /// Closes the dedicated connections of users whose subscriptions are all gone, e.g. because
+    /// their streams were dropped, except `keep`'s.
+    async fn close_idle_user_connections(&mut self, keep: Option<H160>) -> Result<()> {
+        let mut idle = Vec::new();
+        for (user, ws_manager) in &self.user_ws_managers {
+            if Some(*user) != keep && ws_manager.is_empty().await {
+                idle.push(*user);
+            }
+        }
+        for user in idle {
+            self.subscription_users
+                .retain(|_, subscription_user| *subscription_user != Some(user));
+            if let Some(mut ws_manager) = self.user_ws_managers.remove(&user) {
+                ws_manager.close().await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn ws_manager_for(&mut self, user: Option<H160>) -> Result<&mut WsManager> {
+        self.close_idle_user_connections(user).await?;
+        let url = self.ws_url.clone();
+        match user {
+            Some(user) => {
+                if !self.user_ws_managers.contains_key(&user) {
+                    let ws_manager = WsManager::new(
+                        url,
+                        self.reconnect.clone(),
+                        Some(user),
+                        self.recorder.clone(),
+                    )
+                    .await?;
+                    self.user_ws_managers.insert(user, ws_manager);
+                }
+                self.user_ws_managers
+                    .get_mut(&user)
+                    .ok_or(Error::WsManagerNotFound)
+            }
+            None => {
+                if self.ws_manager.is_none() {
+                    let ws_manager =
+                        WsManager::new(url, self.reconnect.clone(), None, self.recorder.clone())
+                            .await?;
+                    self.ws_manager = Some(ws_manager);
+                }
+                self.ws_manager.as_mut().ok_or(Error::WsManagerNotFound)
+            }
+        }
+    }
+
+    /// Sends info requests over `transport` from now on; subscriptions are unaffected.
+    pub fn set_transport(&mut self, transport: Transport) {
+        self.transport = transport;
     }
 
     async fn send_info_request<T: for<'a> Deserialize<'a>>(
         &self,
         info_request: InfoRequest,
     ) -> Result<T> {
+        if let Transport::Ws(ws_post_client) = &self.transport {
+            let payload =
+                serde_json::to_value(&info_request).map_err(|e| Error::JsonParse(e.to_string()))?;
+            // posts count against the IP limit like HTTP requests
+            self.http_client
+                .rate_limiter
+                .acquire("/info", &payload.to_string(), None)
+                .await?;
+            let output = ws_post_client.post_info(&payload).await?;
+            return serde_json::from_value(output).map_err(|e| Error::JsonParse(e.to_string()));
+        }
+
         let data =
             serde_json::to_string(&info_request).map_err(|e| Error::JsonParse(e.to_string()))?;
 
@@ -225,6 +599,59 @@ impl InfoClient {
         self.send_info_request(input).await
     }
 
+    pub async fn user_fills_by_time(
+        &self,
+        address: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    ) -> Result<Vec<UserFillsResponse>> {
+        let input = InfoRequest::UserFillsByTime {
+            user: address,
+            start_time,
+            end_time,
+        };
+        self.send_info_request(input).await
+    }
+
+    pub async fn twap_history(&self, address: H160) -> Result<Vec<TwapHistory>> {
+        let input = InfoRequest::TwapHistory { user: address };
+        self.send_info_request(input).await
+    }
+
+    pub async fn user_twap_slice_fills(&self, address: H160) -> Result<Vec<TwapSliceFill>> {
+        let input = InfoRequest::UserTwapSliceFills { user: address };
+        self.send_info_request(input).await
+    }
+
+    /// Sub-accounts of the master account `address`, with their perp and spot balances.
+    pub async fn sub_accounts(&self, address: H160) -> Result<Vec<SubAccount>> {
+        let input = InfoRequest::SubAccounts { user: address };
+        // null when the account has no sub-accounts
+        let sub_accounts: Option<Vec<SubAccount>> = self.send_info_request(input).await?;
+        Ok(sub_accounts.unwrap_or_default())
+    }
+
+    /// Named agents approved by `address`.
+    pub async fn extra_agents(&self, address: H160) -> Result<Vec<ExtraAgent>> {
+        let input = InfoRequest::ExtraAgents { user: address };
+        self.send_info_request(input).await
+    }
+
+    pub async fn user_rate_limit(&self, address: H160) -> Result<UserRateLimit> {
+        let input = InfoRequest::UserRateLimit { user: address };
+        self.send_info_request(input).await
+    }
+
+    /// Queries `address`'s request budget and hands it to this client's rate limiter, which
+    /// [`crate::ExchangeClient::with_info`] clients share.
+    pub async fn sync_rate_limit(&self, address: H160) -> Result<UserRateLimit> {
+        let user_rate_limit = self.user_rate_limit(address).await?;
+        self.http_client
+            .rate_limiter
+            .sync(address, &user_rate_limit);
+        Ok(user_rate_limit)
+    }
+
     pub async fn funding_history(
         &self,
         coin: String,
@@ -282,7 +709,22 @@ impl InfoClient {
     }
 
     pub async fn query_order_by_oid(&self, address: H160, oid: u64) -> Result<OrderStatusResponse> {
-        let input = InfoRequest::OrderStatus { user: address, oid };
+        let input = InfoRequest::OrderStatus {
+            user: address,
+            oid: OrderStatusId::Oid(oid),
+        };
+        self.send_info_request(input).await
+    }
+
+    pub async fn query_order_by_cloid(
+        &self,
+        address: H160,
+        cloid: Uuid,
+    ) -> Result<OrderStatusResponse> {
+        let input = InfoRequest::OrderStatus {
+            user: address,
+            oid: OrderStatusId::Cloid(uuid_to_hex_string(cloid)),
+        };
         self.send_info_request(input).await
     }
 
@@ -296,3 +738,43 @@ impl InfoClient {
         self.send_info_request(input).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{str::FromStr, time::Duration};
+
+    #[tokio::test]
+    async fn test_typed_subscription_releases_user_connection() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "ws-empty-{}.jsonl",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        std::fs::write(&path, "").map_err(|e| Error::Replay(e.to_string()))?;
+        let replay = ReplayServer::start(&path, 0.0).await?;
+        let mut info_client = InfoClient::replay(&replay).await?;
+        let user = H160::from_str("0x0d1d9635d0640821d15e323ac8adadfa9c111414").unwrap();
+
+        let events = info_client.subscribe_user_events(user).await?;
+        assert_eq!(
+            info_client
+                .subscription_users
+                .get(&events.subscription_id()),
+            Some(&Some(user))
+        );
+        info_client.unsubscribe(events.subscription_id()).await?;
+        assert!(info_client.user_ws_managers.is_empty());
+        drop(events);
+
+        // a dropped stream leaves an empty connection, closed on the next subscribe
+        let orders = info_client.subscribe_order_updates(user).await?;
+        assert_eq!(info_client.user_ws_managers.len(), 1);
+        drop(orders);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let _mids = info_client.subscribe_all_mids().await?;
+        assert!(info_client.user_ws_managers.is_empty());
+        assert!(info_client.subscription_users.values().all(Option::is_none));
+        let _ = std::fs::remove_file(path);
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@ use crate::{
     info::{AssetPosition, Level, MarginSummary},
     DailyUserVlm, Delta, FeeSchedule, OrderInfo, Referrer, ReferrerState, UserTokenBalance,
 };
+use ethers::types::H160;
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
@@ -54,6 +55,10 @@ pub struct UserFillsResponse {
     pub sz: String,
     pub time: u64,
     pub fee: String,
+    pub tid: u64,
+    #[serde(default)]
+    pub fee_token: String,
+    pub cloid: Option<String>,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -132,3 +137,31 @@ pub struct ReferralResponse {
     pub claimed_rewards: String,
     pub referrer_state: ReferrerState,
 }
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubAccount {
+    pub name: String,
+    pub sub_account_user: H160,
+    pub master: H160,
+    pub clearinghouse_state: UserStateResponse,
+    pub spot_state: UserTokenBalanceResponse,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtraAgent {
+    pub name: String,
+    pub address: H160,
+    /// Expiry in ms
+    pub valid_until: u64,
+}
+
+/// Address-based request budget: a base allowance plus one request per USDC traded.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserRateLimit {
+    pub cum_vlm: String,
+    pub n_requests_used: u64,
+    pub n_requests_cap: u64,
+}
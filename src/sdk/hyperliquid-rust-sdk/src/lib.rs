@@ -6,10 +6,15 @@ mod helpers;
 mod info;
 mod market_maker;
 mod meta;
+#[cfg(any(test, feature = "test-support"))]
+mod mock_server;
+mod nonce;
 mod prelude;
 mod proxy_digest;
+mod rate_limit;
 mod req;
 mod signature;
+mod signer;
 mod ws;
 pub use consts::{EPSILON, LOCAL_API_URL, MAINNET_API_URL, TESTNET_API_URL};
 pub use errors::Error;
@@ -18,4 +23,11 @@ pub use helpers::{bps_diff, truncate_float, BaseUrl};
 pub use info::{info_client::*, *};
 pub use market_maker::{MarketMaker, MarketMakerInput, MarketMakerRestingOrder};
 pub use meta::*;
+#[cfg(any(test, feature = "test-support"))]
+pub use mock_server::*;
+pub use nonce::*;
+pub use rate_limit::*;
+pub use req::{RetryPolicy, Timeouts};
+pub use signature::{recover_l1_action_signer, sign_l1_action, sign_typed_data};
+pub use signer::*;
 pub use ws::*;
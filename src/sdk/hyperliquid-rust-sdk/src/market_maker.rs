@@ -2,14 +2,13 @@ use ethers::{
     signers::{LocalWallet, Signer},
     types::H160,
 };
+use futures_util::StreamExt;
 use log::{error, info};
 
-use tokio::sync::mpsc::unbounded_channel;
-
 use crate::{
     bps_diff, truncate_float, BaseUrl, ClientCancelRequest, ClientLimit, ClientOrder,
     ClientOrderRequest, ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus, InfoClient,
-    Message, Subscription, UserData, EPSILON,
+    UserData, EPSILON,
 };
 #[derive(Debug)]
 pub struct MarketMakerRestingOrder {
@@ -82,30 +81,20 @@ impl MarketMaker {
     }
 
     pub async fn start(&mut self) {
-        let (sender, mut receiver) = unbounded_channel();
-
         // Subscribe to UserEvents for fills
-        self.info_client
-            .subscribe(
-                Subscription::UserEvents {
-                    user: self.user_address,
-                },
-                sender.clone(),
-            )
+        let mut user_events = self
+            .info_client
+            .subscribe_user_events(self.user_address)
             .await
             .unwrap();
 
         // Subscribe to AllMids so we can market make around the mid price
-        self.info_client
-            .subscribe(Subscription::AllMids, sender)
-            .await
-            .unwrap();
+        let mut all_mids = self.info_client.subscribe_all_mids().await.unwrap();
 
         loop {
-            let message = receiver.recv().await.unwrap();
-            match message {
-                Message::AllMids(all_mids) => {
-                    let all_mids = all_mids.data.mids;
+            tokio::select! {
+                Some(all_mids) = all_mids.next() => {
+                    let all_mids = all_mids.mids;
                     let mid = all_mids.get(&self.asset);
                     if let Some(mid) = mid {
                         let mid: f64 = mid.parse().unwrap();
@@ -119,12 +108,11 @@ impl MarketMaker {
                         );
                     }
                 }
-                Message::User(user_events) => {
+                Some(user_events) = user_events.next() => {
                     // We haven't seen the first mid price event yet, so just continue
                     if self.latest_mid_price < 0.0 {
                         continue;
                     }
-                    let user_events = user_events.data;
                     if let UserData::Fills(fills) = user_events {
                         for fill in fills {
                             let amount: f64 = fill.sz.parse().unwrap();
@@ -143,8 +131,9 @@ impl MarketMaker {
                     // Check to see if we need to cancel or place any new orders
                     self.potentially_update().await;
                 }
-                _ => {
-                    panic!("Unsupported message type");
+                else => {
+                    error!("market maker subscriptions closed");
+                    break;
                 }
             }
         }
@@ -43,6 +43,31 @@ impl SpotMeta {
 
         coin_to_asset
     }
+
+    /// Size decimals of each pair's base token, under the same names as
+    /// [`SpotMeta::add_pair_and_name_to_index_map`].
+    pub fn add_pair_and_name_to_sz_decimals_map(
+        &self,
+        mut sz_decimals: HashMap<String, u32>,
+    ) -> HashMap<String, u32> {
+        let index_to_token: HashMap<usize, &TokenInfo> =
+            self.tokens.iter().map(|info| (info.index, info)).collect();
+
+        for asset in self.universe.iter() {
+            let Some(base) = index_to_token.get(&asset.tokens[0]) else {
+                continue;
+            };
+            let Some(quote) = index_to_token.get(&asset.tokens[1]) else {
+                continue;
+            };
+
+            let decimals = base.sz_decimals as u32;
+            sz_decimals.insert(format!("{}/{}", base.name, quote.name), decimals);
+            sz_decimals.insert(asset.name.clone(), decimals);
+        }
+
+        sz_decimals
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -89,3 +114,28 @@ pub struct TokenInfo {
     pub token_id: H128,
     pub is_canonical: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spot_sz_decimals_from_base_token() {
+        let spot_meta: SpotMeta = serde_json::from_value(serde_json::json!({
+            "universe": [{ "tokens": [1, 0], "name": "@1", "index": 1, "isCanonical": false }],
+            "tokens": [
+                { "name": "USDC", "szDecimals": 8, "weiDecimals": 8, "index": 0,
+                  "tokenId": "0x6d1e7cde53ba9467b783cb7c530ce054", "isCanonical": true },
+                { "name": "HFUN", "szDecimals": 2, "weiDecimals": 8, "index": 1,
+                  "tokenId": "0xbaf265ef389da684513d98d68edf4eae", "isCanonical": false },
+            ],
+        }))
+        .unwrap();
+
+        let sz_decimals =
+            spot_meta.add_pair_and_name_to_sz_decimals_map(HashMap::from([("ETH".to_string(), 4)]));
+        assert_eq!(sz_decimals["HFUN/USDC"], 2);
+        assert_eq!(sz_decimals["@1"], 2);
+        assert_eq!(sz_decimals["ETH"], 4);
+    }
+}
@@ -0,0 +1,881 @@
+use crate::{
+    helpers::now_timestamp_ms, prelude::*, Actions, ApproveAgent, BaseUrl, Error, ExchangeClient,
+    ExchangePayload, InfoClient, Order, OrderRequest, RateLimiter, ScheduleCancel, VerifiedPayload,
+    LOCAL_API_URL,
+};
+use axum::{
+    extract::{
+        ws::{Message as WsMessage, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::H160,
+};
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, warn};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    net::TcpListener,
+    sync::broadcast,
+    time::{self, Instant},
+};
+
+/// Address `BaseUrl::Localhost` points at.
+pub const LOCAL_MOCK_ADDR: &str = "127.0.0.1:3001";
+
+/// Private key of the wallet [`MockHyperliquid::start_local`] hands out.
+pub const MOCK_WALLET_KEY: &str =
+    "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e";
+
+/// What the mock answers to orders.
+#[derive(Debug, Clone)]
+pub enum MockOrderStatus {
+    /// Fully filled at the order's limit price
+    Filled,
+    /// Fills this size at the order's limit price and cancels the rest, like an IOC order
+    PartiallyFilled(f64),
+    Resting,
+    Error(String),
+}
+
+/// An `/exchange` request that passed signature checks.
+#[derive(Debug, Clone)]
+pub struct ReceivedAction {
+    pub signer: H160,
+    pub nonce: u64,
+    pub vault_address: Option<H160>,
+    pub action: Actions,
+}
+
+/// An agent approved through `approveAgent`.
+#[derive(Debug, Clone)]
+struct MockAgent {
+    master: H160,
+    name: String,
+    address: H160,
+    valid_until: Option<u64>,
+}
+
+/// An accepted order, kept for `orderStatus` lookups.
+#[derive(Debug, Clone)]
+struct MockOrder {
+    // vault, else the agent's master, else the signer
+    user: H160,
+    oid: u64,
+    coin: String,
+    timestamp: u64,
+    filled_sz: f64,
+    // "open", "filled" or "canceled"
+    status: &'static str,
+    order: OrderRequest,
+}
+
+#[derive(Debug)]
+struct MockState {
+    is_mainnet: bool,
+    info: Mutex<HashMap<String, Value>>,
+    order_status: Mutex<MockOrderStatus>,
+    expected_signer: Mutex<Option<H160>>,
+    agents: Mutex<Vec<MockAgent>>,
+    actions: Mutex<Vec<ReceivedAction>>,
+    orders: Mutex<Vec<MockOrder>>,
+    // assetPositions of clearinghouseState per user
+    positions: Mutex<HashMap<H160, Vec<Value>>>,
+    used_nonces: Mutex<HashSet<(H160, u64)>>,
+    // pending injected failures per path, and /exchange requests whose response is dropped
+    failures: Mutex<HashMap<&'static str, usize>>,
+    lost_responses: Mutex<usize>,
+    info_requests: Mutex<Vec<Value>>,
+    subscriptions: Mutex<Vec<Value>>,
+    next_oid: AtomicU64,
+    frames: broadcast::Sender<String>,
+}
+
+/// In-process Hyperliquid for testing clients without the network: `/info` answers from preset
+/// data, `/exchange` verifies signatures and answers with the configured order status, `/ws`
+/// pushes frames injected by the test.
+#[derive(Debug, Clone)]
+pub struct MockHyperliquid {
+    state: Arc<MockState>,
+}
+
+impl Default for MockHyperliquid {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl MockHyperliquid {
+    /// `is_mainnet` selects the signature source and must match the client's `BaseUrl`.
+    pub fn new(is_mainnet: bool) -> Self {
+        let (frames, _) = broadcast::channel(1024);
+        MockHyperliquid {
+            state: Arc::new(MockState {
+                is_mainnet,
+                info: Mutex::new(default_info()),
+                order_status: Mutex::new(MockOrderStatus::Filled),
+                expected_signer: Mutex::new(None),
+                agents: Mutex::new(Vec::new()),
+                actions: Mutex::new(Vec::new()),
+                orders: Mutex::new(Vec::new()),
+                positions: Mutex::new(HashMap::new()),
+                used_nonces: Mutex::new(HashSet::new()),
+                failures: Mutex::new(HashMap::new()),
+                lost_responses: Mutex::new(0),
+                info_requests: Mutex::new(Vec::new()),
+                subscriptions: Mutex::new(Vec::new()),
+                next_oid: AtomicU64::new(1),
+                frames,
+            }),
+        }
+    }
+
+    /// Starts a testnet mock on a free local port, returning it with its `BaseUrl` and the
+    /// wallet for [`MOCK_WALLET_KEY`].
+    pub async fn start_local() -> Result<(MockHyperliquid, BaseUrl, LocalWallet)> {
+        let mock = MockHyperliquid::new(false);
+        let base_url = mock.serve_local().await?;
+        let wallet = MOCK_WALLET_KEY
+            .parse()
+            .map_err(|e: ethers::signers::WalletError| Error::Wallet(e.to_string()))?;
+        Ok((mock, base_url, wallet))
+    }
+
+    /// Starts a local mock that only accepts the fixture wallet and its agents, and a client
+    /// signing as that wallet. The client's [`ExchangeClient::info`] queries the same mock and
+    /// has a rate limiter of its own, so parallel tests don't share the process-wide budget.
+    pub async fn exchange_client() -> Result<(MockHyperliquid, ExchangeClient)> {
+        let (mock, base_url, wallet) = MockHyperliquid::start_local().await?;
+        mock.expect_signer(wallet.address());
+        let mut info = InfoClient::new(None, Some(base_url)).await?;
+        info.http_client.rate_limiter = Arc::new(RateLimiter::default());
+        let exchange_client =
+            ExchangeClient::with_info(None, wallet, Arc::new(info), None, None).await?;
+        Ok((mock, exchange_client))
+    }
+
+    /// Sets the response to an `/info` request type such as "meta" or "l2Book".
+    pub fn set_info(&self, info_type: &str, response: Value) {
+        self.state
+            .info
+            .lock()
+            .unwrap()
+            .insert(info_type.to_string(), response);
+    }
+
+    /// Gives `user` a cross position of `szi` in `coin`, shown by its `clearinghouseState`.
+    pub fn set_position(&self, user: H160, coin: &str, szi: f64) {
+        let position = json!({
+            "type": "oneWay",
+            "position": {
+                "coin": coin,
+                "entryPx": "3000.0",
+                "leverage": { "type": "cross", "value": 1 },
+                "liquidationPx": null,
+                "marginUsed": "0.0",
+                "positionValue": "0.0",
+                "returnOnEquity": "0.0",
+                "szi": szi.to_string(),
+                "unrealizedPnl": "0.0",
+                "maxLeverage": 50,
+                "cumFunding": { "allTime": "0.0", "sinceOpen": "0.0", "sinceChange": "0.0" },
+            },
+        });
+        let mut positions = self.state.positions.lock().unwrap();
+        let positions = positions.entry(user).or_default();
+        positions.retain(|p| p["position"]["coin"] != coin);
+        positions.push(position);
+    }
+
+    pub fn set_order_status(&self, status: MockOrderStatus) {
+        *self.state.order_status.lock().unwrap() = status;
+    }
+
+    /// Only accepts requests signed by `signer` or one of its live agents. Without it any
+    /// request with a recoverable signer is accepted.
+    pub fn expect_signer(&self, signer: H160) {
+        *self.state.expected_signer.lock().unwrap() = Some(signer);
+    }
+
+    /// Approves `agent` to sign for `master` without going through `approveAgent`.
+    pub fn set_agent(&self, master: H160, agent: H160) {
+        self.state.agents.lock().unwrap().push(MockAgent {
+            master,
+            name: String::new(),
+            address: agent,
+            valid_until: None,
+        });
+    }
+
+    /// Answers the next `count` requests to `path` ("/info" or "/exchange") with a 502 without
+    /// handling them.
+    pub fn fail_requests(&self, path: &'static str, count: usize) {
+        self.state.failures.lock().unwrap().insert(path, count);
+    }
+
+    /// Handles the next `count` `/exchange` requests but answers with a 502, as if the order
+    /// went through and the response was lost.
+    pub fn lose_responses(&self, count: usize) {
+        *self.state.lost_responses.lock().unwrap() = count;
+    }
+
+    pub fn actions(&self) -> Vec<ReceivedAction> {
+        self.state.actions.lock().unwrap().clone()
+    }
+
+    /// `/info` requests received so far, including those sent as websocket posts.
+    pub fn info_requests(&self) -> Vec<Value> {
+        self.state.info_requests.lock().unwrap().clone()
+    }
+
+    /// Active `/ws` subscriptions.
+    pub fn subscriptions(&self) -> Vec<Value> {
+        self.state.subscriptions.lock().unwrap().clone()
+    }
+
+    /// Waits for a subscription of the given type, returning false on timeout.
+    pub async fn wait_for_subscription(&self, subscription_type: &str, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            let subscribed = self
+                .subscriptions()
+                .iter()
+                .any(|subscription| subscription["type"] == subscription_type);
+            if subscribed {
+                return true;
+            }
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        false
+    }
+
+    /// Pushes a raw frame to every `/ws` connection.
+    pub fn push_frame(&self, frame: Value) {
+        let _ = self.state.frames.send(frame.to_string());
+    }
+
+    /// Pushes a userEvents fills frame, `fills` being an array of `TradeInfo`.
+    pub fn push_user_fills(&self, fills: Value) {
+        self.push_frame(json!({ "channel": "user", "data": { "fills": fills } }));
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/info", post(info))
+            .route("/exchange", post(exchange))
+            .route("/ws", get(ws))
+            .with_state(self.state.clone())
+    }
+
+    /// Serves in the background and returns the bound address; port 0 picks a free one.
+    pub async fn serve(&self, addr: &str) -> Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| Error::GenericRequest(e.to_string()))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| Error::GenericRequest(e.to_string()))?;
+        let app = self.router();
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                warn!("Mock server stopped: {e}");
+            }
+        });
+        debug!("Mock server listening on {local_addr}, {LOCAL_API_URL} maps to {LOCAL_MOCK_ADDR}");
+        Ok(local_addr)
+    }
+
+    /// Serves on a free local port and returns a `BaseUrl` for it, so tests can run in parallel.
+    pub async fn serve_local(&self) -> Result<BaseUrl> {
+        let addr = self.serve("127.0.0.1:0").await?;
+        Ok(BaseUrl::custom(
+            format!("http://{addr}"),
+            self.state.is_mainnet,
+        ))
+    }
+}
+
+fn default_info() -> HashMap<String, Value> {
+    let mut info = HashMap::new();
+    info.insert(
+        "meta".to_string(),
+        json!({ "universe": [
+            { "name": "BTC", "szDecimals": 5, "maxLeverage": 50 },
+            { "name": "ETH", "szDecimals": 4, "maxLeverage": 50 },
+        ]}),
+    );
+    info.insert(
+        "spotMeta".to_string(),
+        json!({
+            "universe": [
+                { "tokens": [1, 0], "name": "PURR/USDC", "index": 0, "isCanonical": true },
+            ],
+            "tokens": [
+                { "name": "USDC", "szDecimals": 8, "weiDecimals": 8, "index": 0,
+                  "tokenId": "0x6d1e7cde53ba9467b783cb7c530ce054", "isCanonical": true },
+                { "name": "PURR", "szDecimals": 0, "weiDecimals": 5, "index": 1,
+                  "tokenId": "0xc1fb593aeffbeb02f85e0308e9956a90", "isCanonical": true },
+            ],
+        }),
+    );
+    let empty_summary = json!({
+        "accountValue": "1000.0",
+        "totalMarginUsed": "0.0",
+        "totalNtlPos": "0.0",
+        "totalRawUsd": "1000.0",
+    });
+    info.insert(
+        "clearinghouseState".to_string(),
+        json!({
+            "assetPositions": [],
+            "crossMarginSummary": empty_summary,
+            "marginSummary": empty_summary,
+            "withdrawable": "1000.0",
+        }),
+    );
+    info.insert(
+        "allMids".to_string(),
+        json!({ "BTC": "60000.0", "ETH": "3000.0", "PURR/USDC": "0.2" }),
+    );
+    info.insert(
+        "l2Book".to_string(),
+        json!({
+            "coin": "ETH",
+            "time": 0,
+            "levels": [
+                [{ "px": "2999.9", "sz": "10.0", "n": 1 }],
+                [{ "px": "3000.1", "sz": "10.0", "n": 1 }],
+            ],
+        }),
+    );
+    for info_type in ["userFillsByTime", "twapHistory", "userTwapSliceFills"] {
+        info.insert(info_type.to_string(), json!([]));
+    }
+    info.insert(
+        "userRateLimit".to_string(),
+        json!({ "cumVlm": "0.0", "nRequestsUsed": 0, "nRequestsCap": 10000 }),
+    );
+    // the exchange answers null when there are no sub-accounts
+    info.insert("subAccounts".to_string(), Value::Null);
+    info
+}
+
+// Decrements a pending count, returning whether one was left
+fn take(counter: &mut usize) -> bool {
+    let available = *counter > 0;
+    *counter = counter.saturating_sub(1);
+    available
+}
+
+fn bad_gateway() -> Response {
+    (StatusCode::BAD_GATEWAY, "injected failure").into_response()
+}
+
+async fn info(State(state): State<Arc<MockState>>, Json(request): Json<Value>) -> Response {
+    if state.take_failure("/info") {
+        return bad_gateway();
+    }
+    match state.info_response(&request) {
+        Some(response) => Json(response).into_response(),
+        None => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("unsupported info request: {request}"),
+        )
+            .into_response(),
+    }
+}
+
+async fn exchange(State(state): State<Arc<MockState>>, Json(request): Json<Value>) -> Response {
+    if state.take_failure("/exchange") {
+        return bad_gateway();
+    }
+    let response = state.exchange_response(request);
+    if take(&mut state.lost_responses.lock().unwrap()) {
+        return bad_gateway();
+    }
+    Json(response).into_response()
+}
+
+async fn ws(State(state): State<Arc<MockState>>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| serve_socket(state, socket))
+}
+
+async fn serve_socket(state: Arc<MockState>, socket: WebSocket) {
+    let mut frames = state.frames.subscribe();
+    let (mut writer, mut reader) = socket.split();
+    loop {
+        let outgoing = tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(frame) => frame,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Mock server dropped {skipped} frames");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            message = reader.next() => match message {
+                Some(Ok(WsMessage::Text(text))) => match state.ws_response(&text) {
+                    Some(response) => response.to_string(),
+                    None => continue,
+                },
+                Some(Ok(_)) => continue,
+                _ => return,
+            },
+        };
+        if writer.send(WsMessage::Text(outgoing)).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Checks the exchange applies to action contents.
+fn validate(action: ReceivedAction) -> std::result::Result<ReceivedAction, String> {
+    if let Actions::ScheduleCancel(ScheduleCancel { time: Some(time) }) = &action.action {
+        if *time < now_timestamp_ms() + 5_000 {
+            return Err(
+                "Scheduled cancel time too early, must be at least 5 seconds from now.".to_string(),
+            );
+        }
+    }
+    Ok(action)
+}
+
+impl MockOrder {
+    /// The `order` object of an `orderStatus` response.
+    fn to_status(&self) -> Value {
+        let (order_type, tif, trigger_px, trigger_condition) = match &self.order.order_type {
+            Order::Limit(limit) => ("Limit".to_string(), limit.tif.clone(), "0.0", "N/A"),
+            Order::Trigger(trigger) => {
+                let kind = if trigger.tpsl == "tp" {
+                    "Take Profit"
+                } else {
+                    "Stop"
+                };
+                let execution = if trigger.is_market { "Market" } else { "Limit" };
+                (
+                    format!("{kind} {execution}"),
+                    "Gtc".to_string(),
+                    trigger.trigger_px.as_str(),
+                    "Triggered",
+                )
+            }
+        };
+        let orig_sz: f64 = self.order.sz.parse().unwrap_or_default();
+        let remaining = (orig_sz - self.filled_sz).to_string();
+        json!({
+            "order": {
+                "coin": self.coin,
+                "side": if self.order.is_buy { "B" } else { "A" },
+                "limitPx": self.order.limit_px,
+                "sz": remaining,
+                "oid": self.oid,
+                "timestamp": self.timestamp,
+                "triggerCondition": trigger_condition,
+                "isTrigger": matches!(self.order.order_type, Order::Trigger(_)),
+                "triggerPx": trigger_px,
+                "isPositionTpsl": false,
+                "reduceOnly": self.order.reduce_only,
+                "orderType": order_type,
+                "origSz": self.order.sz,
+                "tif": tif,
+                "cloid": self.order.cloid,
+            },
+            "status": self.status,
+            "statusTimestamp": self.timestamp,
+        })
+    }
+
+    /// The order's fill as a `userFills` entry, if it matched.
+    fn to_fill(&self) -> Option<Value> {
+        if self.filled_sz == 0.0 {
+            return None;
+        }
+        Some(json!({
+            "coin": self.coin,
+            "px": self.order.limit_px,
+            "sz": self.filled_sz.to_string(),
+            "side": if self.order.is_buy { "B" } else { "A" },
+            "time": self.timestamp,
+            "startPosition": "0.0",
+            "dir": if self.order.is_buy { "Buy" } else { "Sell" },
+            "closedPnl": "0.0",
+            "hash": "0x0",
+            "oid": self.oid,
+            "crossed": true,
+            "fee": "0.0",
+            "tid": self.oid,
+            "feeToken": "USDC",
+            "cloid": self.order.cloid,
+        }))
+    }
+}
+
+impl MockState {
+    fn take_failure(&self, path: &'static str) -> bool {
+        self.failures
+            .lock()
+            .unwrap()
+            .get_mut(path)
+            .is_some_and(take)
+    }
+
+    fn info_response(&self, request: &Value) -> Option<Value> {
+        self.info_requests.lock().unwrap().push(request.clone());
+        let info_type = request["type"].as_str()?;
+        if info_type == "extraAgents" {
+            let user: H160 = serde_json::from_value(request["user"].clone()).ok()?;
+            return Some(self.extra_agents(user));
+        }
+        if info_type == "userFills" {
+            let user: H160 = serde_json::from_value(request["user"].clone()).ok()?;
+            return Some(self.user_fills(user));
+        }
+        if info_type == "orderStatus" {
+            let user: H160 = serde_json::from_value(request["user"].clone()).ok()?;
+            return Some(self.order_status(user, &request["oid"]));
+        }
+        let mut response = self.info.lock().unwrap().get(info_type).cloned()?;
+        if info_type == "clearinghouseState" {
+            let user: H160 = serde_json::from_value(request["user"].clone()).ok()?;
+            if let Some(positions) = self.positions.lock().unwrap().get(&user) {
+                response["assetPositions"] = json!(positions);
+            }
+        }
+        Some(response)
+    }
+
+    fn exchange_response(&self, request: Value) -> Value {
+        match self.verify(request).and_then(validate) {
+            Ok(action) => {
+                if let Actions::ApproveAgent(approve_agent) = &action.action {
+                    self.approve_agent(action.signer, approve_agent);
+                }
+                let response = self.action_response(&action);
+                self.actions.lock().unwrap().push(action);
+                json!({ "status": "ok", "response": response })
+            }
+            Err(e) => json!({ "status": "err", "response": e }),
+        }
+    }
+
+    /// Decodes the action and recovers its signer with `ExchangePayload::verify`.
+    fn verify(&self, request: Value) -> std::result::Result<ReceivedAction, String> {
+        let request: ExchangePayload =
+            serde_json::from_value(request).map_err(|e| format!("invalid request: {e}"))?;
+        let VerifiedPayload { action, signer, .. } = request
+            .verify(self.is_mainnet, &[])
+            .map_err(|e| format!("invalid action: {e}"))?;
+        // agents can't approve other agents
+        if matches!(action, Actions::ApproveAgent(_)) && self.agent_master(signer).is_some() {
+            return Err("Agent cannot approve another agent.".to_string());
+        }
+        if let Some(expected) = *self.expected_signer.lock().unwrap() {
+            if signer != expected && self.agent_master(signer) != Some(expected) {
+                return Err(format!("User or API Wallet {signer:?} does not exist."));
+            }
+        }
+        // each nonce can be used once per signer
+        if !self
+            .used_nonces
+            .lock()
+            .unwrap()
+            .insert((signer, request.nonce))
+        {
+            return Err(format!("Invalid nonce: duplicate nonce {}", request.nonce));
+        }
+        Ok(ReceivedAction {
+            signer,
+            nonce: request.nonce,
+            vault_address: request.vault_address,
+            action,
+        })
+    }
+
+    /// A new approval replaces the master's agent of the same name; `valid_until` in the name is
+    /// the expiry.
+    fn approve_agent(&self, master: H160, approve_agent: &ApproveAgent) {
+        let agent_name = approve_agent.agent_name.clone().unwrap_or_default();
+        let (name, valid_until) = match agent_name.split_once(" valid_until ") {
+            Some((name, valid_until)) => (name.to_string(), valid_until.parse().ok()),
+            None => (agent_name, None),
+        };
+        let mut agents = self.agents.lock().unwrap();
+        agents.retain(|agent| agent.master != master || agent.name != name);
+        agents.push(MockAgent {
+            master,
+            name,
+            address: approve_agent.agent_address,
+            valid_until,
+        });
+    }
+
+    /// The agent's master, `None` if not approved or expired.
+    fn agent_master(&self, address: H160) -> Option<H160> {
+        let now = now_timestamp_ms();
+        self.agents
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|agent| {
+                agent.address == address && agent.valid_until.is_none_or(|until| until > now)
+            })
+            .map(|agent| agent.master)
+    }
+
+    /// Looks up `user`'s order by numeric oid or cloid.
+    fn order_status(&self, user: H160, id: &Value) -> Value {
+        let orders = self.orders.lock().unwrap();
+        let order = orders.iter().find(|order| {
+            order.user == user
+                && match id {
+                    Value::Number(oid) => oid.as_u64() == Some(order.oid),
+                    Value::String(cloid) => order.order.cloid.as_ref() == Some(cloid),
+                    _ => false,
+                }
+        });
+        match order {
+            Some(order) => json!({ "status": "order", "order": order.to_status() }),
+            None => json!({ "status": "unknownOid" }),
+        }
+    }
+
+    fn user_fills(&self, user: H160) -> Value {
+        let fills: Vec<Value> = self
+            .orders
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|order| order.user == user)
+            .filter_map(MockOrder::to_fill)
+            .collect();
+        json!(fills)
+    }
+
+    // Coin name for an order's asset index, spot assets starting at 10000
+    fn coin_name(&self, asset: u32) -> String {
+        let info = self.info.lock().unwrap();
+        let name = match asset.checked_sub(10_000) {
+            None => info["meta"]["universe"][asset as usize]["name"].as_str(),
+            Some(index) => info["spotMeta"]["universe"]
+                .as_array()
+                .and_then(|universe| universe.iter().find(|pair| pair["index"] == index))
+                .and_then(|pair| pair["name"].as_str()),
+        };
+        name.map_or_else(|| asset.to_string(), str::to_string)
+    }
+
+    fn extra_agents(&self, master: H160) -> Value {
+        let agents: Vec<Value> = self
+            .agents
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|agent| agent.master == master && !agent.name.is_empty())
+            .map(|agent| {
+                json!({
+                    "name": agent.name,
+                    "address": agent.address,
+                    "validUntil": agent.valid_until.unwrap_or(u64::MAX),
+                })
+            })
+            .collect();
+        json!(agents)
+    }
+
+    fn action_response(&self, received: &ReceivedAction) -> Value {
+        let statuses: Vec<Value> = match &received.action {
+            Actions::Order(bulk_order) => {
+                let status = self.order_status.lock().unwrap().clone();
+                let user = received
+                    .vault_address
+                    .or_else(|| self.agent_master(received.signer))
+                    .unwrap_or(received.signer);
+                let statuses: Vec<Value> = bulk_order
+                    .orders
+                    .iter()
+                    .map(|order| {
+                        let oid = self.next_oid.fetch_add(1, Ordering::Relaxed);
+                        let (filled_sz, order_status) = match &status {
+                            MockOrderStatus::Filled => {
+                                (order.sz.parse().unwrap_or_default(), "filled")
+                            }
+                            MockOrderStatus::PartiallyFilled(sz) => (*sz, "canceled"),
+                            MockOrderStatus::Resting => (0.0, "open"),
+                            MockOrderStatus::Error(error) => return json!({ "error": error }),
+                        };
+                        self.orders.lock().unwrap().push(MockOrder {
+                            user,
+                            oid,
+                            coin: self.coin_name(order.asset),
+                            timestamp: now_timestamp_ms(),
+                            filled_sz,
+                            status: order_status,
+                            order: order.clone(),
+                        });
+                        if filled_sz > 0.0 {
+                            json!({ "filled": {
+                                "totalSz": filled_sz.to_string(),
+                                "avgPx": order.limit_px,
+                                "oid": oid,
+                            }})
+                        } else {
+                            json!({ "resting": { "oid": oid } })
+                        }
+                    })
+                    .collect();
+                return json!({ "type": "order", "data": { "statuses": statuses } });
+            }
+            Actions::TwapOrder(_) => {
+                let twap_id = self.next_oid.fetch_add(1, Ordering::Relaxed);
+                return json!({ "type": "twapOrder", "data": {
+                    "status": { "running": { "twapId": twap_id } },
+                }});
+            }
+            Actions::TwapCancel(_) => {
+                return json!({ "type": "twapCancel", "data": { "status": "success" } });
+            }
+            Actions::CreateSubAccount(_) => {
+                let sub_account =
+                    H160::from_low_u64_be(self.next_oid.fetch_add(1, Ordering::Relaxed));
+                return json!({ "type": "createSubAccount", "data": sub_account });
+            }
+            Actions::Cancel(bulk_cancel) => vec![json!("success"); bulk_cancel.cancels.len()],
+            Actions::CancelByCloid(bulk_cancel) => {
+                vec![json!("success"); bulk_cancel.cancels.len()]
+            }
+            Actions::BatchModify(bulk_modify) => {
+                vec![json!("success"); bulk_modify.modifies.len()]
+            }
+            _ => return json!({ "type": "default" }),
+        };
+        json!({ "type": "cancel", "data": { "statuses": statuses } })
+    }
+
+    /// Answers pings, subscriptions and posts from the client.
+    fn ws_response(&self, text: &str) -> Option<Value> {
+        let request: Value = serde_json::from_str(text).ok()?;
+        match request["method"].as_str()? {
+            "ping" => Some(json!({ "channel": "pong" })),
+            "subscribe" => {
+                self.subscriptions
+                    .lock()
+                    .unwrap()
+                    .push(request["subscription"].clone());
+                Some(json!({ "channel": "subscriptionResponse", "data": request }))
+            }
+            "unsubscribe" => {
+                let mut subscriptions = self.subscriptions.lock().unwrap();
+                if let Some(index) = subscriptions
+                    .iter()
+                    .position(|subscription| *subscription == request["subscription"])
+                {
+                    subscriptions.remove(index);
+                }
+                Some(json!({ "channel": "subscriptionResponse", "data": request }))
+            }
+            "post" => {
+                let id = request["id"].clone();
+                let payload = request["request"]["payload"].clone();
+                let response = match request["request"]["type"].as_str()? {
+                    "action" => {
+                        json!({ "type": "action", "payload": self.exchange_response(payload) })
+                    }
+                    "info" => match self.info_response(&payload) {
+                        Some(data) => json!({ "type": "info", "payload": {
+                            "type": payload["type"],
+                            "data": data,
+                        }}),
+                        None => json!({ "type": "error", "payload": "unsupported info request" }),
+                    },
+                    _ => json!({ "type": "error", "payload": "unsupported post type" }),
+                };
+                Some(json!({ "channel": "post", "data": { "id": id, "response": response } }))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ClientLimit, ClientOrder, ClientOrderRequest, ExchangeDataStatus, ExchangeResponseStatus,
+    };
+
+    #[tokio::test]
+    async fn test_order_status_by_oid_and_cloid() -> Result<()> {
+        let (mock, exchange_client) = MockHyperliquid::exchange_client().await?;
+        let user = exchange_client.signer.address();
+        let info = exchange_client.info();
+        mock.set_order_status(MockOrderStatus::Resting);
+
+        let cloid = uuid::Uuid::new_v4();
+        let response = exchange_client
+            .order(
+                ClientOrderRequest {
+                    asset: "ETH".to_string(),
+                    is_buy: true,
+                    reduce_only: false,
+                    limit_px: 3000.0,
+                    sz: 0.01,
+                    cloid: Some(cloid),
+                    order_type: ClientOrder::Limit(ClientLimit {
+                        tif: "Gtc".to_string(),
+                    }),
+                },
+                None,
+            )
+            .await?;
+        let ExchangeResponseStatus::Ok(response) = response else {
+            panic!("order rejected: {response:?}");
+        };
+        let statuses = response.data.map(|data| data.statuses).unwrap_or_default();
+        let Some(ExchangeDataStatus::Resting(resting)) = statuses.first() else {
+            panic!("order not resting");
+        };
+
+        let by_oid = info.query_order_by_oid(user, resting.oid).await?;
+        let order = by_oid.order.expect("order by oid");
+        assert_eq!(
+            (order.status.as_str(), order.order.coin.as_str()),
+            ("open", "ETH")
+        );
+        assert_eq!(
+            (order.order.side.as_str(), order.order.tif.as_str()),
+            ("B", "Gtc")
+        );
+        let by_cloid = info.query_order_by_cloid(user, cloid).await?;
+        assert_eq!(
+            by_cloid.order.map(|order| order.order.oid),
+            Some(resting.oid)
+        );
+
+        // orders of other users and unknown oids are not found
+        let other = info
+            .query_order_by_oid(H160::from_low_u64_be(1), resting.oid)
+            .await?;
+        assert_eq!(
+            (other.status.as_str(), other.order.is_none()),
+            ("unknownOid", true)
+        );
+        let unknown = info.query_order_by_oid(user, resting.oid + 100).await?;
+        assert_eq!(unknown.status, "unknownOid");
+        Ok(())
+    }
+}
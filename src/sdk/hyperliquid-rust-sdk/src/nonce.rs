@@ -0,0 +1,231 @@
+use crate::{helpers::now_timestamp_ms, prelude::*, Error};
+use ethers::types::H160;
+use lazy_static::lazy_static;
+use log::info;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Nonces for `/exchange` requests. The exchange keeps the highest nonces used by each signer
+/// and rejects a nonce that was already used or is far from the current time.
+pub trait NonceProvider: Debug + Send + Sync {
+    fn next_nonce(&self, signer: H160) -> Result<u64>;
+}
+
+lazy_static! {
+    static ref PROCESS_NONCES: Arc<AtomicNonces> = Arc::new(AtomicNonces::new());
+}
+
+/// The process-wide [`AtomicNonces`] used by clients without their own provider.
+pub fn default_nonce_provider() -> Arc<dyn NonceProvider> {
+    PROCESS_NONCES.clone()
+}
+
+/// One counter for all signers, seeded from the clock. Unique within a process only.
+#[derive(Debug)]
+pub struct AtomicNonces {
+    next: AtomicU64,
+}
+
+impl AtomicNonces {
+    pub fn new() -> AtomicNonces {
+        AtomicNonces {
+            next: AtomicU64::new(now_timestamp_ms()),
+        }
+    }
+}
+
+impl Default for AtomicNonces {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NonceProvider for AtomicNonces {
+    fn next_nonce(&self, _signer: H160) -> Result<u64> {
+        let nonce = self.next.fetch_add(1, Ordering::Relaxed);
+        let now_ms = now_timestamp_ms();
+        if nonce > now_ms + 1000 {
+            info!("nonce progressed too far ahead {nonce} {now_ms}");
+        }
+        // more than 300 seconds behind
+        if nonce + 300000 < now_ms {
+            self.next.fetch_max(now_ms, Ordering::Relaxed);
+        }
+        Ok(nonce)
+    }
+}
+
+// Next nonce after `last`: the current time, unless that was already handed out
+fn advance(last: Option<u64>) -> u64 {
+    let now_ms = now_timestamp_ms();
+    last.map_or(now_ms, |last| now_ms.max(last + 1))
+}
+
+/// A counter per signer that stays at the current time, so signers sharing a process don't
+/// push each other's nonces ahead of the clock.
+#[derive(Debug, Default)]
+pub struct PerSignerNonces {
+    last: Mutex<HashMap<H160, u64>>,
+}
+
+impl PerSignerNonces {
+    pub fn new() -> PerSignerNonces {
+        Self::default()
+    }
+}
+
+impl NonceProvider for PerSignerNonces {
+    fn next_nonce(&self, signer: H160) -> Result<u64> {
+        let mut last = self.last.lock().unwrap();
+        let nonce = advance(last.get(&signer).copied());
+        last.insert(signer, nonce);
+        Ok(nonce)
+    }
+}
+
+/// Per-signer counters persisted in a JSON file under an exclusive lock, so restarts and
+/// several processes signing for the same address never reuse a nonce.
+#[derive(Debug)]
+pub struct FileNonces {
+    path: PathBuf,
+    // serializes threads of this process, the file lock serializes processes
+    lock: Mutex<()>,
+}
+
+impl FileNonces {
+    pub fn new(path: impl Into<PathBuf>) -> FileNonces {
+        FileNonces {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn next_locked(&self, file: &mut File, signer: H160) -> Result<u64> {
+        let mut data = String::new();
+        file.read_to_string(&mut data).map_err(nonce_file_error)?;
+        let mut last: HashMap<H160, u64> = if data.trim().is_empty() {
+            HashMap::new()
+        } else {
+            serde_json::from_str(&data).map_err(|e| Error::JsonParse(e.to_string()))?
+        };
+
+        let nonce = advance(last.get(&signer).copied());
+        last.insert(signer, nonce);
+        let data = serde_json::to_string(&last).map_err(|e| Error::JsonParse(e.to_string()))?;
+        file.seek(SeekFrom::Start(0)).map_err(nonce_file_error)?;
+        file.set_len(0).map_err(nonce_file_error)?;
+        file.write_all(data.as_bytes()).map_err(nonce_file_error)?;
+        file.sync_data().map_err(nonce_file_error)?;
+        Ok(nonce)
+    }
+}
+
+impl NonceProvider for FileNonces {
+    fn next_nonce(&self, signer: H160) -> Result<u64> {
+        let _guard = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)
+            .map_err(nonce_file_error)?;
+        file.lock().map_err(nonce_file_error)?;
+        let nonce = self.next_locked(&mut file, signer);
+        let _ = file.unlock();
+        nonce
+    }
+}
+
+fn nonce_file_error(e: std::io::Error) -> Error {
+    Error::Nonce(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExchangeResponseStatus, MockHyperliquid};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_file_nonces_unique_across_instances() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("nonces-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let signer = H160::from_low_u64_be(1);
+        let other = H160::from_low_u64_be(2);
+
+        // two providers on one file stand in for two processes
+        let providers = [FileNonces::new(&path), FileNonces::new(&path)];
+        let mut seen = HashSet::new();
+        for i in 0..50 {
+            let nonce = providers[i % 2].next_nonce(signer)?;
+            assert!(seen.insert(nonce));
+        }
+        let other_nonce = providers[0].next_nonce(other)?;
+        assert!(other_nonce <= now_timestamp_ms());
+
+        // a restart continues after the persisted nonce
+        let restarted = FileNonces::new(&path);
+        assert!(restarted.next_nonce(signer)? > *seen.iter().max().unwrap());
+
+        std::fs::remove_file(path).map_err(nonce_file_error)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_per_signer_nonces() -> Result<()> {
+        let nonces = PerSignerNonces::new();
+        let a = H160::from_low_u64_be(1);
+        let b = H160::from_low_u64_be(2);
+        let first = nonces.next_nonce(a)?;
+        for _ in 0..10 {
+            nonces.next_nonce(a)?;
+        }
+        assert!(nonces.next_nonce(a)? >= first + 11);
+        // b is unaffected by a's burst
+        assert!(nonces.next_nonce(b)? <= now_timestamp_ms());
+        Ok(())
+    }
+
+    /// Hands out preset nonces, standing in for nonces another process already used
+    #[derive(Debug)]
+    struct ScriptedNonces(Mutex<Vec<u64>>);
+
+    impl NonceProvider for ScriptedNonces {
+        fn next_nonce(&self, _signer: H160) -> Result<u64> {
+            Ok(self.0.lock().unwrap().remove(0))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nonce_rejection_is_retried() -> Result<()> {
+        let (mock, mut exchange_client) = MockHyperliquid::exchange_client().await?;
+        let nonce = now_timestamp_ms();
+        exchange_client.set_nonce_provider(Arc::new(ScriptedNonces(Mutex::new(vec![
+            nonce,
+            nonce,
+            nonce + 1,
+        ]))));
+
+        for _ in 0..2 {
+            let response = exchange_client
+                .update_leverage(5, "ETH", true, None)
+                .await?;
+            assert!(matches!(response, ExchangeResponseStatus::Ok(_)));
+        }
+
+        // the second request's nonce is rejected and it is resent with the next one
+        let nonces: Vec<u64> = mock.actions().iter().map(|action| action.nonce).collect();
+        assert_eq!(nonces, vec![nonce, nonce + 1]);
+        Ok(())
+    }
+}
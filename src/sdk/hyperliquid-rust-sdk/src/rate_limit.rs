@@ -0,0 +1,292 @@
+use crate::{prelude::*, Error, UserRateLimit};
+use ethers::types::H160;
+use lazy_static::lazy_static;
+use log::warn;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Weight budget the exchange allows per IP.
+pub const IP_WEIGHT_PER_MINUTE: u32 = 1200;
+
+lazy_static! {
+    static ref PROCESS_RATE_LIMITER: Arc<RateLimiter> = Arc::new(RateLimiter::default());
+}
+
+/// The process-wide [`RateLimiter`] shared by clients without their own, since the IP limit
+/// covers every request the process makes.
+pub fn default_rate_limiter() -> Arc<RateLimiter> {
+    PROCESS_RATE_LIMITER.clone()
+}
+
+/// What [`RateLimiter::acquire`] does when the weight budget is exhausted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhenExhausted {
+    /// Waits for the budget to refill, requests are served in order
+    Queue,
+    /// Fails with [`Error::RateLimited`]
+    Reject,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    // Address actions left per account as of userRateLimit, unknown until synced
+    address_actions: HashMap<H160, u64>,
+}
+
+/// Token bucket over the exchange's request weights: the IP weight refills continuously, the
+/// action budget of each account only grows with traded volume and is synced from
+/// `userRateLimit`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    when_exhausted: WhenExhausted,
+    bucket: Mutex<Bucket>,
+    // held while waiting so queued requests go in arrival order
+    queue: tokio::sync::Mutex<()>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(
+            IP_WEIGHT_PER_MINUTE,
+            Duration::from_secs(60),
+            WhenExhausted::Queue,
+        )
+    }
+}
+
+impl RateLimiter {
+    /// Allows `weight` per `period`, starting full.
+    pub fn new(weight: u32, period: Duration, when_exhausted: WhenExhausted) -> RateLimiter {
+        RateLimiter {
+            capacity: weight as f64,
+            refill_per_sec: weight as f64 / period.as_secs_f64(),
+            when_exhausted,
+            bucket: Mutex::new(Bucket {
+                tokens: weight as f64,
+                refilled_at: Instant::now(),
+                address_actions: HashMap::new(),
+            }),
+            queue: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// IP weight currently available.
+    pub fn remaining(&self) -> u32 {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+        bucket.tokens as u32
+    }
+
+    /// Actions `account` has left as of its last [`RateLimiter::sync`], minus those sent since.
+    pub fn address_remaining(&self, account: H160) -> Option<u64> {
+        self.bucket
+            .lock()
+            .unwrap()
+            .address_actions
+            .get(&account)
+            .copied()
+    }
+
+    /// Resets `account`'s action budget from its `userRateLimit` response.
+    pub fn sync(&self, account: H160, user_rate_limit: &UserRateLimit) {
+        let remaining = user_rate_limit
+            .n_requests_cap
+            .saturating_sub(user_rate_limit.n_requests_used);
+        self.bucket
+            .lock()
+            .unwrap()
+            .address_actions
+            .insert(account, remaining);
+    }
+
+    /// Takes the weight of a request to `url_path` with body `data`, see [`request_weight`].
+    /// Its address actions are charged to `account`, if its budget has been synced.
+    pub async fn acquire(&self, url_path: &str, data: &str, account: Option<H160>) -> Result<()> {
+        let (weight, actions) = request_weight(url_path, data);
+        // a request heavier than the whole bucket would otherwise wait forever
+        let weight = (weight as f64).min(self.capacity);
+        let _queued = self.queue.lock().await;
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                self.refill(&mut bucket);
+                let left = account.and_then(|account| bucket.address_actions.get(&account));
+                if left.is_some_and(|left| *left < actions) {
+                    return Err(Error::RateLimited(format!(
+                        "address action budget exhausted, {actions} needed"
+                    )));
+                }
+                if bucket.tokens >= weight {
+                    bucket.tokens -= weight;
+                    if let Some(left) =
+                        account.and_then(|account| bucket.address_actions.get_mut(&account))
+                    {
+                        *left -= actions;
+                    }
+                    return Ok(());
+                }
+                if self.when_exhausted == WhenExhausted::Reject {
+                    return Err(Error::RateLimited(format!(
+                        "weight {weight} needed, {:.0} left",
+                        bucket.tokens
+                    )));
+                }
+                Duration::from_secs_f64((weight - bucket.tokens) / self.refill_per_sec)
+            };
+            warn!("Rate limit weight exhausted, waiting {wait:?} for {url_path}");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.refilled_at = now;
+    }
+}
+
+/// IP weight of a request and the address actions it uses. `/exchange` weighs 1 plus 1 per 40
+/// orders or cancels in the batch, each of which is an address action. `/info` weighs 2 for
+/// the cheap queries, 60 for `userRole` and 20 for the rest.
+pub fn request_weight(url_path: &str, data: &str) -> (u32, u64) {
+    let body: Value = serde_json::from_str(data).unwrap_or_default();
+    if url_path == "/exchange" {
+        let action = &body["action"];
+        let batch_length = ["orders", "cancels", "modifies"]
+            .iter()
+            .find_map(|key| action[key].as_array())
+            .map_or(1, |batch| batch.len().max(1));
+        return (1 + batch_length as u32 / 40, batch_length as u64);
+    }
+    let weight = match body["type"].as_str() {
+        Some(
+            "l2Book"
+            | "allMids"
+            | "clearinghouseState"
+            | "orderStatus"
+            | "spotClearinghouseState"
+            | "exchangeStatus",
+        ) => 2,
+        Some("userRole") => 60,
+        _ => 20,
+    };
+    (weight, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BaseUrl, ClientLimit, ClientOrder, ClientOrderRequest, MockHyperliquid, Signer, Transport,
+        WsPostClient,
+    };
+
+    #[test]
+    fn test_request_weight() {
+        assert_eq!(request_weight("/info", r#"{"type":"allMids"}"#), (2, 0));
+        assert_eq!(request_weight("/info", r#"{"type":"meta"}"#), (20, 0));
+        let orders = vec![Value::Null; 85];
+        let exchange = serde_json::json!({ "action": { "type": "order", "orders": orders } });
+        assert_eq!(request_weight("/exchange", &exchange.to_string()), (3, 85));
+        let leverage = r#"{"action":{"type":"updateLeverage"}}"#;
+        assert_eq!(request_weight("/exchange", leverage), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_reject_and_sync() -> Result<()> {
+        let limiter = RateLimiter::new(40, Duration::from_secs(3600), WhenExhausted::Reject);
+        limiter.acquire("/info", r#"{"type":"meta"}"#, None).await?;
+        limiter.acquire("/info", r#"{"type":"meta"}"#, None).await?;
+        assert_eq!(limiter.remaining(), 0);
+        assert!(matches!(
+            limiter
+                .acquire("/info", r#"{"type":"allMids"}"#, None)
+                .await,
+            Err(Error::RateLimited(_))
+        ));
+
+        let limiter = RateLimiter::new(100, Duration::from_secs(60), WhenExhausted::Reject);
+        let (account, other) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        limiter.sync(
+            account,
+            &UserRateLimit {
+                cum_vlm: "10.0".to_string(),
+                n_requests_used: 9,
+                n_requests_cap: 10,
+            },
+        );
+        let order = r#"{"action":{"type":"order","orders":[{}]}}"#;
+        limiter.acquire("/exchange", order, Some(account)).await?;
+        assert_eq!(limiter.address_remaining(account), Some(0));
+        assert!(limiter
+            .acquire("/exchange", order, Some(account))
+            .await
+            .is_err());
+        // each account has a budget of its own, queries don't use any
+        limiter.acquire("/exchange", order, Some(other)).await?;
+        assert_eq!(limiter.address_remaining(other), None);
+        limiter
+            .acquire("/info", r#"{"type":"allMids"}"#, None)
+            .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_queues_until_refilled() -> Result<()> {
+        let limiter = RateLimiter::new(20, Duration::from_millis(200), WhenExhausted::Queue);
+        let start = Instant::now();
+        limiter.acquire("/info", r#"{"type":"meta"}"#, None).await?;
+        limiter.acquire("/info", r#"{"type":"meta"}"#, None).await?;
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_sync_and_order_budget() -> Result<()> {
+        let (_mock, mut exchange_client) = MockHyperliquid::exchange_client().await?;
+        let account = exchange_client.signer.address();
+        let info = exchange_client.info().clone();
+
+        let limit = info.sync_rate_limit(account).await?;
+        assert_eq!(limit.n_requests_cap, 10_000);
+        let order = ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: 3000.0,
+            sz: 0.01,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit {
+                tif: "Gtc".to_string(),
+            }),
+        };
+        exchange_client.order(order.clone(), None).await?;
+        // the limiter is shared with ExchangeClient, so the order used one request of the address
+        assert_eq!(
+            info.http_client.rate_limiter.address_remaining(account),
+            Some(9_999)
+        );
+        assert!(info.http_client.rate_limiter.remaining() < IP_WEIGHT_PER_MINUTE);
+
+        // orders posted over the websocket use the same budget
+        let base_url = BaseUrl::custom(exchange_client.http_client.base_url.clone(), false);
+        let ws_post_client =
+            WsPostClient::connect(base_url, WsPostClient::DEFAULT_TIMEOUT, None).await?;
+        exchange_client.set_transport(Transport::Ws(Arc::new(ws_post_client)));
+        exchange_client.order(order, None).await?;
+        assert_eq!(
+            info.http_client.rate_limiter.address_remaining(account),
+            Some(9_998)
+        );
+        Ok(())
+    }
+}
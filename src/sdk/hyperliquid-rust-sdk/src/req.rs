@@ -1,6 +1,9 @@
-use crate::{prelude::*, BaseUrl, Error};
+use crate::{default_rate_limiter, prelude::*, Error, RateLimiter};
+use ethers::types::H160;
+use log::warn;
 use reqwest::{Client, Response};
 use serde::Deserialize;
+use std::{sync::Arc, time::Duration};
 
 #[derive(Deserialize, Debug)]
 struct ErrorData {
@@ -9,10 +12,79 @@ struct ErrorData {
     msg: String,
 }
 
-#[derive(Debug)]
+/// Backoff for requests that failed to connect, timed out, were rate limited (429) or hit a
+/// server error. Retries resend the same body, so a signed action keeps its nonce and signature
+/// and executes at most once.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Retries after the first attempt, 0 disables retrying.
+    pub max_retries: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            max_retries: 3,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry);
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+/// Request timeouts by endpoint. Actions get a shorter one than queries, a late order is
+/// usually worse than a retried one.
+#[derive(Debug, Clone)]
+pub struct Timeouts {
+    pub info: Duration,
+    pub exchange: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            info: Duration::from_secs(10),
+            exchange: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Timeouts {
+    fn for_path(&self, url_path: &str) -> Duration {
+        match url_path {
+            "/exchange" => self.exchange,
+            _ => self.info,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct HttpClient {
     pub client: Client,
     pub base_url: String,
+    /// Selects the mainnet or testnet signing source.
+    pub is_mainnet: bool,
+    pub retry_policy: RetryPolicy,
+    pub timeouts: Timeouts,
+    /// Request weight budget, shared by default with every client of the process.
+    pub rate_limiter: Arc<RateLimiter>,
 }
 
 async fn parse_response(response: Response) -> Result<String> {
@@ -50,25 +122,79 @@ async fn parse_response(response: Response) -> Result<String> {
     })
 }
 
+fn is_retryable(error: &Error) -> bool {
+    match error {
+        Error::Connection(_) | Error::ServerRequest { .. } => true,
+        Error::ClientRequest { status_code, .. } => *status_code == 429,
+        _ => false,
+    }
+}
+
 impl HttpClient {
+    pub(crate) fn new(client: Client, base_url: String, is_mainnet: bool) -> HttpClient {
+        HttpClient {
+            client,
+            base_url,
+            is_mainnet,
+            retry_policy: RetryPolicy::default(),
+            timeouts: Timeouts::default(),
+            rate_limiter: default_rate_limiter(),
+        }
+    }
+
     pub async fn post(&self, url_path: &'static str, data: String) -> Result<String> {
+        Ok(self.post_counting(url_path, data, None).await?.0)
+    }
+
+    /// Like [`HttpClient::post`], also returning how many times the body was sent. Address
+    /// actions are charged to `account`'s budget in the rate limiter.
+    pub(crate) async fn post_counting(
+        &self,
+        url_path: &'static str,
+        data: String,
+        account: Option<H160>,
+    ) -> Result<(String, u32)> {
+        let mut retry = 0;
+        loop {
+            match self.post_once(url_path, data.clone(), account).await {
+                Err(e) if retry < self.retry_policy.max_retries && is_retryable(&e) => {
+                    let delay = self.retry_policy.delay(retry);
+                    retry += 1;
+                    warn!("{url_path} request failed, retry {retry} in {delay:?}: {e}");
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result.map(|text| (text, retry + 1)),
+            }
+        }
+    }
+
+    async fn post_once(
+        &self,
+        url_path: &'static str,
+        data: String,
+        account: Option<H160>,
+    ) -> Result<String> {
+        self.rate_limiter.acquire(url_path, &data, account).await?;
         let full_url = format!("{}{url_path}", self.base_url);
         let request = self
             .client
             .post(full_url)
             .header("Content-Type", "application/json")
+            .timeout(self.timeouts.for_path(url_path))
             .body(data)
             .build()
             .map_err(|e| Error::GenericRequest(e.to_string()))?;
-        let result = self
-            .client
-            .execute(request)
-            .await
-            .map_err(|e| Error::GenericRequest(e.to_string()))?;
+        let result = self.client.execute(request).await.map_err(|e| {
+            if e.is_connect() || e.is_timeout() {
+                Error::Connection(e.to_string())
+            } else {
+                Error::GenericRequest(e.to_string())
+            }
+        })?;
         parse_response(result).await
     }
 
     pub fn is_mainnet(&self) -> bool {
-        self.base_url == BaseUrl::Mainnet.get_url()
+        self.is_mainnet
     }
 }
@@ -1,35 +1,53 @@
 use ethers::{
     core::k256::{elliptic_curve::FieldBytes, Secp256k1},
     signers::LocalWallet,
-    types::{transaction::eip712::Eip712, Signature, H256, U256},
+    types::{transaction::eip712::Eip712, Signature, H160, H256, U256},
 };
 
-use crate::{prelude::*, proxy_digest::Sha256Proxy, signature::agent::l1, Error};
+use crate::{prelude::*, proxy_digest::Sha256Proxy, signature::agent::l1, Error, Signer};
 
-pub(crate) fn sign_l1_action(
-    wallet: &LocalWallet,
+pub async fn sign_l1_action(
+    signer: &dyn Signer,
     connection_id: H256,
     is_mainnet: bool,
 ) -> Result<Signature> {
+    signer
+        .sign_hash(l1_action_hash(connection_id, is_mainnet)?)
+        .await
+}
+
+/// EIP-712 digest of the phantom agent that L1 actions are signed as.
+pub(crate) fn l1_action_hash(connection_id: H256, is_mainnet: bool) -> Result<H256> {
     let source = if is_mainnet { "a" } else { "b" }.to_string();
-    sign_typed_data(
-        &l1::Agent {
-            source,
-            connection_id,
-        },
-        wallet,
-    )
+    typed_data_hash(&l1::Agent {
+        source,
+        connection_id,
+    })
+}
+
+/// Recovers the address that signed `connection_id` with [`sign_l1_action`]'s scheme.
+pub fn recover_l1_action_signer(
+    connection_id: H256,
+    is_mainnet: bool,
+    signature: &Signature,
+) -> Result<H160> {
+    signature
+        .recover(l1_action_hash(connection_id, is_mainnet)?)
+        .map_err(|e| Error::SignatureFailure(e.to_string()))
+}
+
+pub async fn sign_typed_data<T: Eip712>(payload: &T, signer: &dyn Signer) -> Result<Signature> {
+    signer.sign_hash(typed_data_hash(payload)?).await
 }
 
-pub(crate) fn sign_typed_data<T: Eip712>(payload: &T, wallet: &LocalWallet) -> Result<Signature> {
+pub(crate) fn typed_data_hash<T: Eip712>(payload: &T) -> Result<H256> {
     let encoded = payload
         .encode_eip712()
         .map_err(|e| Error::Eip712(e.to_string()))?;
-
-    sign_hash(H256::from(encoded), wallet)
+    Ok(H256::from(encoded))
 }
 
-fn sign_hash(hash: H256, wallet: &LocalWallet) -> Result<Signature> {
+pub(crate) fn sign_hash(hash: H256, wallet: &LocalWallet) -> Result<Signature> {
     let (sig, rec_id) = wallet
         .signer()
         .sign_digest_recoverable(Sha256Proxy::from(hash))
@@ -49,6 +67,7 @@ fn sign_hash(hash: H256, wallet: &LocalWallet) -> Result<Signature> {
 mod tests {
     use super::*;
     use crate::{UsdSend, Withdraw3};
+    use ethers::signers::Signer;
     use std::str::FromStr;
 
     fn get_wallet() -> Result<LocalWallet> {
@@ -58,8 +77,8 @@ mod tests {
             .map_err(|e| Error::Wallet(e.to_string()))
     }
 
-    #[test]
-    fn test_sign_l1_action() -> Result<()> {
+    #[tokio::test]
+    async fn test_sign_l1_action() -> Result<()> {
         let wallet = get_wallet()?;
         let connection_id =
             H256::from_str("0xde6c4037798a4434ca03cd05f00e3b803126221375cd1e7eaaaf041768be06eb")
@@ -67,19 +86,42 @@ mod tests {
 
         let expected_mainnet_sig = "fa8a41f6a3fa728206df80801a83bcbfbab08649cd34d9c0bfba7c7b2f99340f53a00226604567b98a1492803190d65a201d6805e5831b7044f17fd530aec7841c";
         assert_eq!(
-            sign_l1_action(&wallet, connection_id, true)?.to_string(),
+            sign_l1_action(&wallet, connection_id, true)
+                .await?
+                .to_string(),
             expected_mainnet_sig
         );
         let expected_testnet_sig = "1713c0fc661b792a50e8ffdd59b637b1ed172d9a3aa4d801d9d88646710fb74b33959f4d075a7ccbec9f2374a6da21ffa4448d58d0413a0d335775f680a881431c";
         assert_eq!(
-            sign_l1_action(&wallet, connection_id, false)?.to_string(),
+            sign_l1_action(&wallet, connection_id, false)
+                .await?
+                .to_string(),
             expected_testnet_sig
         );
         Ok(())
     }
 
-    #[test]
-    fn test_sign_usd_transfer_action() -> Result<()> {
+    #[tokio::test]
+    async fn test_recover_l1_action_signer() -> Result<()> {
+        let wallet = get_wallet()?;
+        let connection_id =
+            H256::from_str("0xde6c4037798a4434ca03cd05f00e3b803126221375cd1e7eaaaf041768be06eb")
+                .map_err(|e| Error::GenericParse(e.to_string()))?;
+
+        let signature = sign_l1_action(&wallet, connection_id, false).await?;
+        assert_eq!(
+            recover_l1_action_signer(connection_id, false, &signature)?,
+            wallet.address()
+        );
+        assert_ne!(
+            recover_l1_action_signer(connection_id, true, &signature)?,
+            wallet.address()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sign_usd_transfer_action() -> Result<()> {
         let wallet = get_wallet()?;
 
         let usd_send = UsdSend {
@@ -92,14 +134,14 @@ mod tests {
 
         let expected_sig = "214d507bbdaebba52fa60928f904a8b2df73673e3baba6133d66fe846c7ef70451e82453a6d8db124e7ed6e60fa00d4b7c46e4d96cb2bd61fd81b6e8953cc9d21b";
         assert_eq!(
-            sign_typed_data(&usd_send, &wallet)?.to_string(),
+            sign_typed_data(&usd_send, &wallet).await?.to_string(),
             expected_sig
         );
         Ok(())
     }
 
-    #[test]
-    fn test_sign_withdraw_from_bridge_action() -> Result<()> {
+    #[tokio::test]
+    async fn test_sign_withdraw_from_bridge_action() -> Result<()> {
         let wallet = get_wallet()?;
 
         let usd_send = Withdraw3 {
@@ -112,7 +154,7 @@ mod tests {
 
         let expected_sig = "b3172e33d2262dac2b4cb135ce3c167fda55dafa6c62213564ab728b9f9ba76b769a938e9f6d603dae7154c83bf5a4c3ebab81779dc2db25463a3ed663c82ae41c";
         assert_eq!(
-            sign_typed_data(&usd_send, &wallet)?.to_string(),
+            sign_typed_data(&usd_send, &wallet).await?.to_string(),
             expected_sig
         );
         Ok(())
@@ -1,4 +1,5 @@
 pub(crate) mod agent;
 mod create_signature;
 
-pub(crate) use create_signature::{sign_l1_action, sign_typed_data};
+pub(crate) use create_signature::{l1_action_hash, sign_hash, typed_data_hash};
+pub use create_signature::{recover_l1_action_signer, sign_l1_action, sign_typed_data};
@@ -0,0 +1,83 @@
+use crate::{prelude::*, Error, Signer};
+use ethers::{
+    signers::LocalWallet,
+    types::{Signature, H160, H256},
+};
+use futures_util::future::BoxFuture;
+use std::{fs, path::Path};
+
+/// A key decrypted from an Ethereum JSON keystore file (scrypt or pbkdf2).
+#[derive(Debug, Clone)]
+pub struct KeystoreSigner {
+    wallet: LocalWallet,
+}
+
+impl KeystoreSigner {
+    pub fn open(path: impl AsRef<Path>, password: impl AsRef<[u8]>) -> Result<KeystoreSigner> {
+        let wallet = LocalWallet::decrypt_keystore(path, password)
+            .map_err(|e| Error::Wallet(e.to_string()))?;
+        Ok(KeystoreSigner { wallet })
+    }
+
+    /// Like [`KeystoreSigner::open`], reading the password from `password_file`. A trailing
+    /// newline is not part of the password.
+    pub fn open_with_password_file(
+        path: impl AsRef<Path>,
+        password_file: impl AsRef<Path>,
+    ) -> Result<KeystoreSigner> {
+        let password =
+            fs::read_to_string(password_file).map_err(|e| Error::Wallet(e.to_string()))?;
+        Self::open(path, password.trim_end_matches(['\r', '\n']))
+    }
+
+    /// Encrypts `private_key` (hex) with scrypt into a new keystore file `dir/name`.
+    pub fn import(
+        dir: impl AsRef<Path>,
+        name: &str,
+        private_key: &str,
+        password: impl AsRef<[u8]>,
+    ) -> Result<KeystoreSigner> {
+        let key = hex::decode(private_key.trim_start_matches("0x"))
+            .map_err(|e| Error::PrivateKeyParse(e.to_string()))?;
+        let (wallet, _) =
+            LocalWallet::encrypt_keystore(dir, &mut rand::thread_rng(), key, password, Some(name))
+                .map_err(|e| Error::Wallet(e.to_string()))?;
+        Ok(KeystoreSigner { wallet })
+    }
+}
+
+impl Signer for KeystoreSigner {
+    fn address(&self) -> H160 {
+        self.wallet.address()
+    }
+
+    fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<Signature>> {
+        Signer::sign_hash(&self.wallet, hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystore_import_and_open() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("keystore-{}", std::process::id()));
+        fs::create_dir_all(&dir).map_err(|e| Error::Wallet(e.to_string()))?;
+        let imported = KeystoreSigner::import(
+            &dir,
+            "master",
+            "0xe908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e",
+            "password",
+        )?;
+
+        let password_file = dir.join("password");
+        fs::write(&password_file, "password\n").map_err(|e| Error::Wallet(e.to_string()))?;
+        let opened = KeystoreSigner::open_with_password_file(dir.join("master"), &password_file)?;
+        assert_eq!(opened.address(), imported.address());
+        assert!(KeystoreSigner::open(dir.join("master"), "wrong").is_err());
+
+        fs::remove_dir_all(dir).map_err(|e| Error::Wallet(e.to_string()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+mod keystore;
+mod remote;
+
+pub use keystore::KeystoreSigner;
+pub use remote::{serve_unix_signer, RemoteSigner, SignerRequest, SignerResponse};
+
+use crate::{prelude::*, signature::sign_hash};
+use ethers::{
+    signers::LocalWallet,
+    types::{Signature, H160, H256},
+};
+use futures_util::future::{self, BoxFuture};
+use std::{fmt::Debug, sync::Arc};
+
+/// Signs exchange requests for one address. [`ExchangeClient`](crate::ExchangeClient) only
+/// hands it EIP-712 digests, so the key itself can live in another process.
+pub trait Signer: Debug + Send + Sync {
+    fn address(&self) -> H160;
+
+    /// Signs a 32 byte EIP-712 digest, returning a signature with `v` of 27 or 28.
+    fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<Signature>>;
+}
+
+/// An in-memory key.
+impl Signer for LocalWallet {
+    fn address(&self) -> H160 {
+        ethers::signers::Signer::address(self)
+    }
+
+    fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<Signature>> {
+        Box::pin(future::ready(sign_hash(hash, self)))
+    }
+}
+
+impl<S: Signer + ?Sized> Signer for Arc<S> {
+    fn address(&self) -> H160 {
+        (**self).address()
+    }
+
+    fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<Signature>> {
+        (**self).sign_hash(hash)
+    }
+}
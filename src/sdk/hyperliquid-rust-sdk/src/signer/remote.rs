@@ -0,0 +1,220 @@
+use crate::{prelude::*, Error, Signer};
+use ethers::types::{Signature, H160, H256};
+use futures_util::future::BoxFuture;
+use log::warn;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::{fmt, path::PathBuf, sync::Arc};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+
+/// Request to a remote signer: a JSON body over HTTP, one JSON line over a Unix socket.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "method", rename_all = "camelCase")]
+pub enum SignerRequest {
+    Address,
+    SignHash { hash: H256 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum SignerResponse {
+    Address(H160),
+    Signature(Signature),
+    Error(String),
+}
+
+impl SignerRequest {
+    /// Answers the request with `signer`, for implementing the serving side.
+    pub async fn handle(self, signer: &dyn Signer) -> SignerResponse {
+        match self {
+            SignerRequest::Address => SignerResponse::Address(signer.address()),
+            SignerRequest::SignHash { hash } => match signer.sign_hash(hash).await {
+                Ok(signature) => SignerResponse::Signature(signature),
+                Err(e) => SignerResponse::Error(e.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Endpoint {
+    Http {
+        client: Client,
+        url: String,
+        token: String,
+    },
+    Unix(PathBuf),
+}
+
+impl fmt::Debug for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Http { url, .. } => f.debug_struct("Http").field("url", url).finish(),
+            Endpoint::Unix(path) => f.debug_tuple("Unix").field(path).finish(),
+        }
+    }
+}
+
+/// A key held by another process, reached over HTTP or a Unix socket.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    endpoint: Endpoint,
+    address: H160,
+}
+
+impl RemoteSigner {
+    /// Connects to a signer taking [`SignerRequest`] POSTs at `url`, authenticating with
+    /// `Authorization: Bearer <token>`.
+    pub async fn http(url: impl Into<String>, token: impl Into<String>) -> Result<RemoteSigner> {
+        Self::connect(Endpoint::Http {
+            client: Client::new(),
+            url: url.into(),
+            token: token.into(),
+        })
+        .await
+    }
+
+    /// Connects to a signer served by [`serve_unix_signer`].
+    pub async fn unix(path: impl Into<PathBuf>) -> Result<RemoteSigner> {
+        Self::connect(Endpoint::Unix(path.into())).await
+    }
+
+    async fn connect(endpoint: Endpoint) -> Result<RemoteSigner> {
+        match request(&endpoint, &SignerRequest::Address).await? {
+            SignerResponse::Address(address) => Ok(RemoteSigner { endpoint, address }),
+            response => Err(unexpected(response)),
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn address(&self) -> H160 {
+        self.address
+    }
+
+    fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<Signature>> {
+        Box::pin(async move {
+            let signature = match request(&self.endpoint, &SignerRequest::SignHash { hash }).await?
+            {
+                SignerResponse::Signature(signature) => signature,
+                response => return Err(unexpected(response)),
+            };
+            // a signature from any other key would only be rejected later by the exchange
+            let signer = signature
+                .recover(hash)
+                .map_err(|e| Error::SignatureFailure(e.to_string()))?;
+            if signer != self.address {
+                return Err(Error::RemoteSigner(format!(
+                    "signed by {signer:?}, expected {:?}",
+                    self.address
+                )));
+            }
+            Ok(signature)
+        })
+    }
+}
+
+fn unexpected(response: SignerResponse) -> Error {
+    match response {
+        SignerResponse::Error(e) => Error::RemoteSigner(e),
+        response => Error::RemoteSigner(format!("unexpected response {response:?}")),
+    }
+}
+
+async fn request(endpoint: &Endpoint, request: &SignerRequest) -> Result<SignerResponse> {
+    match endpoint {
+        Endpoint::Http { client, url, token } => client
+            .post(url)
+            .bearer_auth(token)
+            .json(request)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::RemoteSigner(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| Error::RemoteSigner(e.to_string())),
+        Endpoint::Unix(path) => {
+            let stream = UnixStream::connect(path)
+                .await
+                .map_err(|e| Error::RemoteSigner(e.to_string()))?;
+            let (reader, mut writer) = stream.into_split();
+            let mut line =
+                serde_json::to_string(request).map_err(|e| Error::JsonParse(e.to_string()))?;
+            line.push('\n');
+            writer
+                .write_all(line.as_bytes())
+                .await
+                .map_err(|e| Error::RemoteSigner(e.to_string()))?;
+
+            let mut response = String::new();
+            BufReader::new(reader)
+                .read_line(&mut response)
+                .await
+                .map_err(|e| Error::RemoteSigner(e.to_string()))?;
+            serde_json::from_str(&response).map_err(|e| Error::JsonParse(e.to_string()))
+        }
+    }
+}
+
+/// Serves `signer` on `listener` for [`RemoteSigner::unix`]. Any hash sent is signed, so the
+/// socket must only be accessible to the trading process.
+pub async fn serve_unix_signer(listener: UnixListener, signer: Arc<dyn Signer>) -> Result<()> {
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .map_err(|e| Error::RemoteSigner(e.to_string()))?;
+        let signer = signer.clone();
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let response = match serde_json::from_str::<SignerRequest>(&line) {
+                    Ok(request) => request.handle(&*signer).await,
+                    Err(e) => SignerResponse::Error(e.to_string()),
+                };
+                let Ok(mut response) = serde_json::to_string(&response) else {
+                    return;
+                };
+                response.push('\n');
+                if let Err(e) = writer.write_all(response.as_bytes()).await {
+                    warn!("Remote signer connection closed: {e}");
+                    return;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::sign_l1_action;
+    use ethers::signers::LocalWallet;
+
+    #[tokio::test]
+    async fn test_unix_remote_signer() -> Result<()> {
+        let wallet: LocalWallet =
+            "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+                .parse()
+                .map_err(|e: ethers::signers::WalletError| Error::Wallet(e.to_string()))?;
+        let path = std::env::temp_dir().join(format!("signer-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).map_err(|e| Error::Wallet(e.to_string()))?;
+        tokio::spawn(serve_unix_signer(listener, Arc::new(wallet.clone())));
+
+        let remote = RemoteSigner::unix(&path).await?;
+        assert_eq!(remote.address(), Signer::address(&wallet));
+        let connection_id = H256::from_low_u64_be(1);
+        assert_eq!(
+            sign_l1_action(&remote, connection_id, true).await?,
+            sign_l1_action(&wallet, connection_id, true).await?
+        );
+        std::fs::remove_file(path).map_err(|e| Error::Wallet(e.to_string()))?;
+        Ok(())
+    }
+}
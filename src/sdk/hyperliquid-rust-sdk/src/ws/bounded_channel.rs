@@ -0,0 +1,260 @@
+use crate::{prelude::*, ws::Message, Error};
+use futures_util::Stream;
+use std::{
+    collections::VecDeque,
+    mem,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::sync::{mpsc::UnboundedSender, Notify};
+
+/// What a bounded subscription does with a new message when its queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued message to make room.
+    DropOldest,
+    /// Drop the incoming message.
+    DropNewest,
+    /// Replace the newest queued data message of the same kind, for snapshot-like feeds such
+    /// as AllMids or L2Book where only the latest state matters. Connection state, pong and
+    /// error messages are never coalesced away.
+    CoalesceLatest,
+}
+
+#[derive(Debug)]
+struct Shared {
+    queue: Mutex<VecDeque<Message>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    notify: Notify,
+    dropped: AtomicU64,
+    sender_closed: AtomicBool,
+    receiver_closed: AtomicBool,
+}
+
+pub(crate) fn bounded(
+    capacity: usize,
+    policy: OverflowPolicy,
+) -> (BoundedSender, SubscriptionReceiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::with_capacity(capacity)),
+        capacity: capacity.max(1),
+        policy,
+        notify: Notify::new(),
+        dropped: AtomicU64::new(0),
+        sender_closed: AtomicBool::new(false),
+        receiver_closed: AtomicBool::new(false),
+    });
+    (
+        BoundedSender {
+            shared: shared.clone(),
+        },
+        SubscriptionReceiver { shared },
+    )
+}
+
+#[derive(Debug)]
+pub(crate) struct BoundedSender {
+    shared: Arc<Shared>,
+}
+
+impl BoundedSender {
+    /// Never waits: a full queue is resolved with the overflow policy.
+    fn send(&self, message: Message) -> Result<()> {
+        if self.shared.receiver_closed.load(Ordering::Relaxed) {
+            return Err(Error::WsSend("subscription receiver dropped".to_string()));
+        }
+        let mut queue = self.shared.queue.lock().unwrap();
+        if queue.len() >= self.shared.capacity {
+            self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+            match self.shared.policy {
+                OverflowPolicy::DropOldest => {
+                    queue.pop_front();
+                    queue.push_back(message);
+                }
+                OverflowPolicy::DropNewest => return Ok(()),
+                OverflowPolicy::CoalesceLatest => coalesce(&mut queue, message),
+            }
+        } else {
+            queue.push_back(message);
+        }
+        drop(queue);
+        self.shared.notify.notify_one();
+        Ok(())
+    }
+}
+
+fn is_data(message: &Message) -> bool {
+    !matches!(
+        message,
+        Message::Connected { .. }
+            | Message::Disconnected { .. }
+            | Message::Reconnected { .. }
+            | Message::Pong
+            | Message::SubscriptionResponse
+            | Message::HyperliquidError(_)
+    )
+}
+
+/// A queue belongs to one subscription, so its data messages all come from the same channel.
+/// Data replaces the newest queued data message of its kind and is dropped when there is none;
+/// anything else evicts the oldest queued data message, or the oldest message if no data is
+/// queued.
+fn coalesce(queue: &mut VecDeque<Message>, message: Message) {
+    if is_data(&message) {
+        let kind = mem::discriminant(&message);
+        if let Some(newest) = queue
+            .iter_mut()
+            .rev()
+            .find(|queued| mem::discriminant(*queued) == kind)
+        {
+            *newest = message;
+        }
+        return;
+    }
+    let evicted = queue.iter().position(is_data).unwrap_or(0);
+    queue.remove(evicted);
+    queue.push_back(message);
+}
+
+impl Drop for BoundedSender {
+    fn drop(&mut self) {
+        self.shared.sender_closed.store(true, Ordering::Relaxed);
+        self.shared.notify.notify_one();
+    }
+}
+
+/// Receiving end of a bounded subscription, see [`crate::InfoClient::subscribe_bounded`].
+#[derive(Debug)]
+pub struct SubscriptionReceiver {
+    shared: Arc<Shared>,
+}
+
+impl SubscriptionReceiver {
+    /// Waits for the next message, `None` once the subscription is removed and the queue drained.
+    pub async fn recv(&mut self) -> Option<Message> {
+        loop {
+            if let Some(message) = self.try_recv() {
+                return Some(message);
+            }
+            if self.shared.sender_closed.load(Ordering::Relaxed) {
+                return self.try_recv();
+            }
+            self.shared.notify.notified().await;
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<Message> {
+        self.shared.queue.lock().unwrap().pop_front()
+    }
+
+    /// Number of messages dropped or coalesced away because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = Message> {
+        futures_util::stream::unfold(self, |mut receiver| async move {
+            receiver.recv().await.map(|message| (message, receiver))
+        })
+    }
+}
+
+impl Drop for SubscriptionReceiver {
+    fn drop(&mut self) {
+        self.shared.receiver_closed.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum MessageSender {
+    Unbounded(UnboundedSender<Message>),
+    Bounded(BoundedSender),
+}
+
+impl MessageSender {
+    pub(crate) fn send(&self, message: Message) -> Result<()> {
+        match self {
+            MessageSender::Unbounded(sender) => sender
+                .send(message)
+                .map_err(|e| Error::WsSend(e.to_string())),
+            MessageSender::Bounded(sender) => sender.send(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AllMids, AllMidsData};
+    use std::collections::HashMap;
+
+    fn mids(n: u32) -> Message {
+        Message::AllMids(AllMids {
+            data: AllMidsData {
+                mids: HashMap::from([("n".to_string(), n.to_string())]),
+            },
+        })
+    }
+
+    // data messages as their number, connection state as its negated attempt
+    fn received(receiver: &mut SubscriptionReceiver) -> Vec<i64> {
+        std::iter::from_fn(|| receiver.try_recv())
+            .map(|message| match message {
+                Message::AllMids(all_mids) => all_mids.data.mids["n"].parse().unwrap(),
+                Message::Reconnected { attempt } => -(attempt as i64),
+                message => panic!("unexpected message {message:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_overflow_policies() {
+        for (policy, expected) in [
+            (OverflowPolicy::DropOldest, vec![3, 4]),
+            (OverflowPolicy::DropNewest, vec![1, 2]),
+            (OverflowPolicy::CoalesceLatest, vec![1, 4]),
+        ] {
+            let (sender, mut receiver) = bounded(2, policy);
+            for n in 1..=4 {
+                sender.send(mids(n)).unwrap();
+            }
+            assert_eq!(received(&mut receiver), expected, "{policy:?}");
+            assert_eq!(receiver.dropped(), 2);
+        }
+    }
+
+    #[test]
+    fn test_coalesce_keeps_connection_state() {
+        let (sender, mut receiver) = bounded(2, OverflowPolicy::CoalesceLatest);
+        sender.send(Message::Reconnected { attempt: 1 }).unwrap();
+        sender.send(mids(1)).unwrap();
+        sender.send(mids(2)).unwrap();
+        // the reconnect evicts data rather than the earlier connection state
+        sender.send(Message::Reconnected { attempt: 2 }).unwrap();
+        assert_eq!(received(&mut receiver), vec![-1, -2]);
+
+        sender.send(mids(3)).unwrap();
+        sender.send(Message::Reconnected { attempt: 3 }).unwrap();
+        sender.send(mids(4)).unwrap();
+        assert_eq!(received(&mut receiver), vec![4, -3]);
+    }
+
+    #[test]
+    fn test_send_fails_after_receiver_dropped() {
+        let (sender, receiver) = bounded(2, OverflowPolicy::DropOldest);
+        drop(receiver);
+        assert!(matches!(sender.send(Message::Pong), Err(Error::WsSend(_))));
+    }
+
+    #[tokio::test]
+    async fn test_recv_ends_after_sender_dropped() {
+        let (sender, mut receiver) = bounded(4, OverflowPolicy::DropOldest);
+        sender.send(Message::Pong).unwrap();
+        drop(sender);
+        assert!(matches!(receiver.recv().await, Some(Message::Pong)));
+        assert!(receiver.recv().await.is_none());
+    }
+}
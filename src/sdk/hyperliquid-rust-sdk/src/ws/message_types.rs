@@ -1,4 +1,5 @@
 use crate::ws::sub_structs::*;
+use ethers::types::H160;
 use serde::Deserialize;
 
 #[derive(Deserialize, Clone, Debug)]
@@ -18,6 +19,9 @@ pub struct AllMids {
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct User {
+    /// The subscribed user, filled in by the SDK since the message itself doesn't carry it.
+    #[serde(skip)]
+    pub user: H160,
     pub data: UserData,
 }
 
@@ -33,6 +37,9 @@ pub struct Candle {
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct OrderUpdates {
+    /// The subscribed user, see [`User::user`].
+    #[serde(skip)]
+    pub user: H160,
     pub data: Vec<OrderUpdate>,
 }
 
@@ -48,6 +55,9 @@ pub struct UserNonFundingLedgerUpdates {
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct Notification {
+    /// The subscribed user, see [`User::user`].
+    #[serde(skip)]
+    pub user: H160,
     pub data: NotificationData,
 }
 
@@ -60,3 +70,28 @@ pub struct WebData2 {
 pub struct ActiveAssetCtx {
     pub data: ActiveAssetCtxData,
 }
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Bbo {
+    pub data: BboData,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ActiveAssetData {
+    pub data: UserActiveAssetData,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct UserTwapSliceFills {
+    pub data: UserTwapSliceFillsData,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct UserTwapHistory {
+    pub data: UserTwapHistoryData,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Post {
+    pub data: PostData,
+}
@@ -1,7 +1,16 @@
+mod bounded_channel;
 mod message_types;
+mod post_client;
+mod replay;
 mod sub_structs;
+mod subscription_stream;
 mod ws_manager;
+pub(crate) use bounded_channel::{bounded, MessageSender};
+pub use bounded_channel::{OverflowPolicy, SubscriptionReceiver};
 pub use message_types::*;
+pub use post_client::{Transport, WsPostClient};
+pub use replay::{FrameRecorder, RecordedFrame, ReplayServer};
 pub use sub_structs::*;
+pub use subscription_stream::SubscriptionStream;
 pub(crate) use ws_manager::WsManager;
-pub use ws_manager::{Message, Subscription};
+pub use ws_manager::{Message, ReconnectConfig, Subscription};
@@ -0,0 +1,58 @@
+use crate::{
+    prelude::*,
+    ws::{ReconnectConfig, WsManager},
+    BaseUrl,
+};
+use std::{sync::Arc, time::Duration};
+
+/// Sends signed actions and info requests over a websocket connection, saving the HTTP round
+/// trip. Responses are matched to requests by id.
+///
+/// Posts are fire-and-forget: nothing is retried or recovered when the connection drops before
+/// the response arrives. The client opens its own connection, separate from subscriptions.
+#[derive(Debug)]
+pub struct WsPostClient {
+    ws_manager: WsManager,
+    timeout: Duration,
+}
+
+impl WsPostClient {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub async fn connect(
+        base_url: BaseUrl,
+        timeout: Duration,
+        reconnect: Option<ReconnectConfig>,
+    ) -> Result<WsPostClient> {
+        Ok(WsPostClient {
+            ws_manager: WsManager::new(base_url.get_ws_url(), reconnect, None, None).await?,
+            timeout,
+        })
+    }
+
+    pub(crate) async fn post_action(
+        &self,
+        payload: &serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        self.ws_manager.post("action", payload, self.timeout).await
+    }
+
+    /// Info responses are wrapped as `{"type": ..., "data": ...}`, only `data` is returned.
+    pub(crate) async fn post_info(&self, payload: &serde_json::Value) -> Result<serde_json::Value> {
+        let mut response = self.ws_manager.post("info", payload, self.timeout).await?;
+        Ok(response["data"].take())
+    }
+
+    pub async fn close(&mut self) -> Result<()> {
+        self.ws_manager.close().await
+    }
+}
+
+/// How `ExchangeClient` and `InfoClient` deliver requests. Only `Http` retries failed requests
+/// and recovers orders whose response was lost.
+#[derive(Debug, Clone, Default)]
+pub enum Transport {
+    #[default]
+    Http,
+    Ws(Arc<WsPostClient>),
+}
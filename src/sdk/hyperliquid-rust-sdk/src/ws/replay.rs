@@ -0,0 +1,236 @@
+use crate::{prelude::*, Error};
+use chrono::Utc;
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{net::TcpListener, spawn, sync::watch, time};
+use tokio_tungstenite::{accept_async, tungstenite::protocol};
+
+/// One raw websocket frame as received, a line of a recording.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedFrame {
+    /// Receive time in milliseconds.
+    pub ts: i64,
+    /// Connection the frame arrived on, numbered in the order connections were opened.
+    #[serde(default)]
+    pub connection: u64,
+    pub frame: String,
+}
+
+/// Appends every raw text frame received by the `WsManager`s sharing it to a JSONL file.
+#[derive(Debug)]
+pub struct FrameRecorder {
+    writer: Mutex<BufWriter<File>>,
+    next_connection: AtomicU64,
+}
+
+impl FrameRecorder {
+    pub fn create(path: impl AsRef<Path>) -> Result<Arc<FrameRecorder>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::Replay(e.to_string()))?;
+        Ok(Arc::new(FrameRecorder {
+            writer: Mutex::new(BufWriter::new(file)),
+            next_connection: AtomicU64::new(0),
+        }))
+    }
+
+    pub(crate) fn connection_id(&self) -> u64 {
+        self.next_connection.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub(crate) fn record(&self, connection: u64, frame: &str) {
+        let line = match serde_json::to_string(&RecordedFrame {
+            ts: Utc::now().timestamp_millis(),
+            connection,
+            frame: frame.to_string(),
+        }) {
+            Ok(line) => line,
+            Err(err) => {
+                warn!("Could not serialize recorded frame: {err}");
+                return;
+            }
+        };
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(err) = writeln!(writer, "{line}").and_then(|_| writer.flush()) {
+            warn!("Could not write recorded frame: {err}");
+        }
+    }
+}
+
+/// Loopback websocket server that plays a recording back, so recorded frames go through the
+/// same `WsManager` parsing and routing as live ones.
+///
+/// The n-th accepted connection gets the frames recorded on connection n. A consumer that opens
+/// its connections in the same order as the recorded one gets each connection's frames back on
+/// the matching connection.
+///
+/// Frames are held until [`ReplayServer::play`], giving consumers time to subscribe.
+#[derive(Debug)]
+pub struct ReplayServer {
+    url: String,
+    play: watch::Sender<bool>,
+}
+
+impl ReplayServer {
+    /// `speed` scales the recorded gaps between frames: 1.0 is real time, 10.0 ten times faster
+    /// and 0.0 sends everything without waiting.
+    pub async fn start(path: impl AsRef<Path>, speed: f64) -> Result<ReplayServer> {
+        let mut connections: HashMap<u64, Vec<RecordedFrame>> = HashMap::new();
+        for line in fs::read_to_string(path)
+            .map_err(|e| Error::Replay(e.to_string()))?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+        {
+            let frame = serde_json::from_str::<RecordedFrame>(line)
+                .map_err(|e| Error::JsonParse(e.to_string()))?;
+            connections.entry(frame.connection).or_default().push(frame);
+        }
+        let connections = Arc::new(connections);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| Error::Websocket(e.to_string()))?;
+        let url = format!(
+            "ws://{}",
+            listener
+                .local_addr()
+                .map_err(|e| Error::Websocket(e.to_string()))?
+        );
+        let (play, _) = watch::channel(false);
+
+        let play_receiver = play.subscribe();
+        spawn(async move {
+            let mut accepted = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                let connections = connections.clone();
+                let connection = accepted;
+                accepted += 1;
+                let mut play = play_receiver.clone();
+                spawn(async move {
+                    let (mut writer, mut reader) = match accept_async(stream).await {
+                        Ok(ws) => ws.split(),
+                        Err(err) => {
+                            warn!("Replay handshake failed: {err}");
+                            return;
+                        }
+                    };
+                    // Subscribe requests and pings from the client are not answered
+                    spawn(async move { while reader.next().await.is_some() {} });
+
+                    if play.wait_for(|play| *play).await.is_err() {
+                        return;
+                    }
+                    let frames = connections.get(&connection).map_or(&[][..], Vec::as_slice);
+                    let mut previous_ts = frames.first().map(|frame| frame.ts);
+                    for frame in frames {
+                        if let Some(delay) = replay_delay(previous_ts, frame.ts, speed) {
+                            time::sleep(delay).await;
+                        }
+                        previous_ts = Some(frame.ts);
+                        if let Err(err) = writer
+                            .send(protocol::Message::Text(frame.frame.clone()))
+                            .await
+                        {
+                            debug!("Replay connection closed: {err}");
+                            return;
+                        }
+                    }
+                    let _ = writer.close().await;
+                });
+            }
+        });
+
+        Ok(ReplayServer { url, play })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Starts sending the recording to all current and future connections.
+    pub fn play(&self) {
+        let _ = self.play.send(true);
+    }
+}
+
+fn replay_delay(previous_ts: Option<i64>, ts: i64, speed: f64) -> Option<Duration> {
+    if speed <= 0.0 {
+        return None;
+    }
+    let gap = ts - previous_ts?;
+    (gap > 0).then(|| Duration::from_millis(gap as u64).div_f64(speed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InfoClient;
+
+    #[test]
+    fn test_replay_delay() {
+        assert_eq!(replay_delay(None, 10, 1.0), None);
+        assert_eq!(replay_delay(Some(10), 110, 0.0), None);
+        assert_eq!(
+            replay_delay(Some(10), 110, 1.0),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            replay_delay(Some(10), 110, 4.0),
+            Some(Duration::from_millis(25))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay_l2_book() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "ws-recording-{}.jsonl",
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let recorder = FrameRecorder::create(&path)?;
+        let connection = recorder.connection_id();
+        for px in ["3000.0", "3000.5"] {
+            recorder.record(connection, &format!(
+                r#"{{"channel":"l2Book","data":{{"coin":"ETH","time":1,"levels":[[{{"px":"{px}","sz":"1","n":1}}],[]]}}}}"#
+            ));
+        }
+        recorder.record(connection, r#"{"channel":"pong"}"#);
+        // another connection's frames are not replayed on this one
+        let other = recorder.connection_id();
+        recorder.record(
+            other,
+            r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1.0","sz":"1","n":1}],[]]}}"#,
+        );
+
+        let replay = ReplayServer::start(&path, 0.0).await?;
+        let mut info_client = InfoClient::replay(&replay).await?;
+        let mut l2_book = info_client.subscribe_l2_book("ETH".to_string()).await?;
+        replay.play();
+
+        for px in ["3000.0", "3000.5"] {
+            let book = time::timeout(Duration::from_secs(5), l2_book.next())
+                .await
+                .map_err(|e| Error::Websocket(e.to_string()))?
+                .unwrap();
+            assert_eq!(book.levels[0][0].px, px);
+        }
+        assert!(time::timeout(Duration::from_millis(200), l2_book.next())
+            .await
+            .is_err());
+        let _ = fs::remove_file(path);
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::Leverage;
 use ethers::types::H160;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -27,6 +28,24 @@ pub struct L2BookData {
     pub levels: Vec<Vec<BookLevel>>,
 }
 
+#[derive(Deserialize, Clone, Debug)]
+pub struct BboData {
+    pub coin: String,
+    pub time: u64,
+    /// Best bid and best ask, `None` when that side of the book is empty.
+    pub bbo: [Option<BookLevel>; 2],
+}
+
+impl BboData {
+    pub fn bid(&self) -> Option<&BookLevel> {
+        self.bbo[0].as_ref()
+    }
+
+    pub fn ask(&self) -> Option<&BookLevel> {
+        self.bbo[1].as_ref()
+    }
+}
+
 #[derive(Deserialize, Clone, Debug)]
 pub struct AllMidsData {
     pub mids: HashMap<String, String>,
@@ -338,3 +357,86 @@ pub struct SpotAssetCtx {
     pub shared: SharedAssetCtx,
     pub circulating_supply: String,
 }
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserActiveAssetData {
+    pub user: H160,
+    pub coin: String,
+    pub leverage: Leverage,
+    /// Max order size as [buy, sell].
+    pub max_trade_szs: [String; 2],
+    /// Available to trade as [buy, sell].
+    pub available_to_trade: [String; 2],
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserTwapSliceFillsData {
+    #[serde(default)]
+    pub is_snapshot: bool,
+    pub user: H160,
+    pub twap_slice_fills: Vec<TwapSliceFill>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TwapSliceFill {
+    pub fill: TradeInfo,
+    pub twap_id: u64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserTwapHistoryData {
+    #[serde(default)]
+    pub is_snapshot: bool,
+    pub user: H160,
+    pub history: Vec<TwapHistory>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TwapHistory {
+    #[serde(default, rename = "twapId")]
+    pub twap_id: Option<u64>,
+    pub state: TwapState,
+    pub status: TwapStatus,
+    pub time: u64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TwapState {
+    pub coin: String,
+    pub user: H160,
+    pub side: String,
+    pub sz: String,
+    pub executed_sz: String,
+    pub executed_ntl: String,
+    pub minutes: u64,
+    pub reduce_only: bool,
+    pub randomize: bool,
+    pub timestamp: u64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TwapStatus {
+    /// One of "activated", "terminated", "finished" or "error".
+    pub status: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PostData {
+    pub id: u64,
+    pub response: PostResponse,
+}
+
+/// `response_type` is "info", "action" or "error"; errors carry a string payload.
+#[derive(Deserialize, Clone, Debug)]
+pub struct PostResponse {
+    #[serde(rename = "type")]
+    pub response_type: String,
+    pub payload: serde_json::Value,
+}
@@ -0,0 +1,58 @@
+use crate::ws::{ws_manager::SubscriptionGuard, Message};
+use futures_util::Stream;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// Stream of one subscription's payloads, see e.g. [`crate::InfoClient::subscribe_l2_book`].
+///
+/// Connection state messages are skipped. The subscription is removed when the stream is dropped.
+#[derive(Debug)]
+pub struct SubscriptionStream<T> {
+    receiver: UnboundedReceiver<Message>,
+    extract: fn(Message) -> Option<T>,
+    subscription_id: u32,
+    _guard: SubscriptionGuard,
+}
+
+impl<T> SubscriptionStream<T> {
+    pub(crate) fn new(
+        receiver: UnboundedReceiver<Message>,
+        extract: fn(Message) -> Option<T>,
+        subscription_id: u32,
+        guard: SubscriptionGuard,
+    ) -> Self {
+        SubscriptionStream {
+            receiver,
+            extract,
+            subscription_id,
+            _guard: guard,
+        }
+    }
+
+    /// Id to pass to [`crate::InfoClient::unsubscribe`] to end the subscription before the
+    /// stream is dropped.
+    pub fn subscription_id(&self) -> u32 {
+        self.subscription_id
+    }
+}
+
+impl<T> Stream for SubscriptionStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        loop {
+            match self.receiver.poll_recv(cx) {
+                Poll::Ready(Some(message)) => {
+                    if let Some(item) = (self.extract)(message) {
+                        return Poll::Ready(Some(item));
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use crate::{
     prelude::*,
-    ws::message_types::{AllMids, Candle, L2Book, OrderUpdates, Trades, User},
+    ws::bounded_channel::MessageSender,
+    ws::message_types::{
+        ActiveAssetData, AllMids, Bbo, Candle, L2Book, OrderUpdates, Post, Trades, User,
+        UserTwapHistory, UserTwapSliceFills,
+    },
+    ws::replay::FrameRecorder,
+    ws::PostResponse,
     ActiveAssetCtx, Error, Notification, UserFills, UserFundings, UserNonFundingLedgerUpdates,
     WebData2,
 };
@@ -11,9 +17,8 @@ use std::{
     borrow::BorrowMut,
     collections::HashMap,
     ops::DerefMut,
-    str::FromStr,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicU64, Ordering},
         Arc,
     },
     time::Duration,
@@ -21,7 +26,8 @@ use std::{
 use tokio::{
     net::TcpStream,
     spawn,
-    sync::{mpsc::UnboundedSender, Mutex},
+    sync::{oneshot, watch, Mutex},
+    task::JoinHandle,
     time,
 };
 use tokio_tungstenite::{
@@ -34,17 +40,78 @@ use ethers::types::H160;
 
 #[derive(Debug)]
 struct SubscriptionData {
-    sending_channel: UnboundedSender<Message>,
+    sending_channel: MessageSender,
     subscription_id: u32,
-    id: String,
 }
+/// Reconnect behaviour of the websocket: exponential backoff between attempts with random jitter.
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of each delay that is randomized away, between 0.0 and 1.0.
+    pub jitter: f64,
+    /// Give up after this many consecutive failed attempts, `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        ReconnectConfig {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.2,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// Delay before reconnect `attempt`, counting from 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self
+            .initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay);
+        delay.mul_f64(1.0 - self.jitter.clamp(0.0, 1.0) * rand::random::<f64>())
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct WsManager {
-    stop_flag: Arc<AtomicBool>,
+    shutdown: watch::Sender<bool>,
+    tasks: Vec<JoinHandle<()>>,
+    writer: Arc<Mutex<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, protocol::Message>>>,
+    subscriptions: Arc<Mutex<HashMap<String, Vec<SubscriptionData>>>>,
+    pending_posts: Arc<PendingPosts>,
+    next_post_id: AtomicU64,
+}
+
+/// Removes a subscription when dropped, so typed subscription streams clean up after themselves.
+#[derive(Debug)]
+pub(crate) struct SubscriptionGuard {
     writer: Arc<Mutex<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, protocol::Message>>>,
     subscriptions: Arc<Mutex<HashMap<String, Vec<SubscriptionData>>>>,
     subscription_id: u32,
-    subscription_identifiers: HashMap<u32, String>,
+}
+
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        let writer = self.writer.clone();
+        let subscriptions = self.subscriptions.clone();
+        let subscription_id = self.subscription_id;
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                match WsManager::remove(&writer, &subscriptions, subscription_id).await {
+                    // already pruned when a message found the receiver gone
+                    Ok(()) | Err(Error::SubscriptionNotFound) => {}
+                    Err(err) => {
+                        warn!("Could not remove dropped subscription {subscription_id}: {err}")
+                    }
+                }
+            });
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -63,13 +130,44 @@ pub enum Subscription {
     UserFundings { user: H160 },
     UserNonFundingLedgerUpdates { user: H160 },
     ActiveAssetCtx { coin: String },
+    Bbo { coin: String },
+    ActiveAssetData { user: H160, coin: String },
+    UserTwapSliceFills { user: H160 },
+    UserTwapHistory { user: H160 },
+}
+
+impl Subscription {
+    /// User of a channel whose messages don't say which user they belong to. Such channels get a
+    /// connection per user so their messages can be attributed.
+    pub(crate) fn connection_user(&self) -> Option<H160> {
+        match self {
+            Subscription::UserEvents { user }
+            | Subscription::OrderUpdates { user }
+            | Subscription::Notification { user } => Some(*user),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Deserialize, Clone, Debug)]
 #[serde(tag = "channel")]
 #[serde(rename_all = "camelCase")]
 pub enum Message {
-    NoData,
+    /// Sent to a subscription once its subscribe request has been written to the socket.
+    #[serde(skip)]
+    Connected {
+        attempt: u32,
+    },
+    /// The connection dropped (`attempt` 0) or a reconnect attempt failed.
+    #[serde(skip)]
+    Disconnected {
+        attempt: u32,
+    },
+    /// The connection was re-established and all subscriptions were restored.
+    #[serde(skip)]
+    Reconnected {
+        attempt: u32,
+    },
     HyperliquidError(String),
     AllMids(AllMids),
     Trades(Trades),
@@ -84,15 +182,47 @@ pub enum Message {
     Notification(Notification),
     WebData2(WebData2),
     ActiveAssetCtx(ActiveAssetCtx),
+    Bbo(Bbo),
+    ActiveAssetData(ActiveAssetData),
+    UserTwapSliceFills(UserTwapSliceFills),
+    UserTwapHistory(UserTwapHistory),
+    Post(Post),
     Pong,
 }
 
+impl Message {
+    fn set_user(&mut self, user: H160) {
+        match self {
+            Message::User(user_event) => user_event.user = user,
+            Message::OrderUpdates(order_updates) => order_updates.user = user,
+            Message::Notification(notification) => notification.user = user,
+            _ => {}
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub(crate) struct SubscriptionSendData<'a> {
     method: &'static str,
     subscription: &'a serde_json::Value,
 }
 
+#[derive(Serialize)]
+struct PostRequest<'a> {
+    method: &'static str,
+    id: u64,
+    request: PostRequestBody<'a>,
+}
+
+#[derive(Serialize)]
+struct PostRequestBody<'a> {
+    #[serde(rename = "type")]
+    request_type: &'static str,
+    payload: &'a serde_json::Value,
+}
+
+type PendingPosts = std::sync::Mutex<HashMap<u64, oneshot::Sender<PostResponse>>>;
+
 #[derive(Serialize)]
 pub(crate) struct Ping {
     method: &'static str,
@@ -101,8 +231,15 @@ pub(crate) struct Ping {
 impl WsManager {
     const SEND_PING_INTERVAL: u64 = 15;
 
-    pub(crate) async fn new(url: String, reconnect: bool) -> Result<WsManager> {
-        let stop_flag = Arc::new(AtomicBool::new(false));
+    /// `user` is set for connections dedicated to one user's `userEvents`, `orderUpdates` and
+    /// `notification` channels; their messages are tagged with it.
+    pub(crate) async fn new(
+        url: String,
+        reconnect: Option<ReconnectConfig>,
+        user: Option<H160>,
+        recorder: Option<Arc<FrameRecorder>>,
+    ) -> Result<WsManager> {
+        let (shutdown, _) = watch::channel(false);
 
         let (writer, mut reader) = Self::connect(&url).await?.split();
         let writer = Arc::new(Mutex::new(writer));
@@ -110,82 +247,120 @@ impl WsManager {
         let subscriptions_map: HashMap<String, Vec<SubscriptionData>> = HashMap::new();
         let subscriptions = Arc::new(Mutex::new(subscriptions_map));
         let subscriptions_copy = Arc::clone(&subscriptions);
+        let pending_posts = Arc::new(PendingPosts::default());
 
-        {
+        // Frames are recorded under an id for this connection, kept across reconnects
+        let recorder = recorder.map(|recorder| {
+            let connection = recorder.connection_id();
+            (recorder, connection)
+        });
+        let reader_task = {
+            let pending_posts = pending_posts.clone();
             let writer = writer.clone();
-            let stop_flag = Arc::clone(&stop_flag);
+            let mut shutdown = shutdown.subscribe();
             let reader_fut = async move {
-                while !stop_flag.load(Ordering::Relaxed) {
-                    if let Some(data) = reader.next().await {
-                        if let Err(err) =
-                            WsManager::parse_and_send_data(data, &subscriptions_copy).await
+                loop {
+                    let data = tokio::select! {
+                        _ = shutdown.changed() => break,
+                        data = reader.next() => data,
+                    };
+                    if let Some(data) = data {
+                        if let (Some((recorder, connection)), Ok(protocol::Message::Text(frame))) =
+                            (&recorder, &data)
                         {
-                            error!("Error processing data received by WsManager reader: {err}");
+                            recorder.record(*connection, frame);
                         }
-                    } else {
-                        warn!("WsManager disconnected");
-                        if let Err(err) = WsManager::send_to_all_subscriptions(
+                        match WsManager::parse_and_send_data(
+                            data,
+                            user,
                             &subscriptions_copy,
-                            Message::NoData,
+                            &pending_posts,
                         )
                         .await
                         {
-                            warn!("Error sending disconnection notification err={err}");
+                            Ok(orphaned) => Self::unsubscribe_orphaned(&writer, orphaned).await,
+                            Err(err) => {
+                                error!("Error processing data received by WsManager reader: {err}")
+                            }
                         }
-                        if reconnect {
-                            // Always sleep for 1 second before attempting to reconnect so it does not spin during reconnecting. This could be enhanced with exponential backoff.
-                            tokio::time::sleep(Duration::from_secs(1)).await;
-                            info!("WsManager attempting to reconnect");
-                            match Self::connect(&url).await {
-                                Ok(ws) => {
-                                    let (new_writer, new_reader) = ws.split();
-                                    reader = new_reader;
-                                    let mut writer_guard = writer.lock().await;
-                                    *writer_guard = new_writer;
-                                    for (identifier, v) in subscriptions_copy.lock().await.iter() {
-                                        // TODO should these special keys be removed and instead use the simpler direct identifier mapping?
-                                        if identifier.eq("userEvents")
-                                            || identifier.eq("orderUpdates")
-                                        {
-                                            for subscription_data in v {
-                                                if let Err(err) = Self::subscribe(
-                                                    writer_guard.deref_mut(),
-                                                    &subscription_data.id,
-                                                )
-                                                .await
-                                                {
-                                                    error!(
-                                                        "Could not resubscribe {identifier}: {err}"
-                                                    );
-                                                }
-                                            }
-                                        } else if let Err(err) =
-                                            Self::subscribe(writer_guard.deref_mut(), identifier)
-                                                .await
-                                        {
-                                            error!("Could not resubscribe correctly {identifier}: {err}");
-                                        }
-                                    }
-                                    info!("WsManager reconnect finished");
-                                }
-                                Err(err) => error!("Could not connect to websocket {err}"),
+                        continue;
+                    }
+
+                    warn!("WsManager disconnected");
+                    // Responses to in-flight posts are lost with the connection
+                    pending_posts.lock().unwrap().clear();
+                    Self::notify_all(&subscriptions_copy, Message::Disconnected { attempt: 0 })
+                        .await;
+                    let Some(config) = &reconnect else {
+                        error!("WsManager reconnection disabled. Will not reconnect and exiting reader task.");
+                        break;
+                    };
+
+                    let mut attempt = 0;
+                    let ws = loop {
+                        attempt += 1;
+                        if config.max_attempts.is_some_and(|max| attempt > max) {
+                            break None;
+                        }
+                        tokio::select! {
+                            _ = shutdown.changed() => break None,
+                            _ = time::sleep(config.delay(attempt)) => {}
+                        }
+                        info!("WsManager reconnect attempt {attempt}");
+                        match Self::connect(&url).await {
+                            Ok(ws) => break Some(ws),
+                            Err(err) => {
+                                error!("Could not connect to websocket {err}");
+                                Self::notify_all(
+                                    &subscriptions_copy,
+                                    Message::Disconnected { attempt },
+                                )
+                                .await;
+                            }
+                        }
+                    };
+                    let Some(ws) = ws else {
+                        if !*shutdown.borrow() {
+                            error!(
+                                "WsManager giving up after {} reconnect attempts",
+                                attempt - 1
+                            );
+                        }
+                        break;
+                    };
+
+                    let (new_writer, new_reader) = ws.split();
+                    reader = new_reader;
+                    {
+                        let mut writer_guard = writer.lock().await;
+                        *writer_guard = new_writer;
+                        for identifier in subscriptions_copy.lock().await.keys() {
+                            if let Err(err) =
+                                Self::subscribe(writer_guard.deref_mut(), identifier).await
+                            {
+                                error!("Could not resubscribe correctly {identifier}: {err}");
                             }
-                        } else {
-                            error!("WsManager reconnection disabled. Will not reconnect and exiting reader task.");
-                            break;
                         }
                     }
+                    info!("WsManager reconnect finished after {attempt} attempts");
+                    let orphaned = Self::send_to_subscriptions(
+                        &subscriptions_copy,
+                        None,
+                        Message::Reconnected { attempt },
+                    )
+                    .await;
+                    Self::unsubscribe_orphaned(&writer, orphaned).await;
                 }
                 warn!("ws message reader task stopped");
             };
-            spawn(reader_fut);
-        }
+            spawn(reader_fut)
+        };
 
-        {
-            let stop_flag = Arc::clone(&stop_flag);
+        let ping_task = {
+            let mut shutdown = shutdown.subscribe();
             let writer = Arc::clone(&writer);
             let ping_fut = async move {
-                while !stop_flag.load(Ordering::Relaxed) {
+                loop {
                     match serde_json::to_string(&Ping { method: "ping" }) {
                         Ok(payload) => {
                             let mut writer = writer.lock().await;
@@ -196,22 +371,94 @@ impl WsManager {
                         }
                         Err(err) => error!("Error serializing ping message: {err}"),
                     }
-                    time::sleep(Duration::from_secs(Self::SEND_PING_INTERVAL)).await;
+                    tokio::select! {
+                        _ = shutdown.changed() => break,
+                        _ = time::sleep(Duration::from_secs(Self::SEND_PING_INTERVAL)) => {}
+                    }
                 }
                 warn!("ws ping task stopped");
             };
-            spawn(ping_fut);
-        }
+            spawn(ping_fut)
+        };
 
         Ok(WsManager {
-            stop_flag,
+            shutdown,
+            tasks: vec![reader_task, ping_task],
             writer,
             subscriptions,
-            subscription_id: 0,
-            subscription_identifiers: HashMap::new(),
+            pending_posts,
+            next_post_id: AtomicU64::new(0),
         })
     }
 
+    /// Sends a `{"method":"post"}` request and waits for the response with the same id.
+    pub(crate) async fn post(
+        &self,
+        request_type: &'static str,
+        payload: &serde_json::Value,
+        timeout: Duration,
+    ) -> Result<serde_json::Value> {
+        let id = self.next_post_id.fetch_add(1, Ordering::Relaxed);
+        let request = serde_json::to_string(&PostRequest {
+            method: "post",
+            id,
+            request: PostRequestBody {
+                request_type,
+                payload,
+            },
+        })
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let (sender, receiver) = oneshot::channel();
+        self.pending_posts.lock().unwrap().insert(id, sender);
+        debug!("Sending post {request}");
+        if let Err(err) = self
+            .writer
+            .lock()
+            .await
+            .send(protocol::Message::Text(request))
+            .await
+        {
+            self.pending_posts.lock().unwrap().remove(&id);
+            return Err(Error::Websocket(err.to_string()));
+        }
+
+        let response = match time::timeout(timeout, receiver).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => {
+                return Err(Error::WsPost(format!(
+                    "connection closed before response to post {id}"
+                )))
+            }
+            Err(_) => {
+                self.pending_posts.lock().unwrap().remove(&id);
+                return Err(Error::WsPost(format!(
+                    "no response to post {id} within {timeout:?}"
+                )));
+            }
+        };
+        if response.response_type == "error" {
+            let error = match response.payload {
+                serde_json::Value::String(error) => error,
+                payload => payload.to_string(),
+            };
+            return Err(Error::WsPost(error));
+        }
+        Ok(response.payload)
+    }
+
+    /// Stops the reader and ping tasks, closes the socket and waits for both tasks to finish.
+    pub(crate) async fn close(&mut self) -> Result<()> {
+        let _ = self.shutdown.send(true);
+        if let Err(err) = self.writer.lock().await.close().await {
+            debug!("Error closing websocket: {err}");
+        }
+        for task in self.tasks.drain(..) {
+            task.await.map_err(|e| Error::Websocket(e.to_string()))?;
+        }
+        Ok(())
+    }
+
     async fn connect(url: &str) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
         Ok(connect_async(url)
             .await
@@ -223,7 +470,10 @@ impl WsManager {
         match message {
             Message::AllMids(_) => serde_json::to_string(&Subscription::AllMids)
                 .map_err(|e| Error::JsonParse(e.to_string())),
-            Message::User(_) => Ok("userEvents".to_string()),
+            Message::User(user_event) => serde_json::to_string(&Subscription::UserEvents {
+                user: user_event.user,
+            })
+            .map_err(|e| Error::JsonParse(e.to_string())),
             Message::UserFills(fills) => serde_json::to_string(&Subscription::UserFills {
                 user: fills.data.user,
             })
@@ -247,7 +497,12 @@ impl WsManager {
                 interval: candle.data.interval.clone(),
             })
             .map_err(|e| Error::JsonParse(e.to_string())),
-            Message::OrderUpdates(_) => Ok("orderUpdates".to_string()),
+            Message::OrderUpdates(order_updates) => {
+                serde_json::to_string(&Subscription::OrderUpdates {
+                    user: order_updates.user,
+                })
+                .map_err(|e| Error::JsonParse(e.to_string()))
+            }
             Message::UserFundings(fundings) => serde_json::to_string(&Subscription::UserFundings {
                 user: fundings.data.user,
             })
@@ -258,7 +513,12 @@ impl WsManager {
                 })
                 .map_err(|e| Error::JsonParse(e.to_string()))
             }
-            Message::Notification(_) => Ok("notification".to_string()),
+            Message::Notification(notification) => {
+                serde_json::to_string(&Subscription::Notification {
+                    user: notification.user,
+                })
+                .map_err(|e| Error::JsonParse(e.to_string()))
+            }
             Message::WebData2(web_data2) => serde_json::to_string(&Subscription::WebData2 {
                 user: web_data2.data.user,
             })
@@ -269,86 +529,159 @@ impl WsManager {
                 })
                 .map_err(|e| Error::JsonParse(e.to_string()))
             }
+            Message::Bbo(bbo) => serde_json::to_string(&Subscription::Bbo {
+                coin: bbo.data.coin.clone(),
+            })
+            .map_err(|e| Error::JsonParse(e.to_string())),
+            Message::ActiveAssetData(active_asset_data) => {
+                serde_json::to_string(&Subscription::ActiveAssetData {
+                    user: active_asset_data.data.user,
+                    coin: active_asset_data.data.coin.clone(),
+                })
+                .map_err(|e| Error::JsonParse(e.to_string()))
+            }
+            Message::UserTwapSliceFills(user_twap_slice_fills) => {
+                serde_json::to_string(&Subscription::UserTwapSliceFills {
+                    user: user_twap_slice_fills.data.user,
+                })
+                .map_err(|e| Error::JsonParse(e.to_string()))
+            }
+            Message::UserTwapHistory(user_twap_history) => {
+                serde_json::to_string(&Subscription::UserTwapHistory {
+                    user: user_twap_history.data.user,
+                })
+                .map_err(|e| Error::JsonParse(e.to_string()))
+            }
             Message::SubscriptionResponse | Message::Pong => Ok("pong".to_string()),
-            Message::NoData => Ok("".to_string()),
+            Message::Connected { .. }
+            | Message::Disconnected { .. }
+            | Message::Reconnected { .. }
+            | Message::Post(_) => Ok("".to_string()),
             Message::HyperliquidError(err) => Ok(format!("hyperliquid error: {err:?}")),
         }
     }
 
     async fn parse_and_send_data(
         data: std::result::Result<protocol::Message, tungstenite::Error>,
+        user: Option<H160>,
         subscriptions: &Arc<Mutex<HashMap<String, Vec<SubscriptionData>>>>,
-    ) -> Result<()> {
+        pending_posts: &PendingPosts,
+    ) -> Result<Vec<String>> {
         match data {
             Ok(data) => match data.into_text() {
                 Ok(data) => {
                     if !data.starts_with('{') {
-                        return Ok(());
+                        return Ok(Vec::new());
                     }
-                    let message = serde_json::from_str::<Message>(&data)
+                    let mut message = serde_json::from_str::<Message>(&data)
                         .map_err(|e| Error::JsonParse(e.to_string()))?;
+                    if let Message::Post(post) = message {
+                        let sender = pending_posts.lock().unwrap().remove(&post.data.id);
+                        match sender {
+                            Some(sender) => {
+                                let _ = sender.send(post.data.response);
+                            }
+                            None => warn!("Response to unknown or expired post {}", post.data.id),
+                        }
+                        return Ok(Vec::new());
+                    }
+                    if let Some(user) = user {
+                        message.set_user(user);
+                    }
                     // 为 Pong 消息添加特殊处理
                     if let Message::Pong = &message {
-                        return WsManager::send_to_all_subscriptions(subscriptions, message).await;
+                        return Ok(
+                            WsManager::send_to_subscriptions(subscriptions, None, message).await,
+                        );
                     }
                     let identifier = WsManager::get_identifier(&message)?;
                     if identifier.is_empty() {
-                        return Ok(());
+                        return Ok(Vec::new());
                     }
-
-                    let mut subscriptions = subscriptions.lock().await;
-                    let mut res = Ok(());
-                    if let Some(subscription_datas) = subscriptions.get_mut(&identifier) {
-                        for subscription_data in subscription_datas {
-                            if let Err(e) = subscription_data
-                                .sending_channel
-                                .send(message.clone())
-                                .map_err(|e| Error::WsSend(e.to_string()))
-                            {
-                                res = Err(e);
-                            }
-                        }
-                    }
-                    res
+                    Ok(
+                        WsManager::send_to_subscriptions(subscriptions, Some(&identifier), message)
+                            .await,
+                    )
                 }
                 Err(err) => {
                     let error = Error::ReaderTextConversion(err.to_string());
-                    Ok(WsManager::send_to_all_subscriptions(
+                    Ok(WsManager::send_to_subscriptions(
                         subscriptions,
+                        None,
                         Message::HyperliquidError(error.to_string()),
                     )
-                    .await?)
+                    .await)
                 }
             },
             Err(err) => {
                 let error = Error::GenericReader(err.to_string());
-                Ok(WsManager::send_to_all_subscriptions(
+                Ok(WsManager::send_to_subscriptions(
                     subscriptions,
+                    None,
                     Message::HyperliquidError(error.to_string()),
                 )
-                .await?)
+                .await)
             }
         }
     }
 
-    async fn send_to_all_subscriptions(
+    /// Connection state changes go to every subscription. Subscriptions pruned meanwhile are
+    /// dropped from the map, so the resubscribe after a reconnect skips them.
+    async fn notify_all(
         subscriptions: &Arc<Mutex<HashMap<String, Vec<SubscriptionData>>>>,
         message: Message,
-    ) -> Result<()> {
+    ) {
+        for identifier in WsManager::send_to_subscriptions(subscriptions, None, message).await {
+            debug!("Subscription {identifier} has no receivers left");
+        }
+    }
+
+    /// Sends `message` to the subscriptions under `identifier`, or to all of them when `None`.
+    /// A subscription whose receiver is gone is removed on the first failed send. Identifiers
+    /// left without subscriptions are removed too and returned, so they can be unsubscribed.
+    async fn send_to_subscriptions(
+        subscriptions: &Arc<Mutex<HashMap<String, Vec<SubscriptionData>>>>,
+        identifier: Option<&str>,
+        message: Message,
+    ) -> Vec<String> {
         let mut subscriptions = subscriptions.lock().await;
-        let mut res = Ok(());
-        for subscription_datas in subscriptions.values_mut() {
-            for subscription_data in subscription_datas {
-                if let Err(e) = subscription_data
-                    .sending_channel
-                    .send(message.clone())
-                    .map_err(|e| Error::WsSend(e.to_string()))
-                {
-                    res = Err(e);
+        let mut orphaned = Vec::new();
+        for (key, subscription_datas) in subscriptions.iter_mut() {
+            if identifier.is_some_and(|identifier| identifier != key) {
+                continue;
+            }
+            subscription_datas.retain(|subscription_data| {
+                match subscription_data.sending_channel.send(message.clone()) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        warn!(
+                            "Removing subscription {} from {key}: {err}",
+                            subscription_data.subscription_id
+                        );
+                        false
+                    }
                 }
+            });
+            if subscription_datas.is_empty() {
+                orphaned.push(key.clone());
+            }
+        }
+        for key in &orphaned {
+            subscriptions.remove(key);
+        }
+        orphaned
+    }
+
+    async fn unsubscribe_orphaned(
+        writer: &Mutex<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, protocol::Message>>,
+        identifiers: Vec<String>,
+    ) {
+        for identifier in identifiers {
+            if let Err(err) = Self::unsubscribe(writer.lock().await.borrow_mut(), &identifier).await
+            {
+                warn!("Could not unsubscribe {identifier}: {err}");
             }
         }
-        res
     }
 
     async fn send_subscription_data(
@@ -387,91 +720,278 @@ impl WsManager {
     pub(crate) async fn add_subscription(
         &mut self,
         identifier: String,
-        sending_channel: UnboundedSender<Message>,
-    ) -> Result<u32> {
+        sending_channel: MessageSender,
+        subscription_id: u32,
+    ) -> Result<()> {
         let mut subscriptions = self.subscriptions.lock().await;
-
-        let identifier_entry = if let Subscription::UserEvents { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
-        {
-            "userEvents".to_string()
-        } else if let Subscription::OrderUpdates { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
-        {
-            "orderUpdates".to_string()
-        } else {
-            identifier.clone()
-        };
         let subscriptions = subscriptions
-            .entry(identifier_entry.clone())
+            .entry(identifier.clone())
             .or_insert(Vec::new());
 
-        if !subscriptions.is_empty() && identifier_entry.eq("userEvents") {
-            return Err(Error::UserEvents);
-        }
-
         if subscriptions.is_empty() {
             Self::subscribe(self.writer.lock().await.borrow_mut(), identifier.as_str()).await?;
         }
 
-        let subscription_id = self.subscription_id;
-        self.subscription_identifiers
-            .insert(subscription_id, identifier.clone());
+        sending_channel.send(Message::Connected { attempt: 0 })?;
+
         subscriptions.push(SubscriptionData {
             sending_channel,
             subscription_id,
-            id: identifier,
         });
-
-        self.subscription_id += 1;
-        Ok(subscription_id)
+        Ok(())
     }
 
     pub(crate) async fn remove_subscription(&mut self, subscription_id: u32) -> Result<()> {
-        let identifier = self
-            .subscription_identifiers
-            .get(&subscription_id)
-            .ok_or(Error::SubscriptionNotFound)?
-            .clone();
-
-        let identifier_entry = if let Subscription::UserEvents { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
-        {
-            "userEvents".to_string()
-        } else if let Subscription::OrderUpdates { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
-        {
-            "orderUpdates".to_string()
-        } else {
-            identifier.clone()
-        };
-
-        self.subscription_identifiers.remove(&subscription_id);
+        Self::remove(&self.writer, &self.subscriptions, subscription_id).await
+    }
 
-        let mut subscriptions = self.subscriptions.lock().await;
+    pub(crate) fn subscription_guard(&self, subscription_id: u32) -> SubscriptionGuard {
+        SubscriptionGuard {
+            writer: self.writer.clone(),
+            subscriptions: self.subscriptions.clone(),
+            subscription_id,
+        }
+    }
 
-        let subscriptions = subscriptions
-            .get_mut(&identifier_entry)
-            .ok_or(Error::SubscriptionNotFound)?;
-        let index = subscriptions
-            .iter()
-            .position(|subscription_data| subscription_data.subscription_id == subscription_id)
+    async fn remove(
+        writer: &Mutex<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, protocol::Message>>,
+        subscriptions: &Mutex<HashMap<String, Vec<SubscriptionData>>>,
+        subscription_id: u32,
+    ) -> Result<()> {
+        let mut subscriptions = subscriptions.lock().await;
+        let (identifier, subscription_datas) = subscriptions
+            .iter_mut()
+            .find(|(_, subscription_datas)| {
+                subscription_datas
+                    .iter()
+                    .any(|subscription_data| subscription_data.subscription_id == subscription_id)
+            })
             .ok_or(Error::SubscriptionNotFound)?;
-        subscriptions.remove(index);
+        subscription_datas
+            .retain(|subscription_data| subscription_data.subscription_id != subscription_id);
 
-        if subscriptions.is_empty() {
-            Self::unsubscribe(self.writer.lock().await.borrow_mut(), identifier.as_str()).await?;
+        if subscription_datas.is_empty() {
+            let identifier = identifier.clone();
+            subscriptions.remove(&identifier);
+            Self::unsubscribe(writer.lock().await.borrow_mut(), identifier.as_str()).await?;
         }
         Ok(())
     }
+
+    pub(crate) async fn is_empty(&self) -> bool {
+        self.subscriptions.lock().await.is_empty()
+    }
 }
 
 impl Drop for WsManager {
     fn drop(&mut self) {
-        self.stop_flag.store(true, Ordering::Relaxed);
+        let _ = self.shutdown.send(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[test]
+    fn test_reconnect_delay() {
+        let config = ReconnectConfig {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            jitter: 0.0,
+            max_attempts: Some(10),
+        };
+        let delays: Vec<u128> = (1..=6).map(|n| config.delay(n).as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+
+        let jittered = ReconnectConfig {
+            jitter: 0.5,
+            ..config
+        };
+        for attempt in 1..=6 {
+            let delay = jittered.delay(attempt);
+            assert!(delay <= config.delay(attempt) && delay >= config.delay(attempt) / 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_user_events_routed_to_connection_user() -> Result<()> {
+        let user = H160::from_str("0x0d1d9635d0640821d15e323ac8adadfa9c111414").unwrap();
+        let identifier = serde_json::to_string(&Subscription::UserEvents { user }).unwrap();
+        let (sender, mut receiver) = unbounded_channel();
+        let subscriptions = Arc::new(Mutex::new(HashMap::from([(
+            identifier,
+            vec![SubscriptionData {
+                sending_channel: MessageSender::Unbounded(sender),
+                subscription_id: 0,
+            }],
+        )])));
+
+        let frame = r#"{"channel":"user","data":{"fills":[]}}"#;
+        WsManager::parse_and_send_data(
+            Ok(protocol::Message::Text(frame.to_string())),
+            Some(user),
+            &subscriptions,
+            &PendingPosts::default(),
+        )
+        .await?;
+
+        match receiver.try_recv() {
+            Ok(Message::User(user_event)) => assert_eq!(user_event.user, user),
+            message => panic!("unexpected message {message:?}"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_closed_receivers_pruned() -> Result<()> {
+        let identifier = serde_json::to_string(&Subscription::AllMids).unwrap();
+        let (live, mut live_receiver) = unbounded_channel();
+        let (dead, dead_receiver) = unbounded_channel();
+        drop(dead_receiver);
+        let subscriptions = Arc::new(Mutex::new(HashMap::from([(
+            identifier.clone(),
+            vec![
+                SubscriptionData {
+                    sending_channel: MessageSender::Unbounded(live),
+                    subscription_id: 0,
+                },
+                SubscriptionData {
+                    sending_channel: MessageSender::Unbounded(dead),
+                    subscription_id: 1,
+                },
+            ],
+        )])));
+
+        let frame = r#"{"channel":"allMids","data":{"mids":{}}}"#;
+        let orphaned = WsManager::parse_and_send_data(
+            Ok(protocol::Message::Text(frame.to_string())),
+            None,
+            &subscriptions,
+            &PendingPosts::default(),
+        )
+        .await?;
+        assert!(orphaned.is_empty());
+        assert_eq!(subscriptions.lock().await[&identifier].len(), 1);
+        assert!(matches!(live_receiver.try_recv(), Ok(Message::AllMids(_))));
+
+        drop(live_receiver);
+        let orphaned = WsManager::parse_and_send_data(
+            Ok(protocol::Message::Text(frame.to_string())),
+            None,
+            &subscriptions,
+            &PendingPosts::default(),
+        )
+        .await?;
+        assert_eq!(orphaned, vec![identifier]);
+        assert!(subscriptions.lock().await.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_new_channels_routed() -> Result<()> {
+        let user = H160::from_str("0x0d1d9635d0640821d15e323ac8adadfa9c111414").unwrap();
+        let frames = [
+            (
+                Subscription::Bbo {
+                    coin: "ETH".to_string(),
+                },
+                r#"{"channel":"bbo","data":{"coin":"ETH","time":1,"bbo":[{"px":"3000.1","sz":"2","n":3},null]}}"#,
+            ),
+            (
+                Subscription::ActiveAssetData {
+                    user,
+                    coin: "ETH".to_string(),
+                },
+                r#"{"channel":"activeAssetData","data":{"user":"0x0d1d9635d0640821d15e323ac8adadfa9c111414","coin":"ETH","leverage":{"type":"cross","value":20},"maxTradeSzs":["1.5","2.5"],"availableToTrade":["4500.0","7500.0"]}}"#,
+            ),
+            (
+                Subscription::UserTwapHistory { user },
+                r#"{"channel":"userTwapHistory","data":{"isSnapshot":true,"user":"0x0d1d9635d0640821d15e323ac8adadfa9c111414","history":[{"state":{"coin":"ETH","user":"0x0d1d9635d0640821d15e323ac8adadfa9c111414","side":"B","sz":"1.0","executedSz":"0.5","executedNtl":"1500.0","minutes":10,"reduceOnly":false,"randomize":true,"timestamp":1},"status":{"status":"activated"},"time":1}]}}"#,
+            ),
+        ];
+
+        for (subscription, frame) in frames {
+            let (sender, mut receiver) = unbounded_channel();
+            let subscriptions = Arc::new(Mutex::new(HashMap::from([(
+                serde_json::to_string(&subscription).unwrap(),
+                vec![SubscriptionData {
+                    sending_channel: MessageSender::Unbounded(sender),
+                    subscription_id: 0,
+                }],
+            )])));
+            WsManager::parse_and_send_data(
+                Ok(protocol::Message::Text(frame.to_string())),
+                None,
+                &subscriptions,
+                &PendingPosts::default(),
+            )
+            .await?;
+
+            match (subscription, receiver.try_recv()) {
+                (Subscription::Bbo { .. }, Ok(Message::Bbo(bbo))) => {
+                    assert_eq!(bbo.data.bid().unwrap().px, "3000.1");
+                    assert!(bbo.data.ask().is_none());
+                }
+                (Subscription::ActiveAssetData { .. }, Ok(Message::ActiveAssetData(data))) => {
+                    assert_eq!(data.data.max_trade_szs[0], "1.5");
+                    assert_eq!(data.data.leverage.value, 20);
+                }
+                (Subscription::UserTwapHistory { .. }, Ok(Message::UserTwapHistory(history))) => {
+                    assert_eq!(history.data.history[0].state.executed_sz, "0.5");
+                    assert_eq!(history.data.history[0].status.status, "activated");
+                }
+                (subscription, message) => panic!("{subscription:?} got {message:?}"),
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_matches_response_by_id() -> Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(frame)) = ws.next().await {
+                let Ok(text) = frame.into_text() else {
+                    continue;
+                };
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                if request["method"] != "post" {
+                    continue;
+                }
+                let response = match request["request"]["payload"]["type"].as_str() {
+                    Some("meta") => serde_json::json!({
+                        "type": "info",
+                        "payload": {"type": "meta", "data": {"universe": []}},
+                    }),
+                    _ => serde_json::json!({"type": "error", "payload": "bad request"}),
+                };
+                let frame = serde_json::json!({
+                    "channel": "post",
+                    "data": {"id": request["id"], "response": response},
+                });
+                ws.send(protocol::Message::Text(frame.to_string()))
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let ws_manager = WsManager::new(url, None, None, None).await?;
+        let timeout = Duration::from_secs(5);
+        let info = ws_manager
+            .post("info", &serde_json::json!({"type": "meta"}), timeout)
+            .await?;
+        assert_eq!(info["data"]["universe"], serde_json::json!([]));
+        let err = ws_manager
+            .post("action", &serde_json::json!({"type": "order"}), timeout)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::WsPost(error) if error == "bad request"));
+        Ok(())
     }
 }
@@ -0,0 +1,182 @@
+use anyhow::{anyhow, bail, Result};
+use axum::{
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    routing::post,
+    Json, Router,
+};
+use ethers::signers::LocalWallet;
+use hyperliquid_rust_sdk::{KeystoreSigner, RemoteSigner, Signer, SignerRequest, SignerResponse};
+use std::{
+    env,
+    fs::{self, DirBuilder},
+    os::unix::fs::{DirBuilderExt, PermissionsExt},
+    path::Path,
+    sync::Arc,
+};
+use subtle::ConstantTimeEq;
+use tokio::net::UnixListener;
+
+use crate::agent::{AgentRecord, AgentStore};
+
+/// 守护进程使用的签名者，以及使用 agent 时对应的记录
+#[derive(Debug)]
+pub struct LoadedSigner {
+    pub signer: Arc<dyn Signer>,
+    pub agent: Option<AgentRecord>,
+}
+
+/// 按优先级选择签名方式，只有都未配置时才读取明文 PRIVATE_KEY：
+/// - REMOTE_SIGNER：远程签名服务，`http://` 开头走 HTTP（需配置 SIGNER_TOKEN），否则视为
+///   Unix socket 路径
+/// - KEYSTORE_PATH：以太坊 JSON keystore，密码取 KEYSTORE_PASSWORD_FILE，未配置时在终端输入
+/// - AGENT_NAME：`agent` 命令创建的 agent
+pub async fn signer_from_env() -> Result<LoadedSigner> {
+    let signer: Arc<dyn Signer> = if let Ok(endpoint) = env::var("REMOTE_SIGNER") {
+        if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+            let token = env::var("SIGNER_TOKEN")
+                .map_err(|_| anyhow!("HTTP 签名服务需要配置 SIGNER_TOKEN"))?;
+            Arc::new(RemoteSigner::http(endpoint, token).await?)
+        } else {
+            Arc::new(RemoteSigner::unix(endpoint).await?)
+        }
+    } else if let Ok(path) = env::var("KEYSTORE_PATH") {
+        Arc::new(open_keystore(&path)?)
+    } else if let Ok(name) = env::var("AGENT_NAME") {
+        let (record, wallet) = AgentStore::from_env()?.load(&name)?;
+        return Ok(LoadedSigner {
+            signer: Arc::new(wallet),
+            agent: Some(record),
+        });
+    } else {
+        let wallet: LocalWallet = env::var("PRIVATE_KEY")
+            .map_err(|_| anyhow!("未配置 REMOTE_SIGNER、KEYSTORE_PATH、AGENT_NAME 或 PRIVATE_KEY"))?
+            .parse()?;
+        Arc::new(wallet)
+    };
+    Ok(LoadedSigner {
+        signer,
+        agent: None,
+    })
+}
+
+/// 解密 keystore，密码来源同 [`signer_from_env`]
+pub fn open_keystore(path: &str) -> Result<KeystoreSigner> {
+    let signer = match env::var("KEYSTORE_PASSWORD_FILE") {
+        Ok(password_file) => KeystoreSigner::open_with_password_file(path, password_file)?,
+        Err(_) => {
+            let password = rpassword::prompt_password(format!("{path} 的密码: "))?;
+            KeystoreSigner::open(path, password)?
+        }
+    };
+    Ok(signer)
+}
+
+#[derive(Clone)]
+struct SignerContext {
+    signer: Arc<dyn Signer>,
+    token: Arc<str>,
+}
+
+/// HTTP 签名服务，供 [`RemoteSigner::http`] 调用，所有请求需携带 `Authorization: Bearer <token>`
+pub fn http_signer(signer: Arc<dyn Signer>, token: String) -> Router {
+    let ctx = SignerContext {
+        signer,
+        token: token.into(),
+    };
+    Router::new()
+        .route("/", post(sign))
+        .layer(middleware::from_fn_with_state(ctx.clone(), authorize))
+        .with_state(ctx)
+}
+
+async fn authorize(
+    State(ctx): State<SignerContext>,
+    request: Request,
+    next: Next,
+) -> std::result::Result<Response, StatusCode> {
+    let authorized = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        // 常量时间比较，避免通过响应耗时逐字节猜出 token
+        .is_some_and(|token| bool::from(token.as_bytes().ct_eq(ctx.token.as_bytes())));
+    if !authorized {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(next.run(request).await)
+}
+
+async fn sign(
+    State(ctx): State<SignerContext>,
+    Json(request): Json<SignerRequest>,
+) -> Json<SignerResponse> {
+    Json(request.handle(&*ctx.signer).await)
+}
+
+/// 在只有当前用户可访问（0700）的目录中创建签名服务的 Unix socket。
+/// 服务会签任何请求的哈希，bind 到 chmod 之间其他用户也不能经由目录连上 socket
+pub fn bind_unix_signer(path: &Path) -> Result<UnixListener> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if !dir.exists() {
+        DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    }
+    let mode = fs::metadata(dir)?.permissions().mode();
+    if mode & 0o077 != 0 {
+        bail!(
+            "socket 所在目录 {} 的权限为 {:o}，需只允许当前用户访问 (chmod 700)",
+            dir.display(),
+            mode & 0o777
+        );
+    }
+    let _ = fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyperliquid_rust_sdk::MOCK_WALLET_KEY;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_http_signer_requires_token() -> Result<()> {
+        let wallet: LocalWallet = MOCK_WALLET_KEY.parse()?;
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let app = http_signer(Arc::new(wallet.clone()), "signer-token".to_string());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let remote = RemoteSigner::http(url.as_str(), "signer-token").await?;
+        assert_eq!(remote.address(), Signer::address(&wallet));
+        assert!(RemoteSigner::http(url.as_str(), "wrong-token")
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unix_signer_needs_private_dir() -> Result<()> {
+        let dir = env::temp_dir().join(format!("signer-{}", std::process::id()));
+        let path = dir.join("private").join("signer.sock");
+        let _listener = bind_unix_signer(&path)?;
+        assert_eq!(
+            fs::metadata(dir.join("private"))?.permissions().mode() & 0o777,
+            0o700
+        );
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755))?;
+        assert!(bind_unix_signer(&dir.join("signer.sock")).is_err());
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::Result;
+use ethers::types::H160;
+use hyperliquid_rust_sdk::TradeInfo;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    env,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    market::MarketCache,
+    notifier::{Notifier, NotifyEvent},
+    risk::{RiskBreach, RiskLimits},
+};
+
+// 最近决策最多保留的条数
+const MAX_RECENT_DECISIONS: usize = 500;
+
+/// 跟单开关，可在运行时通过控制接口修改
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CopyFlags {
+    pub enable_buy: bool,
+    pub enable_sell: bool,
+    pub enable_perps_buy: bool,
+    pub enable_perps_sell: bool,
+}
+
+impl CopyFlags {
+    pub fn from_env() -> Result<Self> {
+        Ok(CopyFlags {
+            enable_buy: env::var("ENABLE_BUY")?.parse()?,
+            enable_sell: env::var("ENABLE_SELL")?.parse()?,
+            enable_perps_buy: env::var("ENABLE_PERPS_BUY")?.parse()?,
+            enable_perps_sell: env::var("ENABLE_PERPS_SELL")?.parse()?,
+        })
+    }
+}
+
+/// 部分更新跟单开关，未提供的字段保持不变
+#[derive(Debug, Default, Deserialize)]
+pub struct CopyFlagsUpdate {
+    pub enable_buy: Option<bool>,
+    pub enable_sell: Option<bool>,
+    pub enable_perps_buy: Option<bool>,
+    pub enable_perps_sell: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum DecisionOutcome {
+    Copied { oid: u64, sz: f64, px: f64 },
+    Skipped { reason: String },
+    Failed { error: String },
+}
+
+/// 针对聪明钱一笔成交做出的跟单决策
+#[derive(Debug, Clone, Serialize)]
+pub struct Decision {
+    pub time: u64,
+    pub leader: H160,
+    pub tid: u64,
+    pub coin: String,
+    pub dir: String,
+    pub px: String,
+    pub sz: String,
+    #[serde(flatten)]
+    pub outcome: DecisionOutcome,
+}
+
+/// 账本中一个币种按跟单订单累计的预期仓位，多头和现货余额为正，空头为负
+#[derive(Debug, Clone, Serialize)]
+pub struct LedgerEntry {
+    pub coin: String,
+    pub position: f64,
+    pub copies: u64,
+    pub updated_at: u64,
+}
+
+impl LedgerEntry {
+    fn new(coin: &str) -> Self {
+        LedgerEntry {
+            coin: coin.to_string(),
+            position: 0.0,
+            copies: 0,
+            updated_at: 0,
+        }
+    }
+}
+
+// 一笔跟单对账本仓位的影响
+fn position_delta(dir: &str, sz: f64) -> Option<f64> {
+    match dir {
+        "Buy" | "Open Long" | "Close Short" => Some(sz),
+        "Sell" | "Close Long" | "Open Short" => Some(-sz),
+        _ => None,
+    }
+}
+
+/// 跟单机器人的共享运行状态
+#[derive(Debug)]
+pub struct BotState {
+    paused: AtomicBool,
+    paused_leaders: RwLock<HashSet<H160>>,
+    flags: RwLock<CopyFlags>,
+    decisions: Mutex<VecDeque<Decision>>,
+    ledger: Mutex<BTreeMap<String, LedgerEntry>>,
+    risk_limits: RwLock<RiskLimits>,
+    leverage: AtomicU32,
+    market: Arc<MarketCache>,
+    notifier: Notifier,
+}
+
+impl BotState {
+    pub fn new(flags: CopyFlags, notifier: Notifier) -> Self {
+        BotState {
+            paused: AtomicBool::new(false),
+            paused_leaders: RwLock::new(HashSet::new()),
+            flags: RwLock::new(flags),
+            decisions: Mutex::new(VecDeque::with_capacity(MAX_RECENT_DECISIONS)),
+            ledger: Mutex::new(BTreeMap::new()),
+            risk_limits: RwLock::new(RiskLimits::default()),
+            leverage: AtomicU32::new(1),
+            market: Arc::default(),
+            notifier,
+        }
+    }
+
+    pub fn notifier(&self) -> &Notifier {
+        &self.notifier
+    }
+
+    /// 行情缓存，由 main 调用 MarketCache::start 开始订阅
+    pub fn market(&self) -> &Arc<MarketCache> {
+        &self.market
+    }
+
+    /// 开仓前设置的杠杆倍数，main 按 LEVERAGE 设置
+    pub fn leverage(&self) -> u32 {
+        self.leverage.load(Ordering::Relaxed)
+    }
+
+    pub fn set_leverage(&self, leverage: u32) {
+        self.leverage.store(leverage, Ordering::Relaxed);
+    }
+
+    pub fn set_risk_limits(&self, limits: RiskLimits) {
+        *self.risk_limits.write().unwrap() = limits;
+    }
+
+    /// 按账本仓位检查跟单是否越过风控上限，越限时推送通知
+    pub fn check_risk(&self, leader: H160, trade: &TradeInfo) -> Option<RiskBreach> {
+        let position = self
+            .ledger
+            .lock()
+            .unwrap()
+            .get(&trade.coin)
+            .map_or(0.0, |entry| entry.position);
+        let breach = self
+            .risk_limits
+            .read()
+            .unwrap()
+            .check(leader, trade, position)?;
+        self.notifier
+            .notify(NotifyEvent::RiskBreach(breach.clone()));
+        Some(breach)
+    }
+
+    pub fn flags(&self) -> CopyFlags {
+        *self.flags.read().unwrap()
+    }
+
+    pub fn update_flags(&self, update: CopyFlagsUpdate) -> CopyFlags {
+        let mut flags = self.flags.write().unwrap();
+        if let Some(v) = update.enable_buy {
+            flags.enable_buy = v;
+        }
+        if let Some(v) = update.enable_sell {
+            flags.enable_sell = v;
+        }
+        if let Some(v) = update.enable_perps_buy {
+            flags.enable_perps_buy = v;
+        }
+        if let Some(v) = update.enable_perps_sell {
+            flags.enable_perps_sell = v;
+        }
+        *flags
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_leader_paused(&self, leader: &H160) -> bool {
+        self.is_paused() || self.paused_leaders.read().unwrap().contains(leader)
+    }
+
+    pub fn set_leader_paused(&self, leader: H160, paused: bool) {
+        let mut paused_leaders = self.paused_leaders.write().unwrap();
+        if paused {
+            paused_leaders.insert(leader);
+        } else {
+            paused_leaders.remove(&leader);
+        }
+    }
+
+    pub fn paused_leaders(&self) -> Vec<H160> {
+        self.paused_leaders
+            .read()
+            .unwrap()
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    pub fn record_decision(&self, decision: Decision) {
+        // 跳过的成交不推送通知，避免刷屏
+        if !matches!(decision.outcome, DecisionOutcome::Skipped { .. }) {
+            self.notifier
+                .notify(NotifyEvent::CopyDecision(decision.clone()));
+        }
+        if let DecisionOutcome::Copied { sz, .. } = decision.outcome {
+            if let Some(delta) = position_delta(&decision.dir, sz) {
+                let mut ledger = self.ledger.lock().unwrap();
+                let entry = ledger
+                    .entry(decision.coin.clone())
+                    .or_insert_with(|| LedgerEntry::new(&decision.coin));
+                entry.position += delta;
+                entry.copies += 1;
+                entry.updated_at = decision.time;
+            }
+        }
+        let mut decisions = self.decisions.lock().unwrap();
+        if decisions.len() == MAX_RECENT_DECISIONS {
+            decisions.pop_front();
+        }
+        decisions.push_back(decision);
+    }
+
+    /// 最近的决策，按时间倒序
+    pub fn recent_decisions(&self, limit: usize) -> Vec<Decision> {
+        let decisions = self.decisions.lock().unwrap();
+        decisions.iter().rev().take(limit).cloned().collect()
+    }
+
+    /// 账本中的全部币种，按币种名排序
+    pub fn ledger(&self) -> Vec<LedgerEntry> {
+        self.ledger.lock().unwrap().values().cloned().collect()
+    }
+
+    /// 对账后以交易所的实际仓位覆盖账本
+    pub fn set_ledger_position(&self, coin: &str, position: f64) {
+        let mut ledger = self.ledger.lock().unwrap();
+        let entry = ledger
+            .entry(coin.to_string())
+            .or_insert_with(|| LedgerEntry::new(coin));
+        entry.position = position;
+        entry.updated_at = now_ms();
+    }
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -1,10 +1,27 @@
 use anyhow::Result;
-use hyperliquid_rust_sdk::InfoClient;
+use hyperliquid_rust_sdk::{BaseUrl, InfoClient};
 use serde_json;
+use std::env;
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
 
+/// API_URL 指向代理或自建节点，签名网络仍由 TEST 决定；WS_URL 默认取 API_URL 下的 /ws
+pub fn network_from_env(is_test: bool) -> BaseUrl {
+    match env::var("API_URL") {
+        Ok(http) => match env::var("WS_URL") {
+            Ok(ws) => BaseUrl::Custom {
+                http,
+                ws,
+                is_mainnet: !is_test,
+            },
+            Err(_) => BaseUrl::custom(http, !is_test),
+        },
+        Err(_) if is_test => BaseUrl::Testnet,
+        Err(_) => BaseUrl::Mainnet,
+    }
+}
+
 pub async fn info_init(query_client_clone: Arc<InfoClient>) -> Result<()> {
     // 获取spot_meta数据
     let spot_meta = query_client_clone.spot_meta().await?;
@@ -35,6 +52,8 @@ pub async fn info_init(query_client_clone: Arc<InfoClient>) -> Result<()> {
 ///
 /// # 示例
 /// ```
+/// use hype_copy_trade::utils::format_adjust_price;
+///
 /// let adjusted = format_adjust_price("123.45", 1.05);
 /// assert_eq!(adjusted, 129.62);
 /// ```
@@ -47,14 +66,12 @@ pub fn format_adjust_price(original_price_str: &str, adjustment_factor: f64) ->
 
     // 获取原始价格的小数位数
     let decimal_places = if original_price_str.contains('.') {
-        original_price_str.split('.').last().unwrap().len() as u32
+        original_price_str.split('.').next_back().unwrap().len() as u32
     } else {
         0
     };
 
     // 调整价格精度，保持与原始价格相同的小数位数
-    let adjusted_price = (adjusted_price_raw * 10f64.powi(decimal_places as i32)).round()
-        / 10f64.powi(decimal_places as i32);
-
-    adjusted_price
+    (adjusted_price_raw * 10f64.powi(decimal_places as i32)).round()
+        / 10f64.powi(decimal_places as i32)
 }